@@ -0,0 +1,25 @@
+use anyhow::{Result, bail};
+use std::path::Path;
+use stead_core_runtime::doctor::diagnose;
+
+use crate::config;
+
+pub(crate) fn run_doctor(repo: Option<&Path>, json_out: bool) -> Result<()> {
+    let report = diagnose(config(), repo);
+    if json_out {
+        println!("{}", serde_json::to_string(&report)?);
+    } else {
+        for check in &report.checks {
+            let mark = format!("[{}]", check.status.as_str());
+            println!("{mark:<6} {}: {}", check.name, check.detail);
+            for problem in &check.problems {
+                println!("         {problem}");
+            }
+        }
+    }
+    let failures = report.failures();
+    if failures > 0 {
+        bail!("{failures} doctor checks failed");
+    }
+    Ok(())
+}
@@ -1,29 +1,73 @@
 use anyhow::{Context, Result, anyhow, bail};
-use chrono::{Datelike, Utc};
+use chrono::{Duration, Utc};
 use clap::{Parser, Subcommand, ValueEnum};
 use serde_json::{Value, json};
-use std::collections::{HashMap, HashSet};
-use std::hash::Hasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use twox_hash::XxHash64;
+use std::sync::OnceLock;
 use uuid::Uuid;
 
-use stead_session_adapters::NativeSessionRef;
+mod doctor;
+mod mcp;
+mod query;
+mod serve;
+
+use stead_core_runtime::discovery::{backend_home, discover_home};
+use stead_core_runtime::gc::DEFAULT_GC_AGE_DAYS;
+use stead_core_runtime::native::{get_native_ref, normalize_path};
+use stead_core_runtime::store::{canonical_lookup_matches, ensure_shared_session_uid, short_hash};
+use stead_core_runtime::{
+    Backend, Config, GcOptions, MergeStrategy, StorageProfile, Store, SyncOptions, SyncOutcome,
+    Workspace,
+};
+use stead_session_adapters::artifacts::{ArtifactKind, attach_artifacts};
+use stead_session_adapters::changes::{FileChange, render_git_patch, session_file_changes};
 use stead_session_adapters::claude::ClaudeAdapter;
 use stead_session_adapters::codex::CodexAdapter;
-use stead_session_model::{BackendKind, SteadSession};
+use stead_session_adapters::compact::{compact_session, rehydrate_session};
+use stead_session_adapters::redact::{RedactionConfig, Redactor};
+use stead_session_adapters::remap::{RootMapping, remap_session};
+use stead_session_adapters::sha256_hex;
+use stead_session_adapters::verify::{
+    ChangeStatus, change_status, commit_search_text, file_status,
+};
+use stead_session_model::diff::diff_sessions;
+use stead_session_model::jsonl::{is_header_line, read_session_jsonl, write_session_jsonl};
+use stead_session_model::{SessionArtifactRef, SteadSession, event_fingerprint};
+
+const BUNDLE_VERSION: u64 = 1;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CanonicalFormat {
+    Json,
+    Jsonl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BundleConflict {
+    Rename,
+    Skip,
+    Overwrite,
+}
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
-enum Backend {
-    Codex,
-    Claude,
+enum LineageFormat {
+    Text,
+    Dot,
+    Json,
 }
 
 #[derive(Debug, Parser)]
 #[command(name = "stead-core")]
 #[command(about = "Stead core session standard CLI")]
 struct Cli {
+    #[arg(long, global = true)]
+    compress: bool,
+    #[arg(long, global = true)]
+    jsonl: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -38,21 +82,27 @@ enum Commands {
         #[arg(long = "from", value_enum)]
         from: Backend,
         #[arg(long)]
-        base_dir: PathBuf,
+        base_dir: Option<PathBuf>,
         #[arg(long)]
         session: String,
         #[arg(long)]
         out: PathBuf,
+        #[arg(long, value_enum, default_value = "json")]
+        format: CanonicalFormat,
     },
     Export {
         #[arg(long = "to", value_enum)]
         to: Backend,
         #[arg(long)]
-        base_dir: PathBuf,
+        base_dir: Option<PathBuf>,
         #[arg(long = "in", alias = "input")]
         input: PathBuf,
         #[arg(long)]
         out: PathBuf,
+        #[arg(long)]
+        redact: bool,
+        #[arg(long)]
+        redaction_config: Option<PathBuf>,
     },
     Convert {
         #[arg(long = "from", value_enum)]
@@ -60,21 +110,41 @@ enum Commands {
         #[arg(long = "to", value_enum)]
         to: Backend,
         #[arg(long)]
-        source_base: PathBuf,
+        source_base: Option<PathBuf>,
         #[arg(long)]
-        target_base: PathBuf,
+        target_base: Option<PathBuf>,
         #[arg(long)]
         session: String,
         #[arg(long)]
         out: PathBuf,
+        #[arg(long)]
+        redact: bool,
+        #[arg(long)]
+        redaction_config: Option<PathBuf>,
+        #[arg(long = "map-root", value_name = "FROM=TO")]
+        map_root: Vec<RootMapping>,
     },
     Sync {
         #[arg(long)]
         repo: PathBuf,
         #[arg(long)]
-        codex_base: PathBuf,
+        codex_base: Option<PathBuf>,
+        #[arg(long)]
+        claude_base: Option<PathBuf>,
+        #[arg(long, value_enum)]
+        merge_strategy: Option<MergeStrategy>,
+        #[arg(long, value_enum)]
+        storage_profile: Option<StorageProfile>,
+        #[arg(long)]
+        redact: bool,
+        /// Skip redaction even when the configuration enables it.
+        #[arg(long, conflicts_with_all = ["redact", "redaction_config"])]
+        no_redact: bool,
+        #[arg(long)]
+        redaction_config: Option<PathBuf>,
+        /// Undo the most recent sync recorded in `.stead-core/journal/` instead of syncing.
         #[arg(long)]
-        claude_base: PathBuf,
+        rollback: bool,
         #[arg(long)]
         json: bool,
     },
@@ -86,9 +156,11 @@ enum Commands {
         #[arg(long = "to", value_enum)]
         to: Backend,
         #[arg(long)]
-        base_dir: PathBuf,
+        base_dir: Option<PathBuf>,
         #[arg(long)]
         out: Option<PathBuf>,
+        #[arg(long = "map-root", value_name = "FROM=TO")]
+        map_root: Vec<RootMapping>,
         #[arg(long)]
         json: bool,
     },
@@ -124,6 +196,171 @@ enum Commands {
         #[arg(long)]
         json: bool,
     },
+    Changes {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long)]
+        session: String,
+        #[arg(long)]
+        patch: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
+    Verify {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long)]
+        session: String,
+        #[arg(long)]
+        json: bool,
+    },
+    Fork {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long)]
+        session: String,
+        #[arg(long)]
+        at: String,
+        #[arg(long = "to", value_enum)]
+        to: Option<Backend>,
+        #[arg(long)]
+        base_dir: Option<PathBuf>,
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
+    Diff {
+        #[arg(long)]
+        repo: PathBuf,
+        left: String,
+        #[arg(required_unless_present = "native")]
+        right: Option<String>,
+        #[arg(long, value_enum, conflicts_with = "right")]
+        native: Option<Backend>,
+        #[arg(long)]
+        json: bool,
+        #[arg(long)]
+        exit_code: bool,
+    },
+    Lineage {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long)]
+        session: String,
+        #[arg(long, value_enum, default_value = "text")]
+        format: LineageFormat,
+    },
+    Redact {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long)]
+        session: Option<String>,
+        #[arg(long)]
+        config: Option<PathBuf>,
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long)]
+        json: bool,
+    },
+    Compact {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long)]
+        session: Option<String>,
+        #[arg(long)]
+        rehydrate: bool,
+        #[arg(long)]
+        json: bool,
+    },
+    Gc {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long, value_name = "DAYS", default_value_t = DEFAULT_GC_AGE_DAYS)]
+        older_than: i64,
+        #[arg(long)]
+        codex_base: Option<PathBuf>,
+        #[arg(long)]
+        claude_base: Option<PathBuf>,
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long)]
+        json: bool,
+    },
+    Archive {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long)]
+        session: String,
+        #[arg(long)]
+        archive_dir: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
+    Restore {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long)]
+        session: String,
+        #[arg(long)]
+        codex_base: Option<PathBuf>,
+        #[arg(long)]
+        claude_base: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
+    Serve {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long, default_value_t = 8787)]
+        port: u16,
+        #[arg(long)]
+        allow_writes: bool,
+    },
+    Mcp {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long)]
+        codex_base: Option<PathBuf>,
+        #[arg(long)]
+        claude_base: Option<PathBuf>,
+    },
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    Doctor {
+        #[arg(long)]
+        repo: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
+    Bundle {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long)]
+        session: String,
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long)]
+        redaction_config: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
+    Unbundle {
+        #[arg(long)]
+        repo: PathBuf,
+        #[arg(long = "in", alias = "input")]
+        input: PathBuf,
+        #[arg(long)]
+        project_root: Option<PathBuf>,
+        #[arg(long, value_enum, default_value = "rename")]
+        on_conflict: BundleConflict,
+        #[arg(long = "map-root", value_name = "FROM=TO")]
+        map_root: Vec<RootMapping>,
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -132,39 +369,83 @@ enum SessionCommands {
         #[arg(long, value_enum)]
         backend: Backend,
         #[arg(long)]
-        base_dir: PathBuf,
+        base_dir: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommands {
+    /// Print the effective configuration and the layers it was read from.
+    Show {
+        #[arg(long)]
+        repo: Option<PathBuf>,
         #[arg(long)]
         json: bool,
     },
 }
 
-#[derive(Debug, Clone)]
-struct StoredCanonical {
-    session: SteadSession,
+/// Rewrites applied to a session before it is written to a native backend.
+struct SessionTransforms {
+    redactor: Option<Redactor>,
+    root_map: Vec<RootMapping>,
+}
+
+impl SessionTransforms {
+    fn apply(&self, session: &mut SteadSession) {
+        if let Some(redactor) = self.redactor.as_ref() {
+            redactor.redact_session(session);
+        }
+        remap_session(session, &self.root_map);
+    }
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let mut loaded = Config::load(config_repo(&cli.command).as_deref())?;
+    if cli.compress {
+        loaded.store.compress = Some(true);
+    }
+    if cli.jsonl {
+        loaded.store.jsonl = Some(true);
+    }
+    let _ = CONFIG.set(loaded);
     match cli.command {
         Commands::Sessions { command } => match command {
             SessionCommands::List {
                 backend,
                 base_dir,
                 json,
-            } => run_list(backend, base_dir, json),
+            } => run_list(backend, resolve_base_dir(backend, base_dir)?, json),
         },
         Commands::Import {
             from,
             base_dir,
             session,
             out,
-        } => run_import(from, base_dir, &session, out),
+            format,
+        } => run_import(
+            from,
+            resolve_base_dir(from, base_dir)?,
+            &session,
+            out,
+            format,
+        ),
         Commands::Export {
             to,
             base_dir,
             input,
             out,
-        } => run_export(to, base_dir, input, out),
+            redact,
+            redaction_config,
+        } => run_export(
+            to,
+            resolve_base_dir(to, base_dir)?,
+            input,
+            out,
+            load_redactor(redact, redaction_config.as_deref(), None)?,
+        ),
         Commands::Convert {
             from,
             to,
@@ -172,21 +453,66 @@ fn main() -> Result<()> {
             target_base,
             session,
             out,
-        } => run_convert(from, to, source_base, target_base, &session, out),
+            redact,
+            redaction_config,
+            map_root,
+        } => run_convert(
+            from,
+            to,
+            resolve_base_dir(from, source_base)?,
+            resolve_base_dir(to, target_base)?,
+            &session,
+            out,
+            SessionTransforms {
+                redactor: load_redactor(redact, redaction_config.as_deref(), None)?,
+                root_map: map_root,
+            },
+        ),
         Commands::Sync {
             repo,
             codex_base,
             claude_base,
+            merge_strategy,
+            storage_profile,
+            redact,
+            no_redact,
+            redaction_config,
+            rollback,
             json,
-        } => run_sync(repo, codex_base, claude_base, json),
+        } => {
+            if rollback {
+                return run_sync_rollback(repo, json);
+            }
+            let options = SyncOptions {
+                codex_base: resolve_base_dir(Backend::Codex, codex_base)?,
+                claude_base: resolve_base_dir(Backend::Claude, claude_base)?,
+                strategy: merge_strategy.unwrap_or(config().merge_strategy()),
+                profile: storage_profile.unwrap_or(config().storage_profile()),
+                redactor: load_redactor(
+                    redact || (!no_redact && config().redact()),
+                    redaction_config.as_deref(),
+                    Some(&repo),
+                )?,
+            };
+            run_sync(repo, &options, json)
+        }
         Commands::Materialize {
             repo,
             session,
             to,
             base_dir,
             out,
+            map_root,
+            json,
+        } => run_materialize(
+            repo,
+            &session,
+            to,
+            resolve_base_dir(to, base_dir)?,
+            out,
+            &map_root,
             json,
-        } => run_materialize(repo, &session, to, base_dir, out, json),
+        ),
         Commands::Resume {
             repo,
             session,
@@ -195,7 +521,15 @@ fn main() -> Result<()> {
             base_dir,
             out,
             json,
-        } => run_resume(repo, &session, backend, &prompt, base_dir, out, json),
+        } => run_resume(
+            repo,
+            &session,
+            backend,
+            &prompt,
+            configured_base_dir(backend, base_dir),
+            out,
+            json,
+        ),
         Commands::Handoff {
             repo,
             session,
@@ -204,7 +538,133 @@ fn main() -> Result<()> {
             base_dir,
             out,
             json,
-        } => run_handoff(repo, &session, to, &resume_prompt, base_dir, out, json),
+        } => run_handoff(
+            repo,
+            &session,
+            to,
+            &resume_prompt,
+            configured_base_dir(to, base_dir),
+            out,
+            json,
+        ),
+        Commands::Changes {
+            repo,
+            session,
+            patch,
+            json,
+        } => run_changes(repo, &session, patch, json),
+        Commands::Verify {
+            repo,
+            session,
+            json,
+        } => run_verify(repo, &session, json),
+        Commands::Fork {
+            repo,
+            session,
+            at,
+            to,
+            base_dir,
+            out,
+            json,
+        } => run_fork(repo, &session, &at, to, base_dir, out, json),
+        Commands::Lineage {
+            repo,
+            session,
+            format,
+        } => run_lineage(repo, &session, format),
+        Commands::Diff {
+            repo,
+            left,
+            right,
+            native,
+            json,
+            exit_code,
+        } => run_diff(repo, &left, right.as_deref(), native, json, exit_code),
+        Commands::Redact {
+            repo,
+            session,
+            config,
+            dry_run,
+            json,
+        } => run_redact(repo, session.as_deref(), config, dry_run, json),
+        Commands::Compact {
+            repo,
+            session,
+            rehydrate,
+            json,
+        } => run_compact(repo, session.as_deref(), rehydrate, json),
+        Commands::Gc {
+            repo,
+            older_than,
+            codex_base,
+            claude_base,
+            dry_run,
+            json,
+        } => run_gc(
+            repo,
+            &GcOptions {
+                older_than: Duration::days(older_than),
+                codex_base: configured_base_dir(Backend::Codex, codex_base),
+                claude_base: configured_base_dir(Backend::Claude, claude_base),
+                dry_run,
+            },
+            json,
+        ),
+        Commands::Archive {
+            repo,
+            session,
+            archive_dir,
+            json,
+        } => run_archive(repo, &session, archive_dir.as_deref(), json),
+        Commands::Restore {
+            repo,
+            session,
+            codex_base,
+            claude_base,
+            json,
+        } => run_restore(
+            repo,
+            &session,
+            codex_base.as_deref(),
+            claude_base.as_deref(),
+            json,
+        ),
+        Commands::Serve {
+            repo,
+            port,
+            allow_writes,
+        } => serve::run_serve(repo, port, allow_writes),
+        Commands::Mcp {
+            repo,
+            codex_base,
+            claude_base,
+        } => mcp::run_mcp(mcp::McpOptions {
+            repo,
+            codex_base: configured_base_dir(Backend::Codex, codex_base),
+            claude_base: configured_base_dir(Backend::Claude, claude_base),
+        }),
+        Commands::Config { command } => match command {
+            ConfigCommands::Show { json, .. } => run_config_show(json),
+        },
+        Commands::Doctor { repo, json } => {
+            let repo = repo.or_else(|| std::env::current_dir().ok());
+            doctor::run_doctor(repo.as_deref(), json)
+        }
+        Commands::Bundle {
+            repo,
+            session,
+            out,
+            redaction_config,
+            json,
+        } => run_bundle(repo, &session, out, redaction_config.as_deref(), json),
+        Commands::Unbundle {
+            repo,
+            input,
+            project_root,
+            on_conflict,
+            map_root,
+            json,
+        } => run_unbundle(repo, input, project_root, on_conflict, &map_root, json),
     }
 }
 
@@ -230,13 +690,29 @@ fn run_list(backend: Backend, base_dir: PathBuf, json: bool) -> Result<()> {
     Ok(())
 }
 
-fn run_import(from: Backend, base_dir: PathBuf, session: &str, out: PathBuf) -> Result<()> {
-    let imported = match from {
+fn run_import(
+    from: Backend,
+    base_dir: PathBuf,
+    session: &str,
+    out: PathBuf,
+    format: CanonicalFormat,
+) -> Result<()> {
+    let mut imported = match from {
         Backend::Codex => CodexAdapter::from_base_dir(base_dir).import_session(session)?,
         Backend::Claude => ClaudeAdapter::from_base_dir(base_dir).import_session(session)?,
     };
-    let serialized =
-        serde_json::to_string_pretty(&imported).context("failed to serialize canonical session")?;
+    attach_artifacts(&mut imported);
+    let serialized = match format {
+        CanonicalFormat::Json => {
+            serde_json::to_vec_pretty(&imported).context("failed to serialize canonical session")?
+        }
+        CanonicalFormat::Jsonl => {
+            let mut buf = Vec::new();
+            write_session_jsonl(&imported, &mut buf)
+                .context("failed to serialize canonical session as JSONL")?;
+            buf
+        }
+    };
     let parent = out
         .parent()
         .with_context(|| format!("invalid output path: {}", out.display()))?;
@@ -249,11 +725,17 @@ fn run_import(from: Backend, base_dir: PathBuf, session: &str, out: PathBuf) ->
     Ok(())
 }
 
-fn run_export(to: Backend, base_dir: PathBuf, input: PathBuf, out: PathBuf) -> Result<()> {
-    let raw = std::fs::read_to_string(&input)
-        .with_context(|| format!("failed to read canonical input {}", input.display()))?;
-    let session: SteadSession = serde_json::from_str(&raw)
-        .with_context(|| format!("invalid canonical JSON in {}", input.display()))?;
+fn run_export(
+    to: Backend,
+    base_dir: PathBuf,
+    input: PathBuf,
+    out: PathBuf,
+    redactor: Option<Redactor>,
+) -> Result<()> {
+    let mut session = read_canonical_input(&input)?;
+    if let Some(redactor) = redactor.as_ref() {
+        redactor.redact_session(&mut session);
+    }
     let parent = out
         .parent()
         .with_context(|| format!("invalid output path: {}", out.display()))?;
@@ -272,6 +754,24 @@ fn run_export(to: Backend, base_dir: PathBuf, input: PathBuf, out: PathBuf) -> R
     Ok(())
 }
 
+/// Reads a canonical session written as a single JSON document or as a JSONL event stream.
+fn read_canonical_input(input: &Path) -> Result<SteadSession> {
+    let raw = std::fs::read_to_string(input)
+        .with_context(|| format!("failed to read canonical input {}", input.display()))?;
+    let is_jsonl = raw
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .and_then(|line| serde_json::from_str::<Value>(line).ok())
+        .is_some_and(|first| is_header_line(&first));
+    if is_jsonl {
+        read_session_jsonl(raw.as_bytes())
+            .with_context(|| format!("invalid canonical JSONL in {}", input.display()))
+    } else {
+        serde_json::from_str(&raw)
+            .with_context(|| format!("invalid canonical JSON in {}", input.display()))
+    }
+}
+
 fn run_convert(
     from: Backend,
     to: Backend,
@@ -279,11 +779,13 @@ fn run_convert(
     target_base: PathBuf,
     session: &str,
     out: PathBuf,
+    transforms: SessionTransforms,
 ) -> Result<()> {
-    let imported = match from {
+    let mut imported = match from {
         Backend::Codex => CodexAdapter::from_base_dir(source_base).import_session(session)?,
         Backend::Claude => ClaudeAdapter::from_base_dir(source_base).import_session(session)?,
     };
+    transforms.apply(&mut imported);
     let parent = out
         .parent()
         .with_context(|| format!("invalid output path: {}", out.display()))?;
@@ -302,116 +804,124 @@ fn run_convert(
     Ok(())
 }
 
-fn run_sync(
-    repo: PathBuf,
-    codex_base: PathBuf,
-    claude_base: PathBuf,
-    json_out: bool,
-) -> Result<()> {
-    std::fs::create_dir_all(canonical_store_dir(&repo))?;
-    let mut stored = load_all_canonical_sessions(&repo)?;
-    let mut imported = Vec::new();
-
-    let codex = CodexAdapter::from_base_dir(codex_base);
-    let codex_sessions = codex.list_sessions()?;
-    for native in scope_sessions_to_repo(&repo, codex_sessions) {
-        let session = codex.import_from_file(&native.file_path)?;
-        let (canonical_uid, stored_path) = upsert_synced_session(
-            &repo,
-            &mut stored,
-            session,
-            Backend::Codex,
-            &native.native_id,
-            &native.file_path,
-        )?;
-        imported.push(json!({
-            "backend": "codex",
-            "native_id": native.native_id,
-            "session_uid": canonical_uid,
-            "stored_at": stored_path
-        }));
-    }
-
-    let claude = ClaudeAdapter::from_base_dir(claude_base);
-    let claude_sessions = claude.list_sessions()?;
-    for native in scope_sessions_to_repo(&repo, claude_sessions) {
-        let session = claude.import_session(&native.native_id)?;
-        let (canonical_uid, stored_path) = upsert_synced_session(
-            &repo,
-            &mut stored,
-            session,
-            Backend::Claude,
-            &native.native_id,
-            &native.file_path,
-        )?;
-        imported.push(json!({
-            "backend": "claude",
-            "native_id": native.native_id,
-            "session_uid": canonical_uid,
-            "stored_at": stored_path
-        }));
-    }
-
+fn run_sync(repo: PathBuf, options: &SyncOptions, json_out: bool) -> Result<()> {
+    let workspace = open_workspace(&repo);
+    let entries = workspace.sync(options)?;
+    let failed = entries
+        .iter()
+        .filter(|entry| entry.outcome == SyncOutcome::Failed)
+        .count();
     if json_out {
-        println!("{}", serde_json::to_string(&imported)?);
+        println!("{}", serde_json::to_string(&entries)?);
     } else {
+        for entry in &entries {
+            match entry.outcome {
+                SyncOutcome::Failed => println!(
+                    "failed {} {}: {}",
+                    entry.backend.as_str(),
+                    entry.native_id,
+                    entry.error.as_deref().unwrap_or("unknown error")
+                ),
+                outcome => println!(
+                    "{} {} {} -> {}",
+                    outcome.as_str(),
+                    entry.backend.as_str(),
+                    entry.native_id,
+                    entry.session_uid
+                ),
+            }
+            if let Some(branch) = entry.branch_session_uid.as_deref() {
+                println!(
+                    "{} {} diverged; branched into {}",
+                    entry.backend.as_str(),
+                    entry.native_id,
+                    branch
+                );
+                if let Some(sibling) = entry.sibling_session_uid.as_deref() {
+                    println!("  other projection moved to sibling {sibling}");
+                }
+                if let Some(strategy) = entry
+                    .conflicts
+                    .iter()
+                    .find_map(|conflict| conflict.get("overrides_strategy")?.as_str())
+                {
+                    println!(
+                        "  handoff timelines are always split; --merge-strategy {} was not applied",
+                        strategy.replace('_', "-")
+                    );
+                }
+            } else if !entry.conflicts.is_empty() {
+                println!(
+                    "{} {} merged into {} with {} conflicting events",
+                    entry.backend.as_str(),
+                    entry.native_id,
+                    entry.session_uid,
+                    entry.conflicts.len()
+                );
+            }
+        }
         println!(
             "synced {} sessions into {}",
-            imported.len(),
-            canonical_store_dir(&repo).display()
+            entries.len() - failed,
+            workspace.store().sessions_dir().display()
         );
     }
+    if failed > 0 {
+        bail!("{failed} of {} sessions failed to sync", entries.len());
+    }
     Ok(())
 }
 
-fn run_materialize(
-    repo: PathBuf,
+fn run_sync_rollback(repo: PathBuf, json_out: bool) -> Result<()> {
+    let rolled_back = open_workspace(&repo).rollback_sync()?;
+    if json_out {
+        println!("{}", serde_json::to_string(&rolled_back)?);
+        return Ok(());
+    }
+    for path in &rolled_back.restored {
+        println!("restored {}", path.display());
+    }
+    for path in &rolled_back.removed {
+        println!("removed {}", path.display());
+    }
+    println!(
+        "rolled back sync {} started at {}",
+        rolled_back.id,
+        rolled_back.started_at.to_rfc3339()
+    );
+    Ok(())
+}
+
+fn run_materialize(
+    repo: PathBuf,
     session_uid: &str,
     to: Backend,
     base_dir: PathBuf,
     out: Option<PathBuf>,
+    mappings: &[RootMapping],
     json_out: bool,
 ) -> Result<()> {
-    let mut session = load_canonical_session(&repo, session_uid)?;
-    ensure_shared_session_uid(&mut session);
-    let native_id = choose_native_id(&session, to);
-    let output_path =
-        out.unwrap_or_else(|| default_materialized_path(&base_dir, &repo, to, &native_id));
-    if let Some(parent) = output_path.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
-    if matches!(to, Backend::Codex) {
-        prune_codex_rollouts_for_native_id(&base_dir, &native_id, &output_path)?;
-    }
-
-    let mut export_session = session.clone();
-    export_session.source.original_session_id = native_id.clone();
-
-    let report =
-        match to {
-            Backend::Codex => CodexAdapter::from_base_dir(base_dir)
-                .export_session(&export_session, &output_path)?,
-            Backend::Claude => ClaudeAdapter::from_base_dir(base_dir)
-                .export_session(&export_session, &output_path)?,
-        };
-
-    set_native_ref(&mut session, to, &native_id, &output_path);
-    ensure_shared_session_uid(&mut session);
-    store_canonical_session(&repo, &session)?;
+    let workspace = open_workspace(&repo);
+    let mut session = workspace.store().load(session_uid)?;
+    let materialized = workspace.materialize(&mut session, to, &base_dir, out, mappings)?;
 
     if json_out {
         println!(
             "{}",
             serde_json::to_string(&json!({
                 "session_uid": session_uid,
-                "backend": backend_key(to),
-                "native_id": native_id,
-                "output_path": output_path,
-                "events_exported": report.events_exported
+                "backend": to.as_str(),
+                "native_id": materialized.native_id,
+                "output_path": materialized.output_path,
+                "events_exported": materialized.report.events_exported
             }))?
         );
     } else {
-        println!("materialized {} -> {}", session_uid, output_path.display());
+        println!(
+            "materialized {} -> {}",
+            session_uid,
+            materialized.output_path.display()
+        );
     }
     Ok(())
 }
@@ -425,94 +935,29 @@ fn run_resume(
     out: Option<PathBuf>,
     json_out: bool,
 ) -> Result<()> {
-    let mut session = load_canonical_session(&repo, session_uid)?;
-    let mut changed = ensure_shared_session_uid(&mut session);
-
-    let (native_id, native_path) = if let Some(found) = get_native_ref(&session, backend) {
-        found
-    } else {
-        let Some(base_dir) = base_dir else {
-            bail!(
-                "missing native projection for backend `{}`; provide --base-dir to materialize",
-                backend_key(backend)
-            );
-        };
-        let native_id = choose_native_id(&session, backend);
-        let output_path =
-            out.unwrap_or_else(|| default_materialized_path(&base_dir, &repo, backend, &native_id));
-        if let Some(parent) = output_path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        if matches!(backend, Backend::Codex) {
-            prune_codex_rollouts_for_native_id(&base_dir, &native_id, &output_path)?;
-        }
-
-        let mut export_session = session.clone();
-        export_session.source.original_session_id = native_id.clone();
-        match backend {
-            Backend::Codex => {
-                CodexAdapter::from_base_dir(base_dir)
-                    .export_session(&export_session, &output_path)?;
-            }
-            Backend::Claude => {
-                ClaudeAdapter::from_base_dir(base_dir)
-                    .export_session(&export_session, &output_path)?;
-            }
-        }
-        set_native_ref(&mut session, backend, &native_id, &output_path);
-        changed = true;
-        (native_id, output_path)
-    };
-
-    let status = if let Ok(runner) = std::env::var("STEAD_CORE_RUNNER") {
-        Command::new(runner)
-            .args([backend_key(backend), "--resume", &native_id, prompt])
-            .status()?
-    } else {
-        let bin = match backend {
-            Backend::Codex => {
-                std::env::var("STEAD_CORE_CODEX_BIN").unwrap_or_else(|_| "codex".to_string())
-            }
-            Backend::Claude => {
-                std::env::var("STEAD_CORE_CLAUDE_BIN").unwrap_or_else(|_| "claude".to_string())
-            }
-        };
-        let mut command = Command::new(bin);
-        command.current_dir(&repo);
-        match backend {
-            Backend::Codex => {
-                command.args(["exec", "resume", &native_id, prompt]);
-            }
-            Backend::Claude => {
-                command.args(["-p", "-r", &native_id, prompt]);
-            }
-        }
-        command.status()?
-    };
-
-    if !status.success() {
-        bail!("resume command failed for backend {}", backend_key(backend));
-    }
+    let handoff = open_workspace(&repo).resume(
+        session_uid,
+        backend,
+        prompt,
+        base_dir.as_deref(),
+        out,
+        &config().resume_launcher(),
+    )?;
 
     if json_out {
         println!(
             "{}",
             serde_json::to_string(&json!({
                 "session_uid": session_uid,
-                "backend": backend_key(backend),
-                "native_id": native_id,
-                "native_path": native_path,
+                "backend": backend.as_str(),
+                "native_id": handoff.native_id,
+                "native_path": handoff.native_path,
                 "status": "ok"
             }))?
         );
     } else {
-        println!("resumed {} on {}", session_uid, backend_key(backend));
-    }
-
-    if changed {
-        store_canonical_session(&repo, &session)?;
+        println!("resumed {} on {}", session_uid, backend.as_str());
     }
-
     Ok(())
 }
 
@@ -536,463 +981,1192 @@ fn run_handoff(
     )
 }
 
-fn upsert_synced_session(
-    repo: &Path,
-    stored: &mut Vec<StoredCanonical>,
-    mut imported: SteadSession,
-    backend: Backend,
-    native_id: &str,
-    native_path: &Path,
-) -> Result<(String, PathBuf)> {
-    ensure_shared_session_uid(&mut imported);
-    set_native_ref(&mut imported, backend, native_id, native_path);
-
-    let target_index = stored.iter().position(|existing| {
-        existing
-            .session
-            .extensions
-            .get("native_refs")
-            .and_then(|v| v.as_object())
-            .and_then(|refs| refs.get(backend_key(backend)))
-            .and_then(|v| v.as_object())
-            .and_then(|obj| obj.get("session_id"))
-            .and_then(|v| v.as_str())
-            .is_some_and(|id| id == native_id)
-    });
-
-    let target_index = target_index.or_else(|| {
-        stored
-            .iter()
-            .position(|existing| existing.session.session_uid == imported.session_uid)
-    });
+fn run_fork(
+    repo: PathBuf,
+    session_uid: &str,
+    at: &str,
+    to: Option<Backend>,
+    base_dir: Option<PathBuf>,
+    out: Option<PathBuf>,
+    json_out: bool,
+) -> Result<()> {
+    let target = to
+        .map(|to| resolve_base_dir(to, base_dir).map(|base_dir| (to, base_dir)))
+        .transpose()?;
+    let _lock = open_store(&repo).lock()?;
+    let parent = open_store(&repo).load(session_uid)?;
+    let mut fork = parent.fork_at(at, &Uuid::new_v4().to_string())?;
+    attach_artifacts(&mut fork);
+    ensure_shared_session_uid(&mut fork);
+    let canonical_path = open_store(&repo).save(&fork)?;
+
+    let materialized = match target {
+        Some((to, base_dir)) => {
+            let materialized =
+                open_workspace(&repo).materialize(&mut fork, to, &base_dir, out, &[])?;
+            Some(json!({
+                "backend": to.as_str(),
+                "native_id": materialized.native_id,
+                "output_path": materialized.output_path
+            }))
+        }
+        None => None,
+    };
+    let lineage = fork.lineage.clone().unwrap_or_default();
 
-    let target_index = target_index.or_else(|| {
-        let imported_shared = imported
-            .shared_session_uid
-            .clone()
-            .unwrap_or_else(|| imported.session_uid.clone());
-        stored.iter().position(|existing| {
-            existing
-                .session
-                .shared_session_uid
-                .as_deref()
-                .unwrap_or(existing.session.session_uid.as_str())
-                == imported_shared
-        })
-    });
+    if json_out {
+        println!(
+            "{}",
+            serde_json::to_string(&json!({
+                "session_uid": fork.session_uid,
+                "parent_session_uid": lineage.parent_session_uid,
+                "root_session_uid": lineage.root_session_uid,
+                "fork_origin_event_uid": lineage.fork_origin_event_uid,
+                "events": fork.events.len(),
+                "canonical_path": canonical_path,
+                "materialized": materialized
+            }))?
+        );
+    } else {
+        println!(
+            "forked {} at {} -> {}",
+            parent.session_uid,
+            lineage.fork_origin_event_uid.unwrap_or_default(),
+            fork.session_uid
+        );
+        if let Some(output_path) = materialized
+            .as_ref()
+            .and_then(|value| value["output_path"].as_str())
+        {
+            println!("materialized {} -> {}", fork.session_uid, output_path);
+        }
+    }
+    Ok(())
+}
 
-    let target_index = target_index.or_else(|| {
-        stored.iter().position(|existing| {
-            session_uid_aliases(&existing.session)
-                .iter()
-                .any(|alias| alias == &imported.session_uid)
-        })
-    });
+fn run_diff(
+    repo: PathBuf,
+    left_uid: &str,
+    right_uid: Option<&str>,
+    native: Option<Backend>,
+    json_out: bool,
+    exit_code: bool,
+) -> Result<()> {
+    let left = open_store(&repo).load(left_uid)?;
+    let right = match (right_uid, native) {
+        (Some(right_uid), _) => open_store(&repo).load(right_uid)?,
+        (None, Some(backend)) => import_native_projection(&left, backend)?,
+        (None, None) => bail!("provide a second session uid or --native <backend>"),
+    };
+    let diff = diff_sessions(&left, &right);
 
-    if let Some(index) = target_index {
-        let mut merged = merge_sessions(stored[index].session.clone(), imported);
-        set_native_ref(&mut merged, backend, native_id, native_path);
-        ensure_shared_session_uid(&mut merged);
-        let stored_path = store_canonical_session(repo, &merged)?;
-        stored[index] = StoredCanonical {
-            session: merged.clone(),
-        };
-        return Ok((merged.session_uid, stored_path));
+    if json_out {
+        println!(
+            "{}",
+            serde_json::to_string(&json!({
+                "left": left.session_uid,
+                "right": right.session_uid,
+                "identical": diff.is_empty(),
+                "diff": diff,
+            }))?
+        );
+    } else if diff.is_empty() {
+        println!("sessions are identical");
+    } else {
+        for field in &diff.metadata {
+            println!("* {}: {} -> {}", field.field, field.left, field.right);
+        }
+        for event in &diff.removed {
+            println!("- #{} {} {:?}", event.index, event.event_uid, event.kind);
+        }
+        for event in &diff.added {
+            println!("+ #{} {} {:?}", event.index, event.event_uid, event.kind);
+        }
+        for event in &diff.modified {
+            let fields: Vec<&str> = event.fields.iter().map(|f| f.field.as_str()).collect();
+            println!(
+                "~ #{} {} -> #{} {} ({})",
+                event.left_index,
+                event.left_uid,
+                event.right_index,
+                event.right_uid,
+                fields.join(", ")
+            );
+        }
+        for event in &diff.reordered {
+            println!(
+                "> {} moved #{} -> #{}",
+                event.event_uid, event.left_index, event.right_index
+            );
+        }
     }
 
-    let stored_path = store_canonical_session(repo, &imported)?;
-    stored.push(StoredCanonical {
-        session: imported.clone(),
-    });
-    Ok((imported.session_uid, stored_path))
+    if exit_code && !diff.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
 }
 
-fn merge_sessions(mut anchor: SteadSession, incoming: SteadSession) -> SteadSession {
-    let anchor_shared = anchor
-        .shared_session_uid
-        .clone()
-        .unwrap_or_else(|| anchor.session_uid.clone());
-    anchor.shared_session_uid = Some(anchor_shared);
+fn import_native_projection(session: &SteadSession, backend: Backend) -> Result<SteadSession> {
+    let (native_id, native_path) = get_native_ref(session, backend).ok_or_else(|| {
+        anyhow!(
+            "session {} has no {} projection",
+            session.session_uid,
+            backend.as_str()
+        )
+    })?;
+    let mut imported = match backend {
+        Backend::Codex => {
+            CodexAdapter::from_base_dir(native_path.parent().unwrap_or(Path::new(".")))
+                .import_from_file(&native_path)?
+        }
+        Backend::Claude => {
+            let base_dir = native_path
+                .ancestors()
+                .find(|dir| dir.file_name().is_some_and(|name| name == "projects"))
+                .and_then(Path::parent);
+            match base_dir {
+                Some(base_dir) => {
+                    ClaudeAdapter::from_base_dir(base_dir).import_session(&native_id)?
+                }
+                None => {
+                    ClaudeAdapter::from_base_dir(native_path.parent().unwrap_or(Path::new(".")))
+                        .import_from_file(&native_path, "main")?
+                }
+            }
+        }
+    };
+    // Store-only fields never round-trip through native files.
+    attach_artifacts(&mut imported);
+    imported.shared_session_uid = session.shared_session_uid.clone();
+    imported.lineage = session.lineage.clone();
+    Ok(imported)
+}
 
-    if incoming.session_uid != anchor.session_uid {
-        add_session_uid_alias(&mut anchor, &incoming.session_uid);
-    }
-    for alias in session_uid_aliases(&incoming) {
-        add_session_uid_alias(&mut anchor, &alias);
-    }
-    if let Some(shared) = incoming.shared_session_uid.as_ref()
-        && shared != &anchor.session_uid
-    {
-        add_session_uid_alias(&mut anchor, shared);
-    }
+fn lineage_graph(repo: &Path, session_uid: &str) -> Result<Value> {
+    let mut sessions: Vec<SteadSession> = open_store(repo).load_all()?;
+    sessions.sort_by(|a, b| {
+        a.metadata
+            .created_at
+            .cmp(&b.metadata.created_at)
+            .then_with(|| a.session_uid.cmp(&b.session_uid))
+    });
+    let target = sessions
+        .iter()
+        .position(|session| canonical_lookup_matches(session, session_uid))
+        .ok_or_else(|| anyhow!("canonical session not found: {}", session_uid))?;
+
+    let parent_uid = |index: usize| {
+        sessions[index]
+            .lineage
+            .as_ref()
+            .and_then(|lineage| lineage.parent_session_uid.clone())
+    };
+    let parent_index = |index: usize| {
+        let parent = parent_uid(index)?;
+        sessions
+            .iter()
+            .position(|session| canonical_lookup_matches(session, &parent))
+    };
 
-    if incoming.metadata.created_at < anchor.metadata.created_at {
-        anchor.metadata.created_at = incoming.metadata.created_at;
-    }
-    if incoming.metadata.updated_at > anchor.metadata.updated_at {
-        anchor.metadata.updated_at = incoming.metadata.updated_at;
+    let mut ancestors = Vec::new();
+    let mut root = target;
+    let mut seen = HashSet::from([target]);
+    while let Some(parent) = parent_index(root) {
+        if !seen.insert(parent) {
+            break;
+        }
+        ancestors.push(sessions[parent].session_uid.clone());
+        root = parent;
     }
-    if anchor.metadata.title.is_none() {
-        anchor.metadata.title = incoming.metadata.title.clone();
+    let missing_parent = parent_index(root)
+        .is_none()
+        .then(|| parent_uid(root))
+        .flatten();
+
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    for index in 0..sessions.len() {
+        if let Some(parent) = parent_index(index)
+            && parent != index
+        {
+            children.entry(parent).or_default().push(index);
+        }
     }
-    if anchor.metadata.project_root == "/unknown" && incoming.metadata.project_root != "/unknown" {
-        anchor.metadata.project_root = incoming.metadata.project_root.clone();
+    let mut order = Vec::new();
+    let mut stack = vec![(root, 0usize)];
+    let mut visited = HashSet::new();
+    while let Some((index, depth)) = stack.pop() {
+        if !visited.insert(index) {
+            continue;
+        }
+        order.push((index, depth));
+        for child in children.get(&index).into_iter().flatten().rev() {
+            stack.push((*child, depth + 1));
+        }
     }
 
-    let mut source_files: Vec<String> = anchor
-        .source
-        .source_files
+    let nodes: Vec<Value> = order
         .iter()
-        .chain(incoming.source.source_files.iter())
-        .cloned()
+        .map(|(index, depth)| {
+            let session = &sessions[*index];
+            let lineage = session.lineage.clone().unwrap_or_default();
+            let fork_origin_sequence = parent_index(*index).and_then(|parent| {
+                sessions[parent]
+                    .events
+                    .iter()
+                    .find(|event| Some(&event.event_uid) == lineage.fork_origin_event_uid.as_ref())
+                    .and_then(|event| event.sequence)
+            });
+            let backends: Vec<String> = session
+                .extensions
+                .get("native_refs")
+                .and_then(Value::as_object)
+                .map(|refs| refs.keys().cloned().collect())
+                .unwrap_or_default();
+            // The recorded parent may be an alias; edges point at the stored session.
+            let parent_session_uid = parent_index(*index)
+                .map(|parent| sessions[parent].session_uid.clone())
+                .or(lineage.parent_session_uid.clone());
+            json!({
+                "session_uid": session.session_uid,
+                "parent_session_uid": parent_session_uid,
+                "root_session_uid": lineage.root_session_uid,
+                "fork_origin_event_uid": lineage.fork_origin_event_uid,
+                "fork_origin_sequence": fork_origin_sequence,
+                "strategy": lineage.strategy,
+                "source_backend": session.source.backend.as_str(),
+                "backends": backends,
+                "title": session.metadata.title,
+                "events": session.events.len(),
+                "created_at": session.metadata.created_at,
+                "depth": depth,
+                "selected": *index == target,
+            })
+        })
         .collect();
-    source_files = dedupe_strings(source_files);
-    anchor.source.source_files = source_files;
-    upsert_backend_raw_lines(&mut anchor.raw_vendor_payload, &incoming);
-
-    let mut index_by_key: HashMap<String, usize> = HashMap::new();
-    let mut merged_events = Vec::new();
-    for event in anchor.events.into_iter().chain(incoming.events.into_iter()) {
-        let key = format!(
-            "{}|{}|{}|{:?}",
-            event.stream_id, event.event_uid, event.timestamp, event.kind
-        );
-        if let Some(index) = index_by_key.get(&key).copied() {
-            merged_events[index] = event;
-        } else {
-            index_by_key.insert(key, merged_events.len());
-            merged_events.push(event);
+
+    Ok(json!({
+        "session_uid": sessions[target].session_uid,
+        "root_session_uid": sessions[root].session_uid,
+        "missing_parent_session_uid": missing_parent,
+        "ancestors": ancestors,
+        "nodes": nodes,
+    }))
+}
+
+fn run_lineage(repo: PathBuf, session_uid: &str, format: LineageFormat) -> Result<()> {
+    let graph = lineage_graph(&repo, session_uid)?;
+    let nodes = graph["nodes"].as_array().cloned().unwrap_or_default();
+    match format {
+        LineageFormat::Json => println!("{}", serde_json::to_string(&graph)?),
+        LineageFormat::Dot => {
+            println!("digraph lineage {{");
+            for node in &nodes {
+                let style = if node["selected"] == true {
+                    ", style=bold"
+                } else {
+                    ""
+                };
+                println!(
+                    "  {} [label={}{}];",
+                    dot_quote(node["session_uid"].as_str().unwrap_or_default()),
+                    dot_quote(&lineage_label(node)),
+                    style
+                );
+            }
+            for node in &nodes {
+                if node["depth"] == 0 {
+                    continue;
+                }
+                let mut label = node["strategy"].as_str().unwrap_or("child").to_string();
+                if let Some(origin) = node["fork_origin_event_uid"].as_str() {
+                    label.push_str(&format!(" @ {origin}"));
+                }
+                println!(
+                    "  {} -> {} [label={}];",
+                    dot_quote(node["parent_session_uid"].as_str().unwrap_or_default()),
+                    dot_quote(node["session_uid"].as_str().unwrap_or_default()),
+                    dot_quote(&label)
+                );
+            }
+            println!("}}");
+        }
+        LineageFormat::Text => {
+            if let Some(missing) = graph["missing_parent_session_uid"].as_str() {
+                println!("(parent {missing} not in store)");
+            }
+            for node in &nodes {
+                let depth = node["depth"].as_u64().unwrap_or_default() as usize;
+                let indent = if depth == 0 {
+                    String::new()
+                } else {
+                    format!("{}└─ ", "   ".repeat(depth - 1))
+                };
+                let marker = if node["selected"] == true { " *" } else { "" };
+                println!("{indent}{}{marker}", lineage_label(node));
+            }
         }
     }
-    stead_session_model::canonical_sort_events(&mut merged_events);
-    anchor.events = merged_events;
-    anchor
+    Ok(())
 }
 
-fn load_all_canonical_sessions(repo: &Path) -> Result<Vec<StoredCanonical>> {
-    let store = canonical_store_dir(repo);
-    if !store.exists() {
-        return Ok(Vec::new());
-    }
-    let mut out = Vec::new();
-    for entry in std::fs::read_dir(&store)? {
-        let path = entry?.path();
-        if !path.is_file() {
-            continue;
+fn lineage_label(node: &Value) -> String {
+    let backends = node["backends"]
+        .as_array()
+        .filter(|backends| !backends.is_empty())
+        .map(|backends| {
+            backends
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_else(|| {
+            node["source_backend"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string()
+        });
+    let mut label = format!(
+        "{} [{}] {} events",
+        node["session_uid"].as_str().unwrap_or_default(),
+        backends,
+        node["events"]
+    );
+    if let Some(strategy) = node["strategy"].as_str() {
+        label.push_str(&format!(" {strategy}"));
+        if let Some(origin) = node["fork_origin_event_uid"].as_str() {
+            label.push_str(&format!(" at {origin}"));
+            if let Some(sequence) = node["fork_origin_sequence"].as_u64() {
+                label.push_str(&format!(" (#{sequence})"));
+            }
         }
-        let raw = std::fs::read_to_string(&path)?;
-        let mut session: SteadSession = serde_json::from_str(&raw)?;
-        ensure_shared_session_uid(&mut session);
-        out.push(StoredCanonical { session });
     }
-    Ok(out)
+    if let Some(title) = node["title"].as_str() {
+        label.push_str(&format!(" \"{title}\""));
+    }
+    label
 }
 
-fn canonical_store_dir(repo: &Path) -> PathBuf {
-    repo.join(".stead-core").join("sessions")
+fn dot_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
 }
 
-fn canonical_session_path(repo: &Path, session_uid: &str) -> PathBuf {
-    let sanitized: String = session_uid
-        .chars()
-        .map(|c| {
-            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
-                c
-            } else {
-                '_'
+fn run_redact(
+    repo: PathBuf,
+    session_uid: Option<&str>,
+    config: Option<PathBuf>,
+    dry_run: bool,
+    json_out: bool,
+) -> Result<()> {
+    let redactor = load_redactor(true, config.as_deref(), Some(&repo))?
+        .context("redaction is always enabled for the redact command")?;
+    let _lock = open_store(&repo).lock()?;
+    let sessions = match session_uid {
+        Some(uid) => vec![open_store(&repo).load(uid)?],
+        None => open_store(&repo).load_all()?,
+    };
+
+    let mut report = Vec::new();
+    for mut session in sessions {
+        let redactions = redactor.redact_session(&mut session);
+        if !redactions.is_empty() && !dry_run {
+            open_store(&repo).save(&session)?;
+        }
+        if !json_out {
+            let count: usize = redactions.iter().map(|redaction| redaction.count).sum();
+            println!(
+                "{} {} matches in {}",
+                if dry_run { "would redact" } else { "redacted" },
+                count,
+                session.session_uid
+            );
+            for redaction in &redactions {
+                println!(
+                    "  {} {} {} x{}",
+                    redaction.event_uid.as_deref().unwrap_or("<session>"),
+                    redaction.field,
+                    redaction.rule,
+                    redaction.count
+                );
             }
-        })
-        .collect();
-    let file_name = if sanitized.is_empty() {
-        format!("session-{}", short_hash(session_uid))
+        }
+        report.push(json!({
+            "session_uid": session.session_uid,
+            "redactions": redactions,
+        }));
+    }
+    if json_out {
+        println!(
+            "{}",
+            serde_json::to_string(&json!({
+                "dry_run": dry_run,
+                "sessions": report
+            }))?
+        );
+    }
+    Ok(())
+}
+
+fn run_gc(repo: PathBuf, options: &GcOptions, json_out: bool) -> Result<()> {
+    let report = open_workspace(&repo).gc(options)?;
+    if json_out {
+        println!("{}", serde_json::to_string(&report)?);
+        return Ok(());
+    }
+    let verb = if report.dry_run {
+        "would remove"
     } else {
-        format!("{}-{}", sanitized, short_hash(session_uid))
+        "removed"
     };
-    canonical_store_dir(repo).join(format!("{}.json", file_name))
+    for session in &report.sessions {
+        println!("{verb} session {}", session.session_uid);
+    }
+    for projection in &report.projections {
+        println!(
+            "{verb} {} projection {}",
+            projection.backend.as_str(),
+            projection.path.display()
+        );
+    }
+    for alias in &report.aliases {
+        println!("{verb} alias {} of {}", alias.alias, alias.session_uid);
+    }
+    let freed: u64 = report.blobs.iter().map(|blob| blob.size).sum();
+    println!(
+        "{verb} {} sessions, {} projections, {} aliases, {} blobs ({freed} bytes)",
+        report.sessions.len(),
+        report.projections.len(),
+        report.aliases.len(),
+        report.blobs.len()
+    );
+    Ok(())
 }
 
-fn store_canonical_session(repo: &Path, session: &SteadSession) -> Result<PathBuf> {
-    let path = canonical_session_path(repo, &session.session_uid);
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)?;
+fn run_archive(
+    repo: PathBuf,
+    session_uid: &str,
+    archive_dir: Option<&Path>,
+    json_out: bool,
+) -> Result<()> {
+    let archived = open_workspace(&repo).archive(session_uid, archive_dir)?;
+    if json_out {
+        println!("{}", serde_json::to_string(&archived)?);
+        return Ok(());
     }
-    std::fs::write(&path, serde_json::to_string_pretty(session)?)?;
-    Ok(path)
+    for file in &archived.files {
+        println!(
+            "archived {} {}",
+            file.backend.as_str(),
+            file.original_path.display()
+        );
+    }
+    println!(
+        "archived {} files of {} to {}",
+        archived.files.len(),
+        archived.session_uid,
+        archived.archive_dir.display()
+    );
+    Ok(())
 }
 
-fn load_canonical_session(repo: &Path, session_uid: &str) -> Result<SteadSession> {
-    let direct = canonical_session_path(repo, session_uid);
-    if direct.exists() {
-        let raw = std::fs::read_to_string(&direct)?;
-        return Ok(serde_json::from_str(&raw)?);
+fn run_restore(
+    repo: PathBuf,
+    session_uid: &str,
+    codex_base: Option<&Path>,
+    claude_base: Option<&Path>,
+    json_out: bool,
+) -> Result<()> {
+    let restored = open_workspace(&repo).restore(session_uid, codex_base, claude_base)?;
+    if json_out {
+        println!("{}", serde_json::to_string(&restored)?);
+        return Ok(());
     }
+    for file in &restored.files {
+        println!("restored {} {}", file.backend.as_str(), file.path.display());
+    }
+    println!(
+        "restored {} files of {}",
+        restored.files.len(),
+        restored.session_uid
+    );
+    Ok(())
+}
+
+fn run_compact(
+    repo: PathBuf,
+    session_uid: Option<&str>,
+    rehydrate: bool,
+    json_out: bool,
+) -> Result<()> {
+    let _lock = open_store(&repo).lock()?;
+    let sessions = match session_uid {
+        Some(uid) => vec![open_store(&repo).load(uid)?],
+        None => open_store(&repo).load_all()?,
+    };
 
-    let store = canonical_store_dir(repo);
-    if !store.exists() {
-        return Err(anyhow!(
-            "canonical store does not exist at {}",
-            store.display()
-        ));
+    let mut report = Vec::new();
+    for mut session in sessions {
+        let entry = if rehydrate {
+            let result = rehydrate_session(&mut session);
+            if result.restored > 0 {
+                open_store(&repo).save(&session)?;
+            }
+            if !json_out {
+                println!(
+                    "{}: restored {} raw lines, {} missing",
+                    session.session_uid, result.restored, result.missing
+                );
+            }
+            json!({ "session_uid": session.session_uid, "restored": result.restored, "missing": result.missing })
+        } else {
+            let result = compact_session(&mut session);
+            if result.compacted > 0 {
+                open_store(&repo).save(&session)?;
+            }
+            if !json_out {
+                println!(
+                    "{}: compacted {} raw lines, {} kept inline",
+                    session.session_uid, result.compacted, result.kept_inline
+                );
+            }
+            json!({ "session_uid": session.session_uid, "compacted": result.compacted, "kept_inline": result.kept_inline })
+        };
+        report.push(entry);
     }
-    for entry in std::fs::read_dir(&store)? {
-        let path = entry?.path();
-        if !path.is_file() {
-            continue;
+    if json_out {
+        println!("{}", serde_json::to_string(&report)?);
+    }
+    Ok(())
+}
+
+fn run_bundle(
+    repo: PathBuf,
+    session_uid: &str,
+    out: PathBuf,
+    redaction_config: Option<&Path>,
+    json_out: bool,
+) -> Result<()> {
+    let store = open_store(&repo);
+    let target = store.load(session_uid)?;
+    let mut chain = vec![target];
+    let mut seen = HashSet::from([chain[0].session_uid.clone()]);
+    while let Some((child_uid, parent_uid)) = chain.last().and_then(|session| {
+        let parent_uid = session.lineage.as_ref()?.parent_session_uid.clone()?;
+        Some((session.session_uid.clone(), parent_uid))
+    }) {
+        let parent = store.load(&parent_uid).with_context(|| {
+            format!("failed to load parent session {parent_uid} of {child_uid}")
+        })?;
+        if !seen.insert(parent.session_uid.clone()) {
+            break;
         }
-        let raw = std::fs::read_to_string(&path)?;
-        let session: SteadSession = serde_json::from_str(&raw)?;
-        if canonical_lookup_matches(&session, session_uid) {
-            return Ok(session);
+        chain.push(parent);
+    }
+    chain.reverse();
+
+    // Bundles leave the machine, so everything in them goes through the redactor.
+    let redactor = load_redactor(true, redaction_config, Some(&repo))?
+        .context("redaction is always enabled for bundles")?;
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    // Artifact contents are staged in the archive only, never in the local blob store.
+    let mut blobs: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    let mut session_entries = Vec::new();
+    let mut artifact_entries = Vec::new();
+    for session in &mut chain {
+        rehydrate_session(session);
+        // Files are matched against the hashes recorded before redaction rewrites them.
+        let contents: Vec<(String, String, Option<String>)> = session
+            .artifacts
+            .iter()
+            .filter_map(|artifact| {
+                let content = bundled_artifact_content(session, artifact)?;
+                Some((
+                    artifact.artifact_uid.clone(),
+                    content,
+                    artifact.path.clone(),
+                ))
+            })
+            .collect();
+        redactor.redact_session(session);
+        for (artifact_uid, content, original_path) in contents {
+            let (content, hits) = redactor.redact_text(&content);
+            let sha256 = sha256_hex(content.as_bytes());
+            artifact_entries.push(json!({
+                "session_uid": session.session_uid,
+                "artifact_uid": artifact_uid,
+                "original_path": original_path,
+                "sha256": sha256,
+                "size": content.len(),
+                "redacted": !hits.is_empty()
+            }));
+            blobs.insert(sha256, content.into_bytes());
         }
+
+        let file_name = store
+            .session_path(&session.session_uid)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| format!("{}.json", short_hash(&session.session_uid)));
+        let entry_path = format!("sessions/{file_name}");
+        let serialized = serde_json::to_vec_pretty(&session)?;
+        session_entries.push(json!({
+            "session_uid": session.session_uid,
+            "path": entry_path,
+            "sha256": sha256_hex(&serialized),
+            "size": serialized.len()
+        }));
+        files.push((entry_path, serialized));
+    }
+    let mut blob_entries = Vec::new();
+    for (sha256, content) in &blobs {
+        let entry_path = format!("blobs/{sha256}");
+        blob_entries.push(json!({ "sha256": sha256, "path": entry_path, "size": content.len() }));
+        files.push((entry_path, content.clone()));
     }
-    Err(anyhow!("canonical session not found: {}", session_uid))
-}
 
-fn canonical_lookup_matches(session: &SteadSession, lookup: &str) -> bool {
-    if session.session_uid == lookup {
-        return true;
+    let target = chain
+        .last()
+        .expect("bundle chain includes the target session");
+    let manifest = json!({
+        "bundle_version": BUNDLE_VERSION,
+        "created_at": Utc::now(),
+        "session_uid": target.session_uid,
+        "project_root": target.metadata.project_root,
+        "sessions": session_entries,
+        "blobs": blob_entries,
+        "artifacts": artifact_entries
+    });
+
+    if let Some(parent) = out.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create output directory {}", parent.display()))?;
     }
-    if session.shared_session_uid.as_deref() == Some(lookup) {
-        return true;
+    let file = std::fs::File::create(&out)
+        .with_context(|| format!("failed to create bundle {}", out.display()))?;
+    let mut builder = tar::Builder::new(file);
+    append_tar_entry(
+        &mut builder,
+        "manifest.json",
+        &serde_json::to_vec_pretty(&manifest)?,
+    )?;
+    for (path, content) in &files {
+        append_tar_entry(&mut builder, path, content)?;
     }
-    session_uid_aliases(session)
-        .iter()
-        .any(|alias| alias == lookup)
-}
+    builder.into_inner()?;
 
-fn ensure_shared_session_uid(session: &mut SteadSession) -> bool {
-    if session.shared_session_uid.is_none() {
-        session.shared_session_uid = Some(session.session_uid.clone());
-        return true;
+    if json_out {
+        println!(
+            "{}",
+            serde_json::to_string(&json!({
+                "session_uid": target.session_uid,
+                "bundle": out,
+                "sessions": chain.len(),
+                "blobs": blobs.len(),
+                "artifacts": manifest["artifacts"].as_array().map_or(0, Vec::len)
+            }))?
+        );
+    } else {
+        println!(
+            "bundled {} ({} sessions, {} blobs) -> {}",
+            target.session_uid,
+            chain.len(),
+            blobs.len(),
+            out.display()
+        );
     }
-    false
+    Ok(())
 }
 
-fn add_session_uid_alias(session: &mut SteadSession, alias: &str) {
-    if alias.is_empty() || alias == session.session_uid {
-        return;
-    }
-    let entry = session
-        .extensions
-        .entry("session_uid_aliases".to_string())
-        .or_insert_with(|| json!([]));
-    if !entry.is_array() {
-        *entry = json!([]);
+/// The current content of a file the session created or modified inside its project root, as
+/// long as it still matches what the session wrote. Files it only read are never bundled.
+fn bundled_artifact_content(
+    session: &SteadSession,
+    artifact: &SessionArtifactRef,
+) -> Option<String> {
+    if artifact.kind != ArtifactKind::Created.as_str()
+        && artifact.kind != ArtifactKind::Modified.as_str()
+    {
+        return None;
     }
-    if let Some(arr) = entry.as_array_mut() {
-        if arr.iter().any(|v| v.as_str() == Some(alias)) {
-            return;
-        }
-        arr.push(Value::String(alias.to_string()));
+    let path = Path::new(artifact.path.as_deref()?);
+    let inside_project = path.is_absolute()
+        && path.starts_with(&session.metadata.project_root)
+        && !path
+            .components()
+            .any(|component| component == std::path::Component::ParentDir);
+    if !inside_project {
+        return None;
     }
+    let content = std::fs::read_to_string(path).ok()?;
+    (artifact.sha256.as_deref() == Some(sha256_hex(content.as_bytes()).as_str())).then_some(content)
 }
 
-fn session_uid_aliases(session: &SteadSession) -> Vec<String> {
-    session
-        .extensions
-        .get("session_uid_aliases")
-        .and_then(|v| v.as_array())
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                .collect()
-        })
-        .unwrap_or_default()
+fn append_tar_entry<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    content: &[u8],
+) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, path, content)?;
+    Ok(())
 }
 
-fn dedupe_strings(values: Vec<String>) -> Vec<String> {
-    let mut seen = HashSet::new();
-    let mut out = Vec::new();
-    for value in values {
-        if seen.insert(value.clone()) {
-            out.push(value);
+fn run_unbundle(
+    repo: PathBuf,
+    input: PathBuf,
+    project_root: Option<PathBuf>,
+    on_conflict: BundleConflict,
+    mappings: &[RootMapping],
+    json_out: bool,
+) -> Result<()> {
+    let _lock = open_store(&repo).lock()?;
+    let file = std::fs::File::open(&input)
+        .with_context(|| format!("failed to open bundle {}", input.display()))?;
+    let mut archive = tar::Archive::new(file);
+    let mut entries: HashMap<String, Vec<u8>> = HashMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut content = Vec::new();
+        std::io::Read::read_to_end(&mut entry, &mut content)?;
+        entries.insert(path, content);
+    }
+    let manifest_raw = entries
+        .get("manifest.json")
+        .ok_or_else(|| anyhow!("bundle {} has no manifest.json", input.display()))?;
+    let manifest: Value = serde_json::from_slice(manifest_raw)
+        .with_context(|| format!("invalid manifest in {}", input.display()))?;
+    if manifest["bundle_version"].as_u64() != Some(BUNDLE_VERSION) {
+        bail!(
+            "unsupported bundle version {} in {}",
+            manifest["bundle_version"],
+            input.display()
+        );
+    }
+    let listed =
+        |key: &str| -> Vec<Value> { manifest[key].as_array().cloned().unwrap_or_default() };
+    for entry in listed("sessions").iter().chain(listed("blobs").iter()) {
+        let path = entry["path"].as_str().unwrap_or_default();
+        let content = entries
+            .get(path)
+            .ok_or_else(|| anyhow!("bundle entry {path} is missing"))?;
+        if entry["sha256"].as_str() != Some(sha256_hex(content).as_str()) {
+            bail!("bundle entry {path} failed hash verification");
         }
     }
-    out
-}
 
-fn upsert_backend_raw_lines(anchor_raw: &mut Value, incoming: &SteadSession) {
-    let Some(lines) = incoming
-        .raw_vendor_payload
-        .get("lines")
-        .and_then(|v| v.as_array())
-        .cloned()
-    else {
-        return;
-    };
+    let blobs = open_store(&repo).blobs();
+    for entry in listed("blobs") {
+        let path = entry["path"].as_str().unwrap_or_default();
+        blobs.put(&entries[path])?;
+    }
 
-    if !anchor_raw.is_object() {
-        *anchor_raw = json!({});
+    let old_root = manifest["project_root"].as_str().unwrap_or_default();
+    let new_root = normalize_path(project_root.as_deref().unwrap_or(&repo))
+        .to_string_lossy()
+        .into_owned();
+    let mut mappings = mappings.to_vec();
+    if !old_root.is_empty() {
+        mappings.push(RootMapping::new(old_root, &new_root));
     }
-    let Some(raw_obj) = anchor_raw.as_object_mut() else {
-        return;
-    };
-    let backend_lines = raw_obj
-        .entry("backend_lines".to_string())
-        .or_insert_with(|| json!({}));
-    if !backend_lines.is_object() {
-        *backend_lines = json!({});
-    }
-    if let Some(map) = backend_lines.as_object_mut() {
-        map.insert(
-            incoming.source.backend.as_str().to_string(),
-            Value::Array(lines),
+    let bundle_sha = sha256_hex(manifest_raw);
+    let mut renames: HashMap<String, String> = HashMap::new();
+    let mut report = Vec::new();
+    for entry in listed("sessions") {
+        let path = entry["path"].as_str().unwrap_or_default();
+        let mut session: SteadSession = serde_json::from_slice(&entries[path])
+            .with_context(|| format!("invalid canonical JSON in bundle entry {path}"))?;
+        blobs.rehydrate_session(&mut session)?;
+        let bundled_uid = session.session_uid.clone();
+        if let Some(lineage) = session.lineage.as_mut() {
+            for uid in [
+                &mut lineage.parent_session_uid,
+                &mut lineage.root_session_uid,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                if let Some(renamed) = renames.get(uid.as_str()) {
+                    *uid = renamed.clone();
+                }
+            }
+        }
+        session.extensions.remove("native_refs");
+        remap_session(&mut session, &mappings);
+        for bundled in listed("artifacts")
+            .iter()
+            .filter(|bundled| bundled["session_uid"].as_str() == Some(bundled_uid.as_str()))
+        {
+            if let Some(artifact) = session
+                .artifacts
+                .iter_mut()
+                .find(|artifact| bundled["artifact_uid"].as_str() == Some(&artifact.artifact_uid))
+            {
+                artifact
+                    .extensions
+                    .insert("content_blob".to_string(), bundled["sha256"].clone());
+            }
+        }
+        session.extensions.insert(
+            "bundle_origin".to_string(),
+            json!({ "session_uid": bundled_uid, "project_root": old_root }),
         );
-    }
-}
 
-fn set_native_ref(session: &mut SteadSession, backend: Backend, native_id: &str, path: &Path) {
-    let key = backend_key(backend);
-    let entry = session
-        .extensions
-        .entry("native_refs".to_string())
-        .or_insert_with(|| json!({}));
-    if !entry.is_object() {
-        *entry = json!({});
+        let existing = open_store(&repo).load(&session.session_uid).ok();
+        let action = match existing {
+            None => "imported",
+            Some(existing) if same_timeline(&existing, &session) => "unchanged",
+            Some(_) if on_conflict == BundleConflict::Skip => "skipped",
+            Some(_) if on_conflict == BundleConflict::Overwrite => "overwritten",
+            Some(_) => {
+                let renamed = format!(
+                    "{}-{}",
+                    session.session_uid,
+                    short_hash(&format!("{}|{}", session.session_uid, bundle_sha))
+                );
+                session.session_uid = renamed.clone();
+                session.shared_session_uid = None;
+                renames.insert(bundled_uid.clone(), renamed.clone());
+                match open_store(&repo).load(&renamed) {
+                    Ok(existing) if same_timeline(&existing, &session) => "unchanged",
+                    _ => "renamed",
+                }
+            }
+        };
+        if matches!(action, "imported" | "overwritten" | "renamed") {
+            ensure_shared_session_uid(&mut session);
+            open_store(&repo).save(&session)?;
+        }
+        if !json_out {
+            if bundled_uid == session.session_uid {
+                println!("{action} {bundled_uid}");
+            } else {
+                println!("{action} {bundled_uid} -> {}", session.session_uid);
+            }
+        }
+        report.push(json!({
+            "bundled_session_uid": bundled_uid,
+            "session_uid": session.session_uid,
+            "action": action
+        }));
     }
-    if let Some(map) = entry.as_object_mut() {
-        map.insert(
-            key.to_string(),
-            json!({
-                "session_id": native_id,
-                "path": path.display().to_string()
-            }),
-        );
+    if json_out {
+        println!("{}", serde_json::to_string(&report)?);
     }
+    Ok(())
 }
 
-fn get_native_ref(session: &SteadSession, backend: Backend) -> Option<(String, PathBuf)> {
-    let key = backend_key(backend);
-    let refs = session.extensions.get("native_refs")?.as_object()?;
-    let selected = refs.get(key)?.as_object()?;
-    let session_id = selected.get("session_id")?.as_str()?.to_string();
-    let path = selected.get("path")?.as_str()?.to_string();
-    Some((session_id, PathBuf::from(path)))
+fn same_timeline(left: &SteadSession, right: &SteadSession) -> bool {
+    left.events.len() == right.events.len()
+        && left.events.iter().zip(&right.events).all(|(a, b)| {
+            a.event_uid == b.event_uid && event_fingerprint(a) == event_fingerprint(b)
+        })
 }
 
-fn choose_native_id(session: &SteadSession, backend: Backend) -> String {
-    if let Some((id, _)) = get_native_ref(session, backend) {
-        return id;
-    }
-    if source_backend_matches(session.source.backend, backend) {
-        return session.source.original_session_id.clone();
-    }
-    deterministic_backend_uuid(session, backend).to_string()
+fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
 }
 
-fn deterministic_backend_uuid(session: &SteadSession, backend: Backend) -> Uuid {
-    let shared = session
-        .shared_session_uid
-        .as_deref()
-        .unwrap_or(session.session_uid.as_str());
-    let key = format!("stead-native:{}:{}", backend_key(backend), shared);
-    Uuid::new_v5(&Uuid::NAMESPACE_URL, key.as_bytes())
+/// The repo whose `.stead-core/config.toml` applies; commands without `--repo` use the current
+/// directory.
+fn config_repo(command: &Commands) -> Option<PathBuf> {
+    let repo = match command {
+        Commands::Sync { repo, .. }
+        | Commands::Materialize { repo, .. }
+        | Commands::Resume { repo, .. }
+        | Commands::Handoff { repo, .. }
+        | Commands::Changes { repo, .. }
+        | Commands::Verify { repo, .. }
+        | Commands::Fork { repo, .. }
+        | Commands::Diff { repo, .. }
+        | Commands::Lineage { repo, .. }
+        | Commands::Redact { repo, .. }
+        | Commands::Compact { repo, .. }
+        | Commands::Gc { repo, .. }
+        | Commands::Archive { repo, .. }
+        | Commands::Restore { repo, .. }
+        | Commands::Serve { repo, .. }
+        | Commands::Mcp { repo, .. }
+        | Commands::Bundle { repo, .. }
+        | Commands::Unbundle { repo, .. } => Some(repo.clone()),
+        Commands::Config {
+            command: ConfigCommands::Show { repo, .. },
+        }
+        | Commands::Doctor { repo, .. } => repo.clone(),
+        Commands::Sessions { .. }
+        | Commands::Import { .. }
+        | Commands::Export { .. }
+        | Commands::Convert { .. } => None,
+    };
+    repo.or_else(|| std::env::current_dir().ok())
 }
 
-fn source_backend_matches(source_backend: BackendKind, backend: Backend) -> bool {
-    matches!(
-        (source_backend, backend),
-        (BackendKind::Codex, Backend::Codex) | (BackendKind::ClaudeCode, Backend::Claude)
-    )
+/// An explicit base directory, else the configured or discovered backend home.
+fn configured_base_dir(backend: Backend, explicit: Option<PathBuf>) -> Option<PathBuf> {
+    explicit.or_else(|| discover_home(config(), backend).map(|home| home.path))
 }
 
-fn short_hash(value: &str) -> String {
-    let mut hasher = XxHash64::with_seed(0);
-    hasher.write(value.as_bytes());
-    format!("{:016x}", hasher.finish())[..8].to_string()
+fn resolve_base_dir(backend: Backend, explicit: Option<PathBuf>) -> Result<PathBuf> {
+    configured_base_dir(backend, explicit).ok_or_else(|| {
+        anyhow!(
+            "no {0} base directory; pass it on the command line, set `[{0}] base_dir` in .stead-core/config.toml or set {1}",
+            backend.as_str(),
+            backend_home(backend).env_var
+        )
+    })
 }
 
-fn default_materialized_path(
-    base_dir: &Path,
-    repo: &Path,
-    backend: Backend,
-    native_id: &str,
-) -> PathBuf {
-    let now = Utc::now();
-    let timestamp = now.format("%Y-%m-%dT%H-%M-%S").to_string();
-    match backend {
-        Backend::Codex => codex_sessions_root(base_dir)
-            .join(format!("{:04}", now.year()))
-            .join(format!("{:02}", now.month()))
-            .join(format!("{:02}", now.day()))
-            .join(format!("rollout-{timestamp}-{native_id}.jsonl")),
-        Backend::Claude => {
-            let slug = repo.display().to_string().replace(['/', '\\'], "-");
-            base_dir
-                .join("projects")
-                .join(slug)
-                .join(format!("{}.jsonl", native_id))
-        }
+fn open_store(repo: &Path) -> Store {
+    Store::with_options(repo, config().store_options())
+}
+
+fn open_workspace(repo: &Path) -> Workspace {
+    Workspace::new(open_store(repo))
+}
+
+/// Rules come from `config_file`, then the repo's `.stead-core/redaction.toml`, then the
+/// `[redaction]` config section, then the built-in defaults.
+fn load_redactor(
+    enabled: bool,
+    config_file: Option<&Path>,
+    repo: Option<&Path>,
+) -> Result<Option<Redactor>> {
+    if !enabled && config_file.is_none() {
+        return Ok(None);
     }
+    let config_path = config_file.map(Path::to_path_buf).or_else(|| {
+        repo.map(|repo| repo.join(".stead-core").join("redaction.toml"))
+            .filter(|path| path.exists())
+    });
+    let rules = match config_path {
+        Some(path) => {
+            let raw = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read redaction config {}", path.display()))?;
+            toml::from_str::<RedactionConfig>(&raw)
+                .with_context(|| format!("invalid redaction config {}", path.display()))?
+        }
+        None => config().redaction.clone().unwrap_or_default(),
+    };
+    Ok(Some(Redactor::from_config(&rules)?))
 }
 
-fn codex_sessions_root(base_dir: &Path) -> PathBuf {
-    if base_dir
-        .file_name()
-        .is_some_and(|v| v.to_string_lossy().eq_ignore_ascii_case("sessions"))
-    {
-        base_dir.to_path_buf()
+fn run_config_show(json_out: bool) -> Result<()> {
+    let config = config();
+    let effective = config.effective();
+    if json_out {
+        println!(
+            "{}",
+            serde_json::to_string(&json!({
+                "sources": config.sources,
+                "config": effective,
+            }))?
+        );
     } else {
-        base_dir.join("sessions")
+        if config.sources.is_empty() {
+            println!("# sources: defaults only");
+        } else {
+            println!("# sources: {}", config.sources.join(", "));
+        }
+        print!("{}", toml::to_string(&effective)?);
     }
+    Ok(())
 }
 
-fn prune_codex_rollouts_for_native_id(base_dir: &Path, native_id: &str, keep: &Path) -> Result<()> {
-    let root = codex_sessions_root(base_dir);
-    if !root.exists() {
-        return Ok(());
+fn run_changes(
+    repo: PathBuf,
+    session_uid: &str,
+    patch: Option<PathBuf>,
+    json_out: bool,
+) -> Result<()> {
+    let session = open_store(&repo).load(session_uid)?;
+    let files = session_file_changes(&session);
+    let rendered = render_git_patch(&files, &session.metadata.project_root);
+
+    if let Some(patch_path) = patch.as_ref() {
+        if let Some(parent) = patch_path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(patch_path, &rendered)
+            .with_context(|| format!("failed to write patch to {}", patch_path.display()))?;
     }
-    prune_codex_rollouts_in_dir(&root, native_id, keep)
+
+    if json_out {
+        println!(
+            "{}",
+            serde_json::to_string(&json!({
+                "session_uid": session.session_uid,
+                "project_root": session.metadata.project_root,
+                "files": files,
+                "patch_path": patch
+            }))?
+        );
+    } else if let Some(patch_path) = patch {
+        println!(
+            "wrote changes for {} files to {}",
+            files.len(),
+            patch_path.display()
+        );
+    } else {
+        print!("{rendered}");
+    }
+    Ok(())
 }
 
-fn prune_codex_rollouts_in_dir(dir: &Path, native_id: &str, keep: &Path) -> Result<()> {
-    let suffix = format!("-{native_id}.jsonl");
-    for entry in std::fs::read_dir(dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        let file_type = entry.file_type()?;
-        if file_type.is_dir() {
-            prune_codex_rollouts_in_dir(&path, native_id, keep)?;
-            continue;
-        }
-        if !file_type.is_file() || path == keep {
-            continue;
-        }
-        let Some(name) = path.file_name().and_then(|v| v.to_str()) else {
-            continue;
+fn run_verify(repo: PathBuf, session_uid: &str, json_out: bool) -> Result<()> {
+    let session = open_store(&repo).load(session_uid)?;
+    let project_root = PathBuf::from(&session.metadata.project_root);
+    let work_dir = if project_root.is_dir() {
+        project_root
+    } else {
+        repo.clone()
+    };
+    let git_root = git_output(&work_dir, &["rev-parse", "--show-toplevel"])?
+        .map(|out| PathBuf::from(out.trim()))
+        .ok_or_else(|| anyhow!("not a git repository: {}", work_dir.display()))?;
+
+    let mut files_out = Vec::new();
+    let mut counts: HashMap<&'static str, usize> = HashMap::new();
+    for file in session_file_changes(&session) {
+        let current = std::fs::read_to_string(&file.path).ok();
+        let status = file_status(&file, current.as_deref());
+        *counts.entry(status.as_str()).or_default() += 1;
+        let rel_path = Path::new(&file.path)
+            .strip_prefix(&git_root)
+            .map(|path| path.display().to_string())
+            .ok();
+        let uncommitted = match rel_path.as_deref() {
+            Some(rel) => git_output(&git_root, &["status", "--porcelain", "--", rel])?
+                .is_some_and(|out| !out.trim().is_empty()),
+            None => false,
         };
-        if name.ends_with(&suffix) {
-            std::fs::remove_file(path)?;
+
+        let mut changes_out = Vec::new();
+        for change in &file.changes {
+            let change_state = change_status(&file, change, current.as_deref());
+            let commit = match rel_path.as_deref() {
+                Some(rel) if change_state != ChangeStatus::Superseded => {
+                    find_change_commit(&git_root, rel, change)?
+                }
+                _ => None,
+            };
+            changes_out.push(json!({
+                "source_event_uid": change.source_event_uid,
+                "tool_name": change.tool_name,
+                "kind": change.kind,
+                "timestamp": change.timestamp,
+                "status": change_state,
+                "commit": commit,
+            }));
         }
+        files_out.push(json!({
+            "path": file.path,
+            "status": status,
+            "uncommitted": uncommitted,
+            "in_repo": rel_path.is_some(),
+            "changes": changes_out,
+        }));
     }
-    Ok(())
-}
 
-fn backend_key(backend: Backend) -> &'static str {
-    match backend {
-        Backend::Codex => "codex",
-        Backend::Claude => "claude",
+    if json_out {
+        println!(
+            "{}",
+            serde_json::to_string(&json!({
+                "session_uid": session.session_uid,
+                "git_root": git_root,
+                "summary": counts,
+                "files": files_out,
+            }))?
+        );
+    } else {
+        for file in &files_out {
+            let dirty = if file["uncommitted"] == true {
+                " (uncommitted)"
+            } else {
+                ""
+            };
+            println!(
+                "{}\t{}{}",
+                file["status"].as_str().unwrap_or_default(),
+                file["path"].as_str().unwrap_or_default(),
+                dirty
+            );
+            for change in file["changes"].as_array().into_iter().flatten() {
+                let commit = change["commit"]["sha"]
+                    .as_str()
+                    .map(|sha| {
+                        format!(
+                            "{} {}",
+                            &sha[..sha.len().min(12)],
+                            change["commit"]["subject"].as_str().unwrap_or_default()
+                        )
+                    })
+                    .unwrap_or_else(|| "not committed".to_string());
+                println!(
+                    "  {}\t{}\t{}",
+                    change["source_event_uid"].as_str().unwrap_or_default(),
+                    change["status"].as_str().unwrap_or_default(),
+                    commit
+                );
+            }
+        }
     }
+    Ok(())
 }
 
-fn scope_sessions_to_repo(repo: &Path, sessions: Vec<NativeSessionRef>) -> Vec<NativeSessionRef> {
-    let (repo_scoped, others): (Vec<_>, Vec<_>) = sessions
-        .into_iter()
-        .partition(|session| session_matches_repo(repo, session.project_root.as_deref()));
-    if repo_scoped.is_empty() {
-        others
+fn find_change_commit(
+    git_root: &Path,
+    rel_path: &str,
+    change: &FileChange,
+) -> Result<Option<Value>> {
+    let pickaxe;
+    let mut args = vec!["log", "--reverse", "--format=%H%x1f%ct%x1f%s"];
+    if change.kind == ArtifactKind::Deleted.as_str() {
+        args.push("--diff-filter=D");
+    } else if let Some(needle) = commit_search_text(change) {
+        pickaxe = format!("-S{needle}");
+        args.push(&pickaxe);
+    } else if change.kind == ArtifactKind::Created.as_str() {
+        args.push("--diff-filter=A");
     } else {
-        repo_scoped
+        return Ok(None);
     }
-}
+    args.extend(["--", rel_path]);
 
-fn session_matches_repo(repo: &Path, project_root: Option<&str>) -> bool {
-    let Some(project_root) = project_root else {
-        return false;
+    let Some(log) = git_output(git_root, &args)? else {
+        return Ok(None);
     };
-    normalize_path(repo) == normalize_path(Path::new(project_root))
+    let edited_at = change.timestamp.timestamp();
+    Ok(log.lines().find_map(|line| {
+        let mut parts = line.splitn(3, '\u{1f}');
+        let sha = parts.next()?;
+        let committed_at: i64 = parts.next()?.parse().ok()?;
+        let subject = parts.next().unwrap_or_default();
+        (committed_at >= edited_at).then(|| {
+            json!({
+                "sha": sha,
+                "subject": subject,
+                "committed_at": chrono::DateTime::from_timestamp(committed_at, 0),
+            })
+        })
+    }))
 }
 
-fn normalize_path(path: &Path) -> PathBuf {
-    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+fn git_output(dir: &Path, args: &[&str]) -> Result<Option<String>> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
 }
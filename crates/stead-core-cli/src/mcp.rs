@@ -0,0 +1,273 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use stead_core_runtime::Backend;
+use stead_session_model::{EventKind, EventPayload, SteadEvent};
+
+use crate::open_workspace;
+use crate::query::{
+    QueryError, event_text, find_session, list_sessions, search, session_events, session_summary,
+};
+
+/// Newest first; the first entry is offered when the client asks for an unknown version.
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+const INVALID_PARAMS: i64 = -32602;
+
+pub(crate) struct McpOptions {
+    pub(crate) repo: PathBuf,
+    pub(crate) codex_base: Option<PathBuf>,
+    pub(crate) claude_base: Option<PathBuf>,
+}
+
+/// Serves newline-delimited JSON-RPC 2.0 on stdin/stdout until stdin closes.
+pub(crate) fn run_mcp(options: McpOptions) -> Result<()> {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle_message(&options, &message),
+            Err(err) => Some(rpc_error(
+                Value::Null,
+                -32700,
+                &format!("parse error: {err}"),
+            )),
+        };
+        if let Some(response) = response {
+            writeln!(stdout, "{response}")?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn handle_message(options: &McpOptions, message: &Value) -> Option<Value> {
+    // Notifications carry no id, and responses to requests we never send carry no method;
+    // neither gets a response.
+    let id = message.get("id").cloned()?;
+    let method = message.get("method")?.as_str().unwrap_or_default();
+    let result = match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": negotiate_protocol_version(&message["params"]),
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "stead-core", "version": env!("CARGO_PKG_VERSION") },
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+        "tools/call" => call_tool(
+            options,
+            message["params"]["name"].as_str().unwrap_or_default(),
+            &message["params"]["arguments"],
+        ),
+        _ => Err((-32601, format!("method not found: {method}"))),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => rpc_error(id, code, &message),
+    })
+}
+
+fn negotiate_protocol_version(params: &Value) -> &'static str {
+    let requested = params["protocolVersion"].as_str();
+    PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| requested == Some(**version))
+        .unwrap_or(&PROTOCOL_VERSIONS[0])
+}
+
+fn rpc_error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Unknown tools and invalid arguments are JSON-RPC errors; failures while running a tool are
+/// reported in the result with `isError`.
+fn call_tool(options: &McpOptions, name: &str, arguments: &Value) -> Result<Value, (i64, String)> {
+    let repo = options.repo.as_path();
+    let query = query_from_arguments(arguments);
+    let result = match name {
+        "list_sessions" => list_sessions(repo, &query),
+        "get_session_summary" => {
+            required(arguments, "session").and_then(|session| detailed_summary(repo, session))
+        }
+        "search_sessions" => {
+            let mut query = query;
+            if let Some(text) = query.remove("query") {
+                query.insert("q".to_string(), text);
+            }
+            search(repo, &query)
+        }
+        "get_events" => {
+            required(arguments, "session").and_then(|session| session_events(repo, session, &query))
+        }
+        "handoff_to" => handoff_to(options, arguments),
+        _ => return Err((INVALID_PARAMS, format!("unknown tool: {name}"))),
+    };
+    match result {
+        Ok(value) => Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": serde_json::to_string_pretty(&value).unwrap_or_default()
+            }],
+            "isError": false,
+        })),
+        Err(QueryError::InvalidInput(message)) => Err((INVALID_PARAMS, message)),
+        Err(err) => Ok(json!({
+            "content": [{ "type": "text", "text": err.to_string() }],
+            "isError": true,
+        })),
+    }
+}
+
+fn tool_definitions() -> Value {
+    let session =
+        json!({ "type": "string", "description": "Canonical session uid, shared uid or alias" });
+    json!([
+        {
+            "name": "list_sessions",
+            "description": "List canonical sessions stored for this repository, newest first.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "backend": { "type": "string", "enum": ["codex", "claude"] },
+                    "q": { "type": "string", "description": "Substring of the session title" },
+                    "updated_since": { "type": "string", "format": "date-time" },
+                    "offset": { "type": "integer", "minimum": 0 },
+                    "limit": { "type": "integer", "minimum": 1 }
+                }
+            }
+        },
+        {
+            "name": "get_session_summary",
+            "description": "Summarize one session: first prompt, last reply, tools used and files touched.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "session": session },
+                "required": ["session"]
+            }
+        },
+        {
+            "name": "search_sessions",
+            "description": "Case-insensitive search over messages, tool calls and tool output of past sessions.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "session": session,
+                    "limit": { "type": "integer", "minimum": 1 }
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "get_events",
+            "description": "Read a range of a session's events in canonical order.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "session": session,
+                    "offset": { "type": "integer", "minimum": 0 },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": 1000 },
+                    "kind": {
+                        "type": "string",
+                        "enum": EventKind::ALL.map(|kind| kind.as_str())
+                    }
+                },
+                "required": ["session"]
+            }
+        },
+        {
+            "name": "handoff_to",
+            "description": "Make a session resumable in another backend and return the command that resumes it.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "session": session,
+                    "backend": { "type": "string", "enum": ["codex", "claude"] },
+                    "base_dir": { "type": "string", "description": "Backend home used when the session has no projection yet" }
+                },
+                "required": ["session", "backend"]
+            }
+        }
+    ])
+}
+
+fn detailed_summary(repo: &Path, uid: &str) -> Result<Value, QueryError> {
+    let session = find_session(repo, uid)?;
+    let text_of = |event: &SteadEvent, kind: EventKind| {
+        (event.kind == kind).then(|| event_text(&event.payload))
+    };
+    let first_prompt = session
+        .events
+        .iter()
+        .find_map(|event| text_of(event, EventKind::MessageUser));
+    let last_reply = session
+        .events
+        .iter()
+        .rev()
+        .find_map(|event| text_of(event, EventKind::MessageAssistant));
+    let mut tools: BTreeMap<&str, usize> = BTreeMap::new();
+    for event in &session.events {
+        if let EventPayload::ToolCall { tool_name, .. } = &event.payload {
+            *tools.entry(tool_name.as_str()).or_default() += 1;
+        }
+    }
+    let mut files: BTreeMap<&str, &str> = BTreeMap::new();
+    for artifact in &session.artifacts {
+        if let Some(path) = artifact.path.as_deref() {
+            files.insert(path, artifact.kind.as_str());
+        }
+    }
+    let mut summary = session_summary(&session);
+    summary["first_prompt"] = json!(first_prompt);
+    summary["last_reply"] = json!(last_reply);
+    summary["tool_calls"] = json!(tools);
+    summary["files_touched"] = json!(files);
+    Ok(summary)
+}
+
+fn handoff_to(options: &McpOptions, arguments: &Value) -> Result<Value, QueryError> {
+    let uid = required(arguments, "session")?;
+    let backend = Backend::from_str(required(arguments, "backend")?, true)
+        .map_err(|err| QueryError::InvalidInput(format!("invalid backend: {err}")))?;
+    find_session(&options.repo, uid)?;
+    let base_dir = arguments["base_dir"]
+        .as_str()
+        .map(PathBuf::from)
+        .or_else(|| match backend {
+            Backend::Codex => options.codex_base.clone(),
+            Backend::Claude => options.claude_base.clone(),
+        });
+    let handoff = open_workspace(&options.repo).handoff(uid, backend, base_dir.as_deref(), None)?;
+    let mut result = json!(handoff);
+    result["resume_command"] = json!(handoff.resume_command());
+    Ok(result)
+}
+
+fn required<'a>(arguments: &'a Value, key: &str) -> Result<&'a str, QueryError> {
+    arguments[key]
+        .as_str()
+        .ok_or_else(|| QueryError::InvalidInput(format!("missing string argument {key}")))
+}
+
+fn query_from_arguments(arguments: &Value) -> HashMap<String, String> {
+    arguments
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| {
+            let value = match value {
+                Value::String(text) => text.clone(),
+                Value::Number(number) => number.to_string(),
+                Value::Bool(flag) => flag.to_string(),
+                _ => return None,
+            };
+            Some((key.clone(), value))
+        })
+        .collect()
+}
@@ -0,0 +1,249 @@
+//! Read-only queries over the canonical store, shared by the HTTP API and the MCP server.
+
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use stead_session_model::{EventPayload, SteadSession};
+
+use crate::open_store;
+
+const DEFAULT_EVENT_LIMIT: usize = 100;
+const MAX_PAGE_LIMIT: usize = 1000;
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+/// Why a query failed; each server maps these onto its own protocol's errors.
+#[derive(Debug)]
+pub(crate) enum QueryError {
+    InvalidInput(String),
+    NotFound(String),
+    Failed(anyhow::Error),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidInput(message) | Self::NotFound(message) => f.write_str(message),
+            Self::Failed(err) => write!(f, "{err:#}"),
+        }
+    }
+}
+
+impl From<anyhow::Error> for QueryError {
+    fn from(err: anyhow::Error) -> Self {
+        Self::Failed(err)
+    }
+}
+
+pub(crate) type QueryResult = Result<Value, QueryError>;
+
+pub(crate) fn load_sessions(repo: &Path) -> Result<Vec<SteadSession>, QueryError> {
+    Ok(open_store(repo).load_all()?)
+}
+
+pub(crate) fn find_session(repo: &Path, uid: &str) -> Result<SteadSession, QueryError> {
+    open_store(repo)
+        .find(uid)?
+        .ok_or_else(|| QueryError::NotFound(format!("canonical session not found: {uid}")))
+}
+
+pub(crate) fn list_sessions(repo: &Path, query: &HashMap<String, String>) -> QueryResult {
+    let backend = query.get("backend").map(|backend| match backend.as_str() {
+        "claude" => "claude_code",
+        other => other,
+    });
+    let since = query
+        .get("updated_since")
+        .map(|since| {
+            DateTime::parse_from_rfc3339(since)
+                .map(|since| since.with_timezone(&Utc))
+                .map_err(|err| QueryError::InvalidInput(format!("invalid updated_since: {err}")))
+        })
+        .transpose()?;
+    let title = query.get("q").map(|q| q.to_lowercase());
+    let (offset, limit) = page(query, usize::MAX)?;
+
+    let mut sessions: Vec<SteadSession> = load_sessions(repo)?
+        .into_iter()
+        .filter(|session| backend.is_none_or(|backend| session.source.backend.as_str() == backend))
+        .filter(|session| {
+            query
+                .get("project_root")
+                .is_none_or(|root| session.metadata.project_root == *root)
+        })
+        .filter(|session| since.is_none_or(|since| session.metadata.updated_at >= since))
+        .filter(|session| {
+            title.as_ref().is_none_or(|q| {
+                session
+                    .metadata
+                    .title
+                    .as_deref()
+                    .is_some_and(|title| title.to_lowercase().contains(q.as_str()))
+            })
+        })
+        .collect();
+    sessions.sort_by(|a, b| {
+        b.metadata
+            .updated_at
+            .cmp(&a.metadata.updated_at)
+            .then_with(|| a.session_uid.cmp(&b.session_uid))
+    });
+    let total = sessions.len();
+    let page: Vec<Value> = sessions
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .map(session_summary)
+        .collect();
+    Ok(json!({ "total": total, "offset": offset, "sessions": page }))
+}
+
+pub(crate) fn session_summary(session: &SteadSession) -> Value {
+    let backends: Vec<String> = session
+        .extensions
+        .get("native_refs")
+        .and_then(Value::as_object)
+        .map(|refs| refs.keys().cloned().collect())
+        .unwrap_or_default();
+    json!({
+        "session_uid": session.session_uid,
+        "shared_session_uid": session.shared_session_uid,
+        "source_backend": session.source.backend.as_str(),
+        "original_session_id": session.source.original_session_id,
+        "title": session.metadata.title,
+        "project_root": session.metadata.project_root,
+        "created_at": session.metadata.created_at,
+        "updated_at": session.metadata.updated_at,
+        "events": session.events.len(),
+        "artifacts": session.artifacts.len(),
+        "backends": backends,
+        "parent_session_uid": session.lineage.as_ref().and_then(|lineage| lineage.parent_session_uid.clone()),
+    })
+}
+
+pub(crate) fn session_events(
+    repo: &Path,
+    uid: &str,
+    query: &HashMap<String, String>,
+) -> QueryResult {
+    let session = find_session(repo, uid)?;
+    let (offset, limit) = page(query, DEFAULT_EVENT_LIMIT)?;
+    let events: Vec<_> = session
+        .events
+        .iter()
+        .filter(|event| {
+            query
+                .get("kind")
+                .is_none_or(|kind| event.kind.as_str() == kind)
+        })
+        .collect();
+    Ok(json!({
+        "session_uid": session.session_uid,
+        "total": events.len(),
+        "offset": offset,
+        "limit": limit,
+        "events": events.into_iter().skip(offset).take(limit).collect::<Vec<_>>(),
+    }))
+}
+
+pub(crate) fn search(repo: &Path, query: &HashMap<String, String>) -> QueryResult {
+    let needle = query
+        .get("q")
+        .filter(|q| !q.is_empty())
+        .ok_or_else(|| QueryError::InvalidInput("missing query parameter q".to_string()))?
+        .to_lowercase();
+    let limit = match query.get("limit") {
+        Some(limit) => parse_number(limit, "limit")?.min(MAX_PAGE_LIMIT),
+        None => DEFAULT_SEARCH_LIMIT,
+    };
+    let sessions = match query.get("session") {
+        Some(uid) => vec![find_session(repo, uid)?],
+        None => load_sessions(repo)?,
+    };
+    let mut hits = Vec::new();
+    let mut total = 0;
+    for session in sessions {
+        for event in &session.events {
+            let text = event_text(&event.payload);
+            let Some(at) = find_ignore_case(&text, &needle) else {
+                continue;
+            };
+            total += 1;
+            if hits.len() < limit {
+                hits.push(json!({
+                    "session_uid": session.session_uid,
+                    "event_uid": event.event_uid,
+                    "sequence": event.sequence,
+                    "kind": event.kind,
+                    "timestamp": event.timestamp,
+                    "snippet": snippet(&text, at),
+                }));
+            }
+        }
+    }
+    Ok(json!({ "query": needle, "total": total, "limit": limit, "hits": hits }))
+}
+
+fn page(
+    query: &HashMap<String, String>,
+    default_limit: usize,
+) -> Result<(usize, usize), QueryError> {
+    let offset = match query.get("offset") {
+        Some(offset) => parse_number(offset, "offset")?,
+        None => 0,
+    };
+    let limit = match query.get("limit") {
+        Some(limit) => parse_number(limit, "limit")?.min(MAX_PAGE_LIMIT),
+        None => default_limit,
+    };
+    Ok((offset, limit))
+}
+
+fn parse_number(value: &str, name: &str) -> Result<usize, QueryError> {
+    value
+        .parse()
+        .map_err(|_| QueryError::InvalidInput(format!("invalid {name}: {value}")))
+}
+
+pub(crate) fn event_text(payload: &EventPayload) -> String {
+    match payload {
+        EventPayload::Text { text } => text.clone(),
+        EventPayload::ToolCall { tool_name, input } => format!("{tool_name} {input}"),
+        EventPayload::ToolResult {
+            output_text,
+            error_text,
+            ..
+        } => [output_text.as_deref(), error_text.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n"),
+        EventPayload::Json { value } => value.to_string(),
+    }
+}
+
+/// Byte offset in `text` of the first case-insensitive match of the lowercased `needle`.
+fn find_ignore_case(text: &str, needle: &str) -> Option<usize> {
+    // Lowercasing can change a character's byte length, so keep where each lowered byte came from.
+    let mut lowered = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len());
+    for (idx, ch) in text.char_indices() {
+        for lower in ch.to_lowercase() {
+            lowered.push(lower);
+            origins.resize(lowered.len(), idx);
+        }
+    }
+    let needle: String = needle.chars().flat_map(char::to_lowercase).collect();
+    lowered.find(&needle).map(|at| origins[at])
+}
+
+/// Up to 60 characters before the match at byte offset `at` of `text`, 160 in total.
+fn snippet(text: &str, at: usize) -> String {
+    let start = text[..at]
+        .char_indices()
+        .rev()
+        .nth(59)
+        .map_or(0, |(idx, _)| idx);
+    text[start..].chars().take(160).collect()
+}
@@ -0,0 +1,320 @@
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use stead_session_adapters::remap::RootMapping;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use stead_core_runtime::{Backend, SyncOptions};
+
+use crate::query::{
+    QueryError, find_session, list_sessions, load_sessions, search, session_events,
+};
+use crate::{config, configured_base_dir, lineage_graph, load_redactor, open_workspace};
+
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        Self::new(500, format!("{err:#}"))
+    }
+}
+
+impl From<QueryError> for ApiError {
+    fn from(err: QueryError) -> Self {
+        let status = match err {
+            QueryError::InvalidInput(_) => 400,
+            QueryError::NotFound(_) => 404,
+            QueryError::Failed(_) => 500,
+        };
+        Self::new(status, err.to_string())
+    }
+}
+
+type ApiResult = std::result::Result<Value, ApiError>;
+
+pub(crate) fn run_serve(repo: PathBuf, port: u16, allow_writes: bool) -> Result<()> {
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|err| anyhow!("failed to listen on 127.0.0.1:{port}: {err}"))?;
+    let addr = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| anyhow!("server is not listening on an IP address"))?;
+    println!("listening on http://{addr}");
+    std::io::stdout().flush()?;
+
+    for mut request in server.incoming_requests() {
+        let (status, body) = match handle(&repo, addr.port(), allow_writes, &mut request) {
+            Ok(body) => (200, body),
+            Err(err) => (err.status, json!({ "error": err.message })),
+        };
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(
+                Header::from_bytes("Content-Type", "application/json")
+                    .expect("static header is valid"),
+            );
+        if let Err(err) = request.respond(response) {
+            eprintln!("failed to send response: {err}");
+        }
+    }
+    Ok(())
+}
+
+fn handle(repo: &Path, port: u16, allow_writes: bool, request: &mut Request) -> ApiResult {
+    require_local_request(request, port)?;
+    let (path, query) = match request.url().split_once('?') {
+        Some((path, query)) => (path.to_string(), parse_query(query)),
+        None => (request.url().to_string(), HashMap::new()),
+    };
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| percent_decode(segment, false))
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let method = request.method().clone();
+
+    match (&method, segments.as_slice()) {
+        (Method::Get, ["sessions"]) => Ok(list_sessions(repo, &query)?),
+        (Method::Get, ["sessions", uid]) => {
+            Ok(serde_json::to_value(find_session(repo, uid)?).map_err(anyhow::Error::from)?)
+        }
+        (Method::Get, ["sessions", uid, "events"]) => Ok(session_events(repo, uid, &query)?),
+        (Method::Get, ["sessions", uid, "lineage"]) => {
+            find_session(repo, uid)?;
+            Ok(lineage_graph(repo, uid)?)
+        }
+        (Method::Get, ["search"]) => Ok(search(repo, &query)?),
+        (Method::Get, ["stats"]) => stats(repo),
+        (Method::Post, ["sync"]) => {
+            require_writes(allow_writes)?;
+            sync(repo, &read_body(request)?)
+        }
+        (Method::Post, ["sessions", uid, "materialize"]) => {
+            require_writes(allow_writes)?;
+            materialize(repo, uid, &read_body(request)?)
+        }
+        (
+            _,
+            ["sessions"]
+            | ["sessions", _]
+            | ["sessions", _, "events" | "lineage" | "materialize"]
+            | ["search"]
+            | ["stats"]
+            | ["sync"],
+        ) => Err(ApiError::new(405, "method not allowed")),
+        _ => Err(ApiError::new(404, format!("no route for {path}"))),
+    }
+}
+
+fn stats(repo: &Path) -> ApiResult {
+    let sessions = load_sessions(repo)?;
+    let mut by_backend: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut by_kind: BTreeMap<String, usize> = BTreeMap::new();
+    for session in &sessions {
+        *by_backend
+            .entry(session.source.backend.as_str())
+            .or_default() += 1;
+        for event in &session.events {
+            let kind = serde_json::to_value(event.kind)
+                .ok()
+                .and_then(|kind| kind.as_str().map(str::to_string))
+                .unwrap_or_default();
+            *by_kind.entry(kind).or_default() += 1;
+        }
+    }
+    Ok(json!({
+        "sessions": sessions.len(),
+        "events": sessions.iter().map(|session| session.events.len()).sum::<usize>(),
+        "artifacts": sessions.iter().map(|session| session.artifacts.len()).sum::<usize>(),
+        "forks": sessions.iter().filter(|session| session.lineage.is_some()).count(),
+        "sessions_by_backend": by_backend,
+        "events_by_kind": by_kind,
+        "last_updated_at": sessions.iter().map(|session| session.metadata.updated_at).max(),
+    }))
+}
+
+fn sync(repo: &Path, body: &Value) -> ApiResult {
+    let codex_base = body_base_dir(body, "codex_base", Backend::Codex)?;
+    let claude_base = body_base_dir(body, "claude_base", Backend::Claude)?;
+    let strategy = body_enum(body, "merge_strategy", config().merge_strategy())?;
+    let profile = body_enum(body, "storage_profile", config().storage_profile())?;
+    let redact = body["redact"].as_bool().unwrap_or(config().redact());
+    let options = SyncOptions {
+        codex_base,
+        claude_base,
+        strategy,
+        profile,
+        redactor: load_redactor(redact, None, Some(repo))?,
+    };
+    let synced = open_workspace(repo).sync(&options)?;
+    Ok(json!({ "synced": synced }))
+}
+
+fn materialize(repo: &Path, uid: &str, body: &Value) -> ApiResult {
+    let mut session = find_session(repo, uid)?;
+    let to: Backend = body_enum_required(body, "to")?;
+    let base_dir = body_base_dir(body, "base_dir", to)?;
+    if !body["out"].is_null() {
+        return Err(ApiError::new(
+            400,
+            "out is not accepted over HTTP; the session is written under base_dir",
+        ));
+    }
+    let mappings = body["map_root"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|mapping| {
+            mapping
+                .as_str()
+                .unwrap_or_default()
+                .parse::<RootMapping>()
+                .map_err(|err| ApiError::new(400, err.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let materialized =
+        open_workspace(repo).materialize(&mut session, to, &base_dir, None, &mappings)?;
+    Ok(json!({
+        "session_uid": session.session_uid,
+        "backend": to.as_str(),
+        "native_id": materialized.native_id,
+        "output_path": materialized.output_path,
+        "events_exported": materialized.report.events_exported,
+    }))
+}
+
+fn require_writes(allow_writes: bool) -> Result<(), ApiError> {
+    if allow_writes {
+        Ok(())
+    } else {
+        Err(ApiError::new(
+            403,
+            "write endpoints are disabled; start the server with --allow-writes",
+        ))
+    }
+}
+
+/// Rejects requests addressed to another host name (DNS rebinding) or sent from a foreign
+/// origin, so web pages open in a browser cannot reach the API.
+fn require_local_request(request: &Request, port: u16) -> Result<(), ApiError> {
+    let is_local = |authority: &str| {
+        let (host, request_port) = match authority.split_once(':') {
+            Some((host, request_port)) => (host, Some(request_port)),
+            None => (authority, None),
+        };
+        matches!(host, "127.0.0.1" | "localhost")
+            && request_port.is_none_or(|request_port| request_port == port.to_string())
+    };
+    if !header(request, "Host").is_some_and(is_local) {
+        return Err(ApiError::new(403, "Host must be 127.0.0.1 or localhost"));
+    }
+    if let Some(origin) = header(request, "Origin")
+        && !origin.strip_prefix("http://").is_some_and(is_local)
+    {
+        return Err(ApiError::new(
+            403,
+            format!("cross-origin request from {origin}"),
+        ));
+    }
+    Ok(())
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+fn read_body(request: &mut Request) -> Result<Value, ApiError> {
+    let is_json = header(request, "Content-Type").is_some_and(|content_type| {
+        content_type
+            .split(';')
+            .next()
+            .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+    });
+    if !is_json {
+        return Err(ApiError::new(415, "Content-Type must be application/json"));
+    }
+    let mut raw = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut raw)
+        .map_err(|err| ApiError::new(400, format!("failed to read request body: {err}")))?;
+    if raw.trim().is_empty() {
+        return Ok(json!({}));
+    }
+    serde_json::from_str(&raw)
+        .map_err(|err| ApiError::new(400, format!("invalid JSON body: {err}")))
+}
+
+/// A base directory from the body, falling back to the configured one for `backend`.
+fn body_base_dir(body: &Value, key: &str, backend: Backend) -> Result<PathBuf, ApiError> {
+    configured_base_dir(backend, body[key].as_str().map(PathBuf::from))
+        .ok_or_else(|| ApiError::new(400, format!("missing string field {key}")))
+}
+
+fn body_enum<T: ValueEnum>(body: &Value, key: &str, default: T) -> Result<T, ApiError> {
+    match body.get(key) {
+        None | Some(Value::Null) => Ok(default),
+        Some(_) => body_enum_required(body, key),
+    }
+}
+
+fn body_enum_required<T: ValueEnum>(body: &Value, key: &str) -> Result<T, ApiError> {
+    let value = body[key]
+        .as_str()
+        .ok_or_else(|| ApiError::new(400, format!("missing string field {key}")))?;
+    T::from_str(value, true).map_err(|err| ApiError::new(400, format!("invalid {key}: {err}")))
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key, true), percent_decode(value, true))
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str, plus_as_space: bool) -> String {
+    let bytes = value.as_bytes();
+    let hex = |byte: u8| (byte as char).to_digit(16).map(|digit| digit as u8);
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let escaped = (bytes[idx] == b'%' && idx + 2 < bytes.len())
+            .then(|| Some(hex(bytes[idx + 1])? * 16 + hex(bytes[idx + 2])?))
+            .flatten();
+        match (escaped, bytes[idx]) {
+            (Some(byte), _) => {
+                out.push(byte);
+                idx += 3;
+                continue;
+            }
+            (None, b'+') if plus_as_space => out.push(b' '),
+            (None, byte) => out.push(byte),
+        }
+        idx += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -0,0 +1,300 @@
+//! Moving a session's native files out of the backend homes and back.
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Component, Path, PathBuf};
+use stead_session_adapters::claude::ClaudeAdapter;
+use stead_session_adapters::codex::CodexAdapter;
+use stead_session_adapters::compact::{is_compact, rehydrate_session};
+use stead_session_model::SteadSession;
+
+use crate::merge::merge_sessions;
+use crate::native::{
+    claude_projects_root, codex_sessions_root, get_native_ref, normalize_path, set_native_ref,
+};
+use crate::workspace::Workspace;
+use crate::{Backend, MergeStrategy};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Where an archived session's files went; stored as `manifest.json` in its archive directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub session_uid: String,
+    pub archived_at: DateTime<Utc>,
+    pub files: Vec<ArchivedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    pub backend: Backend,
+    pub original_path: PathBuf,
+    /// Path below the backend's `sessions`/`projects` root, used to restore into another home.
+    pub layout: Option<PathBuf>,
+    /// Path inside the archive directory.
+    pub archived_as: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Archived {
+    pub session_uid: String,
+    pub archive_dir: PathBuf,
+    pub files: Vec<ArchivedFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Restored {
+    pub session_uid: String,
+    pub files: Vec<RestoredFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoredFile {
+    pub backend: Backend,
+    pub path: PathBuf,
+}
+
+impl Workspace {
+    pub fn default_archive_root(&self) -> PathBuf {
+        self.repo().join(".stead-core").join("archive")
+    }
+
+    /// Moves the native projections and source files of `session_uid` below `archive_root`
+    /// once re-importing each projection adds nothing the canonical session lacks.
+    pub fn archive(&self, session_uid: &str, archive_root: Option<&Path>) -> Result<Archived> {
+        let _lock = self.store().lock()?;
+        let mut session = self.store().load(session_uid)?;
+        if archive_location(&session).is_some() {
+            bail!("session `{}` is already archived", session.session_uid);
+        }
+        let files = native_files(&session);
+        if files.is_empty() {
+            bail!(
+                "session `{}` has no native files to archive",
+                session.session_uid
+            );
+        }
+        ensure_complete(&mut session)?;
+
+        let archive_root = archive_root
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| self.default_archive_root());
+        let stored_name = self
+            .store()
+            .session_path(&session.session_uid)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "session".to_string());
+        let archive_dir = archive_root.join(stored_name);
+        if archive_dir.exists() {
+            bail!("archive directory {} already exists", archive_dir.display());
+        }
+
+        let mut archived = Vec::new();
+        for (backend, original_path) in files {
+            let layout = backend_layout(backend, &original_path);
+            let archived_as = Path::new(backend.as_str()).join(
+                layout
+                    .clone()
+                    .or_else(|| original_path.file_name().map(PathBuf::from))
+                    .unwrap_or_else(|| PathBuf::from("session.jsonl")),
+            );
+            move_file(&original_path, &archive_dir.join(&archived_as))?;
+            archived.push(ArchivedFile {
+                backend,
+                original_path,
+                layout,
+                archived_as,
+            });
+        }
+        let manifest = ArchiveManifest {
+            session_uid: session.session_uid.clone(),
+            archived_at: Utc::now(),
+            files: archived,
+        };
+        std::fs::write(
+            archive_dir.join(MANIFEST_FILE),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+        session.extensions.insert(
+            "archive".to_string(),
+            json!({
+                "path": archive_dir.display().to_string(),
+                "archived_at": manifest.archived_at,
+            }),
+        );
+        self.store().save(&session)?;
+        Ok(Archived {
+            session_uid: session.session_uid,
+            archive_dir,
+            files: manifest.files,
+        })
+    }
+
+    /// Moves archived files back to where they came from, or below `codex_base`/`claude_base`
+    /// in the backend's usual layout when given.
+    pub fn restore(
+        &self,
+        session_uid: &str,
+        codex_base: Option<&Path>,
+        claude_base: Option<&Path>,
+    ) -> Result<Restored> {
+        let _lock = self.store().lock()?;
+        let mut session = self.store().load(session_uid)?;
+        let Some(archive_dir) = archive_location(&session) else {
+            bail!("session `{}` is not archived", session.session_uid);
+        };
+        let manifest_path = archive_dir.join(MANIFEST_FILE);
+        let manifest: ArchiveManifest = serde_json::from_str(
+            &std::fs::read_to_string(&manifest_path)
+                .with_context(|| format!("failed to read {}", manifest_path.display()))?,
+        )
+        .with_context(|| format!("invalid archive manifest {}", manifest_path.display()))?;
+
+        let targets: Vec<PathBuf> = manifest
+            .files
+            .iter()
+            .map(|file| {
+                let base = match file.backend {
+                    Backend::Codex => codex_base.map(codex_sessions_root),
+                    Backend::Claude => claude_base.map(claude_projects_root),
+                };
+                match (base, file.layout.as_ref()) {
+                    (Some(root), Some(layout)) => root.join(layout),
+                    _ => file.original_path.clone(),
+                }
+            })
+            .collect();
+        if let Some(taken) = targets.iter().find(|target| target.exists()) {
+            bail!("cannot restore over existing file {}", taken.display());
+        }
+
+        let mut restored = Vec::new();
+        for (file, target) in manifest.files.iter().zip(targets) {
+            move_file(&archive_dir.join(&file.archived_as), &target)?;
+            if let Some((native_id, path)) = get_native_ref(&session, file.backend)
+                && path == file.original_path
+            {
+                set_native_ref(&mut session, file.backend, &native_id, &target);
+            }
+            let original = file.original_path.display().to_string();
+            for source in &mut session.source.source_files {
+                if *source == original {
+                    *source = target.display().to_string();
+                }
+            }
+            restored.push(RestoredFile {
+                backend: file.backend,
+                path: target,
+            });
+        }
+        std::fs::remove_dir_all(&archive_dir)?;
+        session.extensions.remove("archive");
+        self.store().save(&session)?;
+        Ok(Restored {
+            session_uid: session.session_uid,
+            files: restored,
+        })
+    }
+}
+
+/// The archive directory recorded on an archived session.
+pub fn archive_location(session: &SteadSession) -> Option<PathBuf> {
+    session
+        .extensions
+        .get("archive")?
+        .get("path")?
+        .as_str()
+        .map(PathBuf::from)
+}
+
+/// Existing native projections and source files, each listed once.
+fn native_files(session: &SteadSession) -> Vec<(Backend, PathBuf)> {
+    let source_backend = Backend::from_kind(session.source.backend);
+    let mut files: Vec<(Backend, PathBuf)> = Vec::new();
+    let candidates = [Backend::Codex, Backend::Claude]
+        .into_iter()
+        .filter_map(|backend| get_native_ref(session, backend).map(|(_, path)| (backend, path)))
+        .chain(
+            session
+                .source
+                .source_files
+                .iter()
+                .map(|path| (source_backend, PathBuf::from(path))),
+        );
+    for (backend, path) in candidates {
+        if path.is_file()
+            && !files
+                .iter()
+                .any(|(_, seen)| normalize_path(seen) == normalize_path(&path))
+        {
+            files.push((backend, path));
+        }
+    }
+    files
+}
+
+/// Fails unless the session validates, its compact raw lines can be inlined, and merging each
+/// native projection back in would not add events.
+fn ensure_complete(session: &mut SteadSession) -> Result<()> {
+    session
+        .validate()
+        .with_context(|| format!("session `{}` is invalid", session.session_uid))?;
+    if is_compact(session) {
+        let report = rehydrate_session(session);
+        if report.missing > 0 {
+            bail!(
+                "session `{}` references {} raw lines that are no longer in its native files",
+                session.session_uid,
+                report.missing
+            );
+        }
+    }
+    for backend in [Backend::Codex, Backend::Claude] {
+        let Some((_, path)) = get_native_ref(session, backend).filter(|(_, path)| path.is_file())
+        else {
+            continue;
+        };
+        let native = match backend {
+            Backend::Codex => CodexAdapter::from_base_dir(".").import_from_file(&path)?,
+            Backend::Claude => ClaudeAdapter::from_base_dir(".").import_from_file(&path, "main")?,
+        };
+        let outcome = merge_sessions(session.clone(), native, MergeStrategy::PreferAnchor)?;
+        if outcome.branch.is_some() || outcome.session.events.len() > session.events.len() {
+            bail!(
+                "{} has events missing from canonical session `{}`; run sync first",
+                path.display(),
+                session.session_uid
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `path` relative to the backend's session root (`sessions/` for Codex, `projects/` for Claude).
+fn backend_layout(backend: Backend, path: &Path) -> Option<PathBuf> {
+    let marker = match backend {
+        Backend::Codex => "sessions",
+        Backend::Claude => "projects",
+    };
+    let components: Vec<Component> = path.components().collect();
+    let index = components
+        .iter()
+        .rposition(|component| component.as_os_str() == marker)?;
+    let layout: PathBuf = components[index + 1..].iter().collect();
+    (!layout.as_os_str().is_empty()).then_some(layout)
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::rename(from, to).is_err() {
+        std::fs::copy(from, to)
+            .with_context(|| format!("failed to move {} to {}", from.display(), to.display()))?;
+        std::fs::remove_file(from)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,299 @@
+//! Layered settings: built-in defaults, the user config, the repo's `.stead-core/config.toml`,
+//! then `STEAD_CORE_*` environment variables. Later layers override individual keys; a
+//! `[redaction]` section replaces the previous one as a whole.
+
+use anyhow::{Context, Result, bail};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use stead_session_adapters::redact::RedactionConfig;
+
+use crate::store::{DEFAULT_BLOB_THRESHOLD, StoreOptions};
+use crate::workspace::ResumeLauncher;
+use crate::{Backend, MergeStrategy, StorageProfile};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub codex: BackendConfig,
+    pub claude: BackendConfig,
+    pub resume: ResumeConfig,
+    pub sync: SyncConfig,
+    pub store: StoreConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redaction: Option<RedactionConfig>,
+    /// Files and environment layers that contributed, lowest precedence first.
+    #[serde(skip)]
+    pub sources: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackendConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_dir: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bin: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResumeConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runner: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge_strategy: Option<MergeStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_profile: Option<StorageProfile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redact: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StoreConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compress: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jsonl: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob_threshold: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob_compress: Option<bool>,
+}
+
+impl Config {
+    /// Loads every layer for `repo`. Without a repo only the user config and environment apply.
+    pub fn load(repo: Option<&Path>) -> Result<Self> {
+        let mut config = Self::default();
+        if let Some(path) = user_config_path()
+            && path.exists()
+        {
+            let base = path.parent().unwrap_or(Path::new("."));
+            config.overlay(Self::from_file(&path)?.resolve_paths(base));
+        }
+        if let Some(repo) = repo {
+            let path = repo_config_path(repo);
+            if path.exists() {
+                config.overlay(Self::from_file(&path)?.resolve_paths(repo));
+            }
+        }
+        config.overlay(Self::from_env()?);
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        let mut config: Self =
+            toml::from_str(&raw).with_context(|| format!("invalid config {}", path.display()))?;
+        config.sources = vec![path.display().to_string()];
+        Ok(config)
+    }
+
+    /// The environment layer: `STEAD_CORE_{CODEX,CLAUDE}_{BASE,BIN}`, `STEAD_CORE_RUNNER`,
+    /// `STEAD_CORE_MERGE_STRATEGY`, `STEAD_CORE_STORAGE_PROFILE`, `STEAD_CORE_REDACT`,
+    /// `STEAD_CORE_COMPRESS`, `STEAD_CORE_BLOB_THRESHOLD` and `STEAD_CORE_BLOB_COMPRESS`.
+    pub fn from_env() -> Result<Self> {
+        let config = Self {
+            codex: BackendConfig {
+                base_dir: env_var("STEAD_CORE_CODEX_BASE").map(PathBuf::from),
+                bin: env_var("STEAD_CORE_CODEX_BIN"),
+            },
+            claude: BackendConfig {
+                base_dir: env_var("STEAD_CORE_CLAUDE_BASE").map(PathBuf::from),
+                bin: env_var("STEAD_CORE_CLAUDE_BIN"),
+            },
+            resume: ResumeConfig {
+                runner: env_var("STEAD_CORE_RUNNER"),
+            },
+            sync: SyncConfig {
+                merge_strategy: env_enum("STEAD_CORE_MERGE_STRATEGY")?,
+                storage_profile: env_enum("STEAD_CORE_STORAGE_PROFILE")?,
+                redact: env_bool("STEAD_CORE_REDACT")?,
+            },
+            store: StoreConfig {
+                compress: env_bool("STEAD_CORE_COMPRESS")?,
+                jsonl: env_bool("STEAD_CORE_JSONL")?,
+                blob_threshold: env_var("STEAD_CORE_BLOB_THRESHOLD")
+                    .map(|value| {
+                        value
+                            .parse()
+                            .with_context(|| format!("invalid STEAD_CORE_BLOB_THRESHOLD `{value}`"))
+                    })
+                    .transpose()?,
+                blob_compress: env_bool("STEAD_CORE_BLOB_COMPRESS")?,
+            },
+            redaction: None,
+            sources: Vec::new(),
+        };
+        let sources = if config == Self::default() {
+            Vec::new()
+        } else {
+            vec!["environment".to_string()]
+        };
+        Ok(Self { sources, ..config })
+    }
+
+    /// Applies `layer` on top of `self`; keys set in `layer` win.
+    pub fn overlay(&mut self, layer: Self) {
+        self.codex.overlay(layer.codex);
+        self.claude.overlay(layer.claude);
+        self.resume.runner = layer.resume.runner.or(self.resume.runner.take());
+        let sync = layer.sync;
+        self.sync.merge_strategy = sync.merge_strategy.or(self.sync.merge_strategy);
+        self.sync.storage_profile = sync.storage_profile.or(self.sync.storage_profile);
+        self.sync.redact = sync.redact.or(self.sync.redact);
+        let store = layer.store;
+        self.store.compress = store.compress.or(self.store.compress);
+        self.store.jsonl = store.jsonl.or(self.store.jsonl);
+        self.store.blob_threshold = store.blob_threshold.or(self.store.blob_threshold);
+        self.store.blob_compress = store.blob_compress.or(self.store.blob_compress);
+        if layer.redaction.is_some() {
+            self.redaction = layer.redaction;
+        }
+        self.sources.extend(layer.sources);
+    }
+
+    pub fn backend(&self, backend: Backend) -> &BackendConfig {
+        match backend {
+            Backend::Codex => &self.codex,
+            Backend::Claude => &self.claude,
+        }
+    }
+
+    pub fn base_dir(&self, backend: Backend) -> Option<&Path> {
+        self.backend(backend).base_dir.as_deref()
+    }
+
+    pub fn merge_strategy(&self) -> MergeStrategy {
+        self.sync.merge_strategy.unwrap_or_default()
+    }
+
+    pub fn storage_profile(&self) -> StorageProfile {
+        self.sync.storage_profile.unwrap_or_default()
+    }
+
+    pub fn redact(&self) -> bool {
+        self.sync.redact.unwrap_or(false)
+    }
+
+    pub fn store_options(&self) -> StoreOptions {
+        StoreOptions {
+            compress: self.store.compress.unwrap_or(false),
+            jsonl: self.store.jsonl.unwrap_or(false),
+            blob_threshold: self.store.blob_threshold.unwrap_or(DEFAULT_BLOB_THRESHOLD),
+            blob_compress: self.store.blob_compress.unwrap_or(false),
+        }
+    }
+
+    pub fn resume_launcher(&self) -> ResumeLauncher {
+        let defaults = ResumeLauncher::default();
+        ResumeLauncher {
+            runner: self.resume.runner.clone(),
+            codex_bin: self.codex.bin.clone().unwrap_or(defaults.codex_bin),
+            claude_bin: self.claude.bin.clone().unwrap_or(defaults.claude_bin),
+        }
+    }
+
+    /// The config with every defaulted key filled in, as printed by `config show`.
+    pub fn effective(&self) -> Self {
+        let launcher = self.resume_launcher();
+        let store = self.store_options();
+        let mut effective = self.clone();
+        effective.codex.bin = Some(launcher.codex_bin);
+        effective.claude.bin = Some(launcher.claude_bin);
+        effective.sync = SyncConfig {
+            merge_strategy: Some(self.merge_strategy()),
+            storage_profile: Some(self.storage_profile()),
+            redact: Some(self.redact()),
+        };
+        effective.store = StoreConfig {
+            compress: Some(store.compress),
+            jsonl: Some(store.jsonl),
+            blob_threshold: Some(store.blob_threshold),
+            blob_compress: Some(store.blob_compress),
+        };
+        effective
+    }
+
+    /// Expands `~/` in base directories and anchors relative ones at `base`.
+    fn resolve_paths(mut self, base: &Path) -> Self {
+        for backend in [&mut self.codex, &mut self.claude] {
+            if let Some(dir) = backend.base_dir.take() {
+                backend.base_dir = Some(expand_path(&dir, base));
+            }
+        }
+        self
+    }
+}
+
+impl BackendConfig {
+    fn overlay(&mut self, layer: Self) {
+        self.base_dir = layer.base_dir.or(self.base_dir.take());
+        self.bin = layer.bin.or(self.bin.take());
+    }
+}
+
+pub fn repo_config_path(repo: &Path) -> PathBuf {
+    repo.join(".stead-core").join("config.toml")
+}
+
+/// `STEAD_CORE_CONFIG`, else `$XDG_CONFIG_HOME/stead-core/config.toml`, else
+/// `~/.config/stead-core/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    if let Some(path) = env_var("STEAD_CORE_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let config_home = env_var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")))?;
+    Some(config_home.join("stead-core").join("config.toml"))
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    env_var("HOME").map(PathBuf::from)
+}
+
+fn expand_path(path: &Path, base: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~")
+        && let Some(home) = home_dir()
+    {
+        return home.join(rest);
+    }
+    if path.is_relative() {
+        base.join(path)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn env_bool(name: &str) -> Result<Option<bool>> {
+    let Some(value) = env_var(name) else {
+        return Ok(None);
+    };
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(Some(true)),
+        "0" | "false" | "no" | "off" => Ok(Some(false)),
+        _ => bail!("invalid {name} `{value}`, expected true or false"),
+    }
+}
+
+/// Accepts the config spelling (`prefer_incoming`) as well as the CLI one (`prefer-incoming`).
+fn env_enum<T: DeserializeOwned>(name: &str) -> Result<Option<T>> {
+    let Some(value) = env_var(name) else {
+        return Ok(None);
+    };
+    serde_json::from_value(serde_json::Value::String(value.replace('-', "_")))
+        .map(Some)
+        .with_context(|| format!("invalid {name} `{value}`"))
+}
@@ -0,0 +1,72 @@
+//! Finds backend home directories that were not configured explicitly.
+
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::Backend;
+use crate::config::{Config, home_dir};
+
+/// Where a backend keeps its sessions when the user has not said otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackendHome {
+    pub backend: Backend,
+    /// Environment variable the backend's own CLI reads to relocate its home.
+    pub env_var: &'static str,
+    /// Default directory name under `$HOME`.
+    pub default_dir: &'static str,
+}
+
+pub const BACKEND_HOMES: &[BackendHome] = &[
+    BackendHome {
+        backend: Backend::Codex,
+        env_var: "CODEX_HOME",
+        default_dir: ".codex",
+    },
+    BackendHome {
+        backend: Backend::Claude,
+        env_var: "CLAUDE_CONFIG_DIR",
+        default_dir: ".claude",
+    },
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiscoveredHome {
+    pub backend: Backend,
+    pub path: PathBuf,
+    /// `config`, the backend's environment variable, or `default`.
+    pub source: String,
+    pub exists: bool,
+}
+
+pub fn backend_home(backend: Backend) -> &'static BackendHome {
+    BACKEND_HOMES
+        .iter()
+        .find(|home| home.backend == backend)
+        .expect("every backend has a registered home")
+}
+
+/// Resolves the home of `backend` from the config, then the backend's own environment variable,
+/// then `~/<default_dir>`. Returns `None` only when nothing is configured and `$HOME` is unset.
+pub fn discover_home(config: &Config, backend: Backend) -> Option<DiscoveredHome> {
+    let home = backend_home(backend);
+    let (path, source) = if let Some(path) = config.base_dir(backend) {
+        (path.to_path_buf(), "config".to_string())
+    } else if let Some(path) = std::env::var_os(home.env_var).filter(|value| !value.is_empty()) {
+        (PathBuf::from(path), home.env_var.to_string())
+    } else {
+        (home_dir()?.join(home.default_dir), "default".to_string())
+    };
+    Some(DiscoveredHome {
+        backend,
+        exists: path.is_dir(),
+        path,
+        source,
+    })
+}
+
+pub fn discover_homes(config: &Config) -> Vec<DiscoveredHome> {
+    BACKEND_HOMES
+        .iter()
+        .filter_map(|home| discover_home(config, home.backend))
+        .collect()
+}
@@ -0,0 +1,337 @@
+//! Health checks behind `stead-core doctor`.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use stead_session_adapters::codex::CodexAdapter;
+
+use crate::archive::archive_location;
+use crate::discovery::{DiscoveredHome, discover_homes};
+use crate::native::{codex_sessions_root, get_native_ref};
+use crate::store::{is_temp_file, parse_canonical_file};
+use crate::{Backend, Config, Store};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Warn => "warn",
+            Self::Fail => "fail",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// Offending files or ids, one per entry.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+            problems: Vec::new(),
+        }
+    }
+
+    fn with_problems(mut self, problems: Vec<String>) -> Self {
+        self.problems = problems;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub backends: Vec<DiscoveredHome>,
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+            .count()
+    }
+}
+
+/// Runs every check against the discovered backend homes and, when given, the store of `repo`.
+pub fn diagnose(config: &Config, repo: Option<&Path>) -> Report {
+    let backends = discover_homes(config);
+    let mut checks = Vec::new();
+    for home in &backends {
+        checks.push(check_home(home));
+    }
+    let launcher = config.resume_launcher();
+    checks.push(check_binary(Backend::Codex, &launcher.codex_bin));
+    checks.push(check_binary(Backend::Claude, &launcher.claude_bin));
+    if let Some(repo) = repo {
+        checks.extend(check_store(&Store::with_options(
+            repo,
+            config.store_options(),
+        )));
+    }
+    if let Some(codex) = backends
+        .iter()
+        .find(|home| home.backend == Backend::Codex && home.exists)
+    {
+        checks.push(check_codex_rollouts(&codex.path));
+    }
+    Report { backends, checks }
+}
+
+fn check_home(home: &DiscoveredHome) -> Check {
+    let name = format!("{} home", home.backend.as_str());
+    let location = format!("{} (from {})", home.path.display(), home.source);
+    if !home.exists {
+        return Check::new(
+            name,
+            CheckStatus::Warn,
+            format!("{location} does not exist"),
+        );
+    }
+    match std::fs::read_dir(&home.path) {
+        Ok(_) => Check::new(name, CheckStatus::Ok, location),
+        Err(err) => Check::new(
+            name,
+            CheckStatus::Fail,
+            format!("{location} is not readable: {err}"),
+        ),
+    }
+}
+
+fn check_binary(backend: Backend, bin: &str) -> Check {
+    let name = format!("{} binary", backend.as_str());
+    let Some(path) = find_executable(bin) else {
+        return Check::new(
+            name,
+            CheckStatus::Warn,
+            format!("`{bin}` not found on PATH"),
+        );
+    };
+    match Command::new(&path).arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            let version = String::from_utf8_lossy(&output.stdout);
+            let version = version.lines().next().unwrap_or("").trim();
+            Check::new(
+                name,
+                CheckStatus::Ok,
+                format!("{} {}", path.display(), version)
+                    .trim_end()
+                    .to_string(),
+            )
+        }
+        Ok(output) => Check::new(
+            name,
+            CheckStatus::Warn,
+            format!("{} --version exited with {}", path.display(), output.status),
+        ),
+        Err(err) => Check::new(
+            name,
+            CheckStatus::Fail,
+            format!("{} could not be run: {err}", path.display()),
+        ),
+    }
+}
+
+fn find_executable(bin: &str) -> Option<PathBuf> {
+    let candidate = Path::new(bin);
+    if candidate.components().count() > 1 {
+        return candidate.is_file().then(|| candidate.to_path_buf());
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(bin))
+        .find(|path| path.is_file())
+}
+
+fn check_store(store: &Store) -> Vec<Check> {
+    let dir = store.sessions_dir();
+    let listed = std::fs::read_dir(&dir).and_then(|entries| {
+        entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<PathBuf>>>()
+    });
+    let Ok(listed) = listed else {
+        return vec![Check::new(
+            "canonical store",
+            CheckStatus::Ok,
+            format!("no store at {}", dir.display()),
+        )];
+    };
+    let (temps, mut files): (Vec<PathBuf>, Vec<PathBuf>) = listed
+        .into_iter()
+        .filter(|path| path.is_file())
+        .partition(|path| is_temp_file(path));
+    files.sort();
+
+    let mut invalid = Vec::new();
+    let mut stale_refs = Vec::new();
+    let mut shared: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut parsed = 0;
+    for path in &files {
+        let session = match parse_canonical_file(path) {
+            Ok(session) => session,
+            Err(err) => {
+                invalid.push(format!("{}: {err:#}", path.display()));
+                continue;
+            }
+        };
+        if let Err(err) = session.validate() {
+            invalid.push(format!("{}: {err}", path.display()));
+            continue;
+        }
+        parsed += 1;
+        let archived = archive_location(&session).is_some();
+        for backend in [Backend::Codex, Backend::Claude] {
+            if !archived
+                && let Some((_, native_path)) = get_native_ref(&session, backend)
+                && !native_path.exists()
+            {
+                stale_refs.push(format!(
+                    "{} {}: {}",
+                    session.session_uid,
+                    backend.as_str(),
+                    native_path.display()
+                ));
+            }
+        }
+        let shared_uid = session
+            .shared_session_uid
+            .clone()
+            .unwrap_or_else(|| session.session_uid.clone());
+        shared
+            .entry(shared_uid)
+            .or_default()
+            .push(session.session_uid);
+    }
+
+    let duplicates: Vec<String> = shared
+        .into_iter()
+        .filter(|(_, sessions)| sessions.len() > 1)
+        .map(|(shared_uid, sessions)| format!("{shared_uid}: {}", sessions.join(", ")))
+        .collect();
+
+    vec![
+        if temps.is_empty() {
+            Check::new(
+                "interrupted writes",
+                CheckStatus::Ok,
+                "no temp files left in the store",
+            )
+        } else {
+            Check::new(
+                "interrupted writes",
+                CheckStatus::Warn,
+                format!(
+                    "{} temp files left by interrupted writes; the next write recovers them",
+                    temps.len()
+                ),
+            )
+            .with_problems(
+                temps
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            )
+        },
+        if invalid.is_empty() {
+            Check::new(
+                "canonical store",
+                CheckStatus::Ok,
+                format!("{parsed} sessions parse and validate"),
+            )
+        } else {
+            Check::new(
+                "canonical store",
+                CheckStatus::Fail,
+                format!("{} of {} files are invalid", invalid.len(), files.len()),
+            )
+            .with_problems(invalid)
+        },
+        if stale_refs.is_empty() {
+            Check::new(
+                "native refs",
+                CheckStatus::Ok,
+                "every native ref points to an existing file",
+            )
+        } else {
+            Check::new(
+                "native refs",
+                CheckStatus::Warn,
+                format!("{} native refs point to missing files", stale_refs.len()),
+            )
+            .with_problems(stale_refs)
+        },
+        if duplicates.is_empty() {
+            Check::new(
+                "shared session uids",
+                CheckStatus::Ok,
+                "no sessions share a shared_session_uid",
+            )
+        } else {
+            Check::new(
+                "shared session uids",
+                CheckStatus::Fail,
+                format!(
+                    "{} shared_session_uids are used by several sessions",
+                    duplicates.len()
+                ),
+            )
+            .with_problems(duplicates)
+        },
+    ]
+}
+
+/// Several rollouts with one native id are what `prune_codex_rollouts_for_native_id` cleans up
+/// after materializing; Codex picks one of them arbitrarily on resume.
+fn check_codex_rollouts(base_dir: &Path) -> Check {
+    let root = codex_sessions_root(base_dir);
+    let sessions = CodexAdapter::from_base_dir(base_dir)
+        .list_sessions()
+        .unwrap_or_default();
+    let mut by_id: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for native in sessions {
+        by_id
+            .entry(native.native_id)
+            .or_default()
+            .push(native.file_path.display().to_string());
+    }
+    let orphaned: Vec<String> = by_id
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|(native_id, mut files)| {
+            files.sort();
+            format!("{native_id}: {}", files.join(", "))
+        })
+        .collect();
+    if orphaned.is_empty() {
+        Check::new(
+            "codex rollouts",
+            CheckStatus::Ok,
+            format!("one rollout per session under {}", root.display()),
+        )
+    } else {
+        Check::new(
+            "codex rollouts",
+            CheckStatus::Warn,
+            format!("{} sessions have several rollouts", orphaned.len()),
+        )
+        .with_problems(orphaned)
+    }
+}
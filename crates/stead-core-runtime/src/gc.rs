@@ -0,0 +1,323 @@
+//! Pruning of the canonical store, its blob directory and stale native projections.
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use stead_session_adapters::blobs::referenced_blobs;
+use stead_session_adapters::claude::ClaudeAdapter;
+use stead_session_adapters::codex::CodexAdapter;
+use stead_session_model::SteadSession;
+
+use crate::Backend;
+use crate::archive::archive_location;
+use crate::journal::snapshot_sessions;
+use crate::native::{deterministic_backend_uuid, get_native_ref, normalize_path};
+use crate::store::{parse_canonical_file, session_uid_aliases};
+use crate::workspace::Workspace;
+
+pub const DEFAULT_GC_AGE_DAYS: i64 = 30;
+
+#[derive(Debug, Clone)]
+pub struct GcOptions {
+    /// Sessions updated more recently than this are kept even when their native files are gone.
+    pub older_than: Duration,
+    /// Backend homes scanned for stale projections; `None` skips that backend.
+    pub codex_base: Option<PathBuf>,
+    pub claude_base: Option<PathBuf>,
+    pub dry_run: bool,
+}
+
+impl Default for GcOptions {
+    fn default() -> Self {
+        Self {
+            older_than: Duration::days(DEFAULT_GC_AGE_DAYS),
+            codex_base: None,
+            claude_base: None,
+            dry_run: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GcReport {
+    pub dry_run: bool,
+    pub sessions: Vec<RemovedSession>,
+    pub projections: Vec<RemovedProjection>,
+    pub aliases: Vec<CollapsedAlias>,
+    pub blobs: Vec<RemovedBlob>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemovedSession {
+    pub session_uid: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemovedProjection {
+    pub backend: Backend,
+    pub native_id: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CollapsedAlias {
+    pub session_uid: String,
+    pub alias: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemovedBlob {
+    pub sha256: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+impl Workspace {
+    /// Removes abandoned sessions, stale projections, redundant aliases and unreferenced blobs.
+    /// With `dry_run` nothing is written and the report lists what would go.
+    pub fn gc(&self, options: &GcOptions) -> Result<GcReport> {
+        let store = self.store();
+        let _lock = store.lock()?;
+        let mut stored = Vec::new();
+        for path in store.session_files()? {
+            let session = parse_canonical_file(&path)?;
+            stored.push((path, session));
+        }
+
+        let mut report = GcReport {
+            dry_run: options.dry_run,
+            ..GcReport::default()
+        };
+
+        let removed = abandoned_sessions(&stored, Utc::now() - options.older_than);
+        let (removed, kept): (Vec<_>, Vec<_>) = stored
+            .into_iter()
+            .partition(|(_, session)| removed.contains(&session.session_uid));
+        for (path, session) in removed {
+            if !options.dry_run {
+                std::fs::remove_file(&path)?;
+            }
+            report.sessions.push(RemovedSession {
+                session_uid: session.session_uid,
+                path,
+            });
+        }
+
+        for (backend, base_dir) in [
+            (Backend::Codex, options.codex_base.as_deref()),
+            (Backend::Claude, options.claude_base.as_deref()),
+        ] {
+            let Some(base_dir) = base_dir else {
+                continue;
+            };
+            for projection in stale_projections(&kept, backend, base_dir)? {
+                if !options.dry_run {
+                    std::fs::remove_file(&projection.path)?;
+                }
+                report.projections.push(projection);
+            }
+        }
+
+        let uids: BTreeSet<&str> = kept
+            .iter()
+            .map(|(_, session)| session.session_uid.as_str())
+            .collect();
+        for (_, session) in &kept {
+            let (stale, remaining) = split_stale_aliases(session, &uids);
+            if stale.is_empty() {
+                continue;
+            }
+            if !options.dry_run {
+                let mut collapsed = session.clone();
+                collapsed.extensions.insert(
+                    "session_uid_aliases".to_string(),
+                    serde_json::json!(remaining),
+                );
+                if remaining.is_empty() {
+                    collapsed.extensions.remove("session_uid_aliases");
+                }
+                store.save(&collapsed)?;
+            }
+            report
+                .aliases
+                .extend(stale.into_iter().map(|alias| CollapsedAlias {
+                    session_uid: session.session_uid.clone(),
+                    alias,
+                }));
+        }
+
+        // Sessions a rollback could bring back need their blobs as much as the stored ones.
+        let snapshots = snapshot_sessions(store)?;
+        let mut referenced = BTreeSet::new();
+        for session in kept.iter().map(|(_, session)| session).chain(&snapshots) {
+            referenced.extend(referenced_blobs(session));
+            referenced.extend(session.artifacts.iter().filter_map(|artifact| {
+                artifact
+                    .extensions
+                    .get("content_blob")
+                    .and_then(|sha| sha.as_str())
+                    .map(str::to_string)
+            }));
+        }
+        for blob in unreferenced_blobs(&store.blobs().root, &referenced)? {
+            if !options.dry_run {
+                std::fs::remove_file(&blob.path)?;
+            }
+            report.blobs.push(blob);
+        }
+        Ok(report)
+    }
+}
+
+/// Sessions that had native files, lost all of them and were last updated before `cutoff`.
+/// Archived sessions and sessions still named as a fork parent or root by a surviving session are kept.
+fn abandoned_sessions(
+    stored: &[(PathBuf, SteadSession)],
+    cutoff: chrono::DateTime<Utc>,
+) -> BTreeSet<String> {
+    let mut removed: BTreeSet<String> = stored
+        .iter()
+        .filter(|(_, session)| {
+            session.metadata.updated_at < cutoff && archive_location(session).is_none()
+        })
+        .filter(|(_, session)| {
+            let native: Vec<PathBuf> = [Backend::Codex, Backend::Claude]
+                .into_iter()
+                .filter_map(|backend| get_native_ref(session, backend).map(|(_, path)| path))
+                .collect();
+            !native.is_empty()
+                && native
+                    .into_iter()
+                    .chain(session.source.source_files.iter().map(PathBuf::from))
+                    .all(|path| !path.exists())
+        })
+        .map(|(_, session)| session.session_uid.clone())
+        .collect();
+    loop {
+        let needed: BTreeSet<String> = stored
+            .iter()
+            .filter(|(_, session)| !removed.contains(&session.session_uid))
+            .filter_map(|(_, session)| session.lineage.as_ref())
+            .flat_map(|lineage| {
+                [
+                    lineage.parent_session_uid.clone(),
+                    lineage.root_session_uid.clone(),
+                ]
+            })
+            .flatten()
+            .filter(|uid| removed.contains(uid))
+            .collect();
+        if needed.is_empty() {
+            return removed;
+        }
+        removed.retain(|uid| !needed.contains(uid));
+    }
+}
+
+/// Native files under `base_dir` that stead-core projected but no stored session points at any
+/// more: older copies of a referenced native id, and projections carrying a session's derived id.
+fn stale_projections(
+    kept: &[(PathBuf, SteadSession)],
+    backend: Backend,
+    base_dir: &Path,
+) -> Result<Vec<RemovedProjection>> {
+    let natives = match backend {
+        Backend::Codex => CodexAdapter::from_base_dir(base_dir).list_sessions()?,
+        Backend::Claude => ClaudeAdapter::from_base_dir(base_dir).list_sessions()?,
+    };
+    let mut referenced_paths = BTreeSet::new();
+    let mut live_ids: BTreeMap<String, bool> = BTreeMap::new();
+    let mut derived_ids = BTreeSet::new();
+    for (_, session) in kept {
+        if let Some((native_id, path)) = get_native_ref(session, backend) {
+            let exists = path.exists();
+            referenced_paths.insert(normalize_path(&path));
+            *live_ids.entry(native_id).or_default() |= exists;
+        }
+        referenced_paths.extend(
+            session
+                .source
+                .source_files
+                .iter()
+                .map(|path| normalize_path(Path::new(path))),
+        );
+        derived_ids.insert(deterministic_backend_uuid(session, backend).to_string());
+    }
+
+    let mut out = Vec::new();
+    for native in natives {
+        if referenced_paths.contains(&normalize_path(&native.file_path)) {
+            continue;
+        }
+        let superseded = live_ids.get(&native.native_id).copied().unwrap_or(false);
+        if superseded || derived_ids.contains(&native.native_id) {
+            out.push(RemovedProjection {
+                backend,
+                native_id: native.native_id,
+                path: native.file_path,
+            });
+        }
+    }
+    out.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(out)
+}
+
+/// Splits the aliases of `session` into stale ones (duplicates, its own uids, or the uid of
+/// another stored session, which lookups resolve directly) and the ones worth keeping.
+fn split_stale_aliases(
+    session: &SteadSession,
+    stored_uids: &BTreeSet<&str>,
+) -> (Vec<String>, Vec<String>) {
+    let shared = session
+        .shared_session_uid
+        .as_deref()
+        .unwrap_or(session.session_uid.as_str());
+    let mut stale = Vec::new();
+    let mut remaining: Vec<String> = Vec::new();
+    for alias in session_uid_aliases(session) {
+        if alias == session.session_uid
+            || alias == shared
+            || stored_uids.contains(alias.as_str())
+            || remaining.contains(&alias)
+        {
+            stale.push(alias);
+        } else {
+            remaining.push(alias);
+        }
+    }
+    (stale, remaining)
+}
+
+fn unreferenced_blobs(root: &Path, referenced: &BTreeSet<String>) -> Result<Vec<RemovedBlob>> {
+    let mut out = Vec::new();
+    let Ok(prefixes) = std::fs::read_dir(root) else {
+        return Ok(out);
+    };
+    for prefix in prefixes {
+        let prefix = prefix?.path();
+        if !prefix.is_dir() {
+            continue;
+        }
+        for entry in std::fs::read_dir(&prefix)? {
+            let entry = entry?;
+            let path = entry.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let sha256 = name.trim_end_matches(".zst");
+            if referenced.contains(sha256) {
+                continue;
+            }
+            out.push(RemovedBlob {
+                sha256: sha256.to_string(),
+                size: entry.metadata()?.len(),
+                path,
+            });
+        }
+    }
+    out.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(out)
+}
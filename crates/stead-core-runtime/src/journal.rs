@@ -0,0 +1,297 @@
+//! The sync journal under `.stead-core/journal/`: what each recent sync did to the store, with the
+//! previous version of every session file it changed so the sync can be rolled back.
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use stead_session_adapters::sha256_hex;
+use stead_session_model::SteadSession;
+
+use crate::store::{Store, parse_canonical_file, session_file_variants, write_atomic};
+use crate::workspace::{SyncEntry, Workspace};
+
+/// Number of syncs kept in the journal; older entries are dropped.
+pub const SYNC_JOURNAL_LIMIT: usize = 10;
+
+const JOURNAL_FILE: &str = "journal.json";
+const BEFORE_DIR: &str = "before";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub sessions: Vec<SyncEntry>,
+    pub changes: Vec<StoreChange>,
+}
+
+/// A session file the sync created, rewrote or deleted, named relative to the sessions directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreChange {
+    pub file: String,
+    pub kind: ChangeKind,
+    /// Hash of the file as the sync left it; `None` for removed files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RolledBack {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    /// Session files put back to their state before the sync.
+    pub restored: Vec<PathBuf>,
+    /// Session files the sync had created.
+    pub removed: Vec<PathBuf>,
+}
+
+pub fn journal_dir(store: &Store) -> PathBuf {
+    store.repo().join(".stead-core").join("journal")
+}
+
+/// Journal entries, oldest first.
+pub fn journal_entries(store: &Store) -> Result<Vec<JournalEntry>> {
+    let mut entries = Vec::new();
+    for dir in entry_dirs(store)? {
+        let path = dir.join(JOURNAL_FILE);
+        let raw = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        entries.push(
+            serde_json::from_str(&raw)
+                .with_context(|| format!("invalid journal entry {}", path.display()))?,
+        );
+    }
+    Ok(entries)
+}
+
+/// The session files the journal keeps to roll syncs back, so the blobs they reference stay
+/// alive.
+pub(crate) fn snapshot_sessions(store: &Store) -> Result<Vec<SteadSession>> {
+    let mut sessions = Vec::new();
+    for dir in entry_dirs(store)? {
+        let Ok(listed) = std::fs::read_dir(dir.join(BEFORE_DIR)) else {
+            continue;
+        };
+        for entry in listed {
+            let path = entry?.path();
+            if path.is_file() {
+                sessions.push(parse_canonical_file(&path)?);
+            }
+        }
+    }
+    Ok(sessions)
+}
+
+/// Journal directories in id order, skipping ones a crash left without a `journal.json`.
+fn entry_dirs(store: &Store) -> Result<Vec<PathBuf>> {
+    let Ok(listed) = std::fs::read_dir(journal_dir(store)) else {
+        return Ok(Vec::new());
+    };
+    let mut dirs = Vec::new();
+    for entry in listed {
+        let path = entry?.path();
+        if path.join(JOURNAL_FILE).is_file() {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// A sync in progress: every session file it writes is first hard-linked (or copied) into the
+/// journal. Session files are replaced by rename, so the links keep the old contents.
+pub(crate) struct PendingSync {
+    id: String,
+    dir: PathBuf,
+    started_at: DateTime<Utc>,
+    /// Session files the sync may have written or deleted.
+    touched: BTreeSet<String>,
+    /// Snapshots of the touched files that existed before the sync.
+    before: BTreeMap<String, PathBuf>,
+}
+
+impl PendingSync {
+    /// Called with the store lock held, so journal directories without a `journal.json` belong
+    /// to syncs that never finished and are removed.
+    pub(crate) fn begin(store: &Store) -> Result<Self> {
+        if let Ok(listed) = std::fs::read_dir(journal_dir(store)) {
+            for entry in listed {
+                let path = entry?.path();
+                if path.is_dir() && !path.join(JOURNAL_FILE).is_file() {
+                    std::fs::remove_dir_all(&path)?;
+                }
+            }
+        }
+        let started_at = Utc::now();
+        let id = started_at.format("%Y%m%dT%H%M%S%.6fZ").to_string();
+        let dir = journal_dir(store).join(&id);
+        std::fs::create_dir_all(dir.join(BEFORE_DIR))?;
+        Ok(Self {
+            id,
+            dir,
+            started_at,
+            touched: BTreeSet::new(),
+            before: BTreeMap::new(),
+        })
+    }
+
+    /// [Saves](Store::save) `session` after snapshotting the files the save may replace or delete.
+    pub(crate) fn save(&mut self, store: &Store, session: &SteadSession) -> Result<PathBuf> {
+        for path in session_file_variants(&store.session_path(&session.session_uid)) {
+            self.snapshot(&path)?;
+        }
+        store.save(session)
+    }
+
+    fn snapshot(&mut self, path: &Path) -> Result<()> {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            return Ok(());
+        };
+        if !self.touched.insert(name.to_string()) || !path.is_file() {
+            return Ok(());
+        }
+        let saved = self.dir.join(BEFORE_DIR).join(name);
+        if std::fs::hard_link(path, &saved).is_err() {
+            std::fs::copy(path, &saved)?;
+        }
+        self.before.insert(name.to_string(), saved);
+        Ok(())
+    }
+
+    /// Records what changed in the touched files, drops unchanged snapshots and trims the journal
+    /// to [`SYNC_JOURNAL_LIMIT`] entries.
+    pub(crate) fn finish(self, store: &Store, sessions: &[SyncEntry]) -> Result<JournalEntry> {
+        let sessions_dir = store.sessions_dir();
+        let mut changes = Vec::new();
+        for name in &self.touched {
+            let after = read_if_exists(&sessions_dir.join(name))?;
+            let before = self.before.get(name);
+            let kind = match (before, &after) {
+                (None, None) => continue,
+                (None, Some(_)) => ChangeKind::Created,
+                (Some(_), None) => ChangeKind::Removed,
+                (Some(saved), Some(after)) => {
+                    if std::fs::read(saved)? == *after {
+                        std::fs::remove_file(saved)?;
+                        continue;
+                    }
+                    ChangeKind::Modified
+                }
+            };
+            changes.push(StoreChange {
+                file: name.clone(),
+                kind,
+                sha256: after.as_deref().map(sha256_hex),
+            });
+        }
+
+        let entry = JournalEntry {
+            id: self.id,
+            started_at: self.started_at,
+            finished_at: Utc::now(),
+            sessions: sessions.to_vec(),
+            changes,
+        };
+        write_atomic(
+            &self.dir.join(JOURNAL_FILE),
+            serde_json::to_string_pretty(&entry)?.as_bytes(),
+        )?;
+        let dirs = entry_dirs(store)?;
+        for stale in &dirs[..dirs.len().saturating_sub(SYNC_JOURNAL_LIMIT)] {
+            std::fs::remove_dir_all(stale)?;
+        }
+        Ok(entry)
+    }
+}
+
+impl Workspace {
+    /// Undoes the most recent journaled sync: session files it rewrote or deleted get their old
+    /// contents back and files it created are removed. Rolling back again undoes the sync before.
+    /// Nothing is touched if any of those files changed after the sync.
+    pub fn rollback_sync(&self) -> Result<RolledBack> {
+        let store = self.store();
+        let _lock = store.lock()?;
+        let Some(dir) = entry_dirs(store)?.pop() else {
+            bail!("no sync to roll back in {}", journal_dir(store).display());
+        };
+        let path = dir.join(JOURNAL_FILE);
+        let entry: JournalEntry = serde_json::from_str(&std::fs::read_to_string(&path)?)
+            .with_context(|| format!("invalid journal entry {}", path.display()))?;
+
+        let sessions_dir = store.sessions_dir();
+        let mut rolled_back = RolledBack {
+            id: entry.id,
+            started_at: entry.started_at,
+            restored: Vec::new(),
+            removed: Vec::new(),
+        };
+        let mut changed_since = Vec::new();
+        for change in &entry.changes {
+            let current = read_if_exists(&sessions_dir.join(safe_name(&change.file)?))?;
+            let unchanged = match change.kind {
+                ChangeKind::Removed => current.is_none(),
+                ChangeKind::Created | ChangeKind::Modified => {
+                    current.as_deref().map(sha256_hex) == change.sha256
+                }
+            };
+            if !unchanged {
+                changed_since.push(change.file.as_str());
+            }
+        }
+        if !changed_since.is_empty() {
+            bail!(
+                "cannot roll back sync {}: session files changed after it: {}",
+                rolled_back.id,
+                changed_since.join(", ")
+            );
+        }
+        for change in &entry.changes {
+            let target = sessions_dir.join(safe_name(&change.file)?);
+            match change.kind {
+                ChangeKind::Created => {
+                    if target.exists() {
+                        std::fs::remove_file(&target)?;
+                    }
+                    rolled_back.removed.push(target);
+                }
+                ChangeKind::Modified | ChangeKind::Removed => {
+                    let saved = dir.join(BEFORE_DIR).join(&change.file);
+                    let bytes = std::fs::read(&saved)
+                        .with_context(|| format!("missing snapshot {}", saved.display()))?;
+                    std::fs::create_dir_all(&sessions_dir)?;
+                    write_atomic(&target, &bytes)?;
+                    rolled_back.restored.push(target);
+                }
+            }
+        }
+        std::fs::remove_dir_all(&dir)?;
+        Ok(rolled_back)
+    }
+}
+
+fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+/// Journal file names are plain file names inside the sessions directory.
+fn safe_name(file: &str) -> Result<&Path> {
+    let path = Path::new(file);
+    if path.components().count() != 1 || path.file_name().is_none() {
+        bail!("invalid file name `{file}` in sync journal");
+    }
+    Ok(path)
+}
@@ -0,0 +1,79 @@
+pub mod archive;
+pub mod config;
+pub mod discovery;
+pub mod doctor;
+pub mod gc;
+pub mod journal;
+pub mod merge;
+pub mod native;
+pub mod store;
+pub mod workspace;
+
+use serde::{Deserialize, Serialize};
+use stead_session_model::BackendKind;
+
+pub use archive::{Archived, Restored};
+pub use config::Config;
+pub use gc::{GcOptions, GcReport};
+pub use journal::{JournalEntry, RolledBack};
+pub use store::{Store, StoreOptions};
+pub use workspace::{
+    Handoff, Materialized, ResumeLauncher, SyncEntry, SyncOptions, SyncOutcome, Workspace,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    Codex,
+    Claude,
+}
+
+impl Backend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Codex => "codex",
+            Self::Claude => "claude",
+        }
+    }
+
+    pub fn from_kind(kind: BackendKind) -> Self {
+        match kind {
+            BackendKind::Codex => Self::Codex,
+            BackendKind::ClaudeCode => Self::Claude,
+        }
+    }
+
+    pub fn kind(&self) -> BackendKind {
+        match self {
+            Self::Codex => BackendKind::Codex,
+            Self::Claude => BackendKind::ClaudeCode,
+        }
+    }
+
+    pub fn other(&self) -> Self {
+        match self {
+            Self::Codex => Self::Claude,
+            Self::Claude => Self::Codex,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    PreferAnchor,
+    #[default]
+    PreferIncoming,
+    BranchOnDivergence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum StorageProfile {
+    #[default]
+    Full,
+    Compact,
+}
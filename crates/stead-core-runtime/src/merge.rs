@@ -0,0 +1,356 @@
+use anyhow::Result;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use stead_session_adapters::artifacts::attach_artifacts;
+use stead_session_model::{EventKind, SteadEvent, SteadSession, event_fingerprint};
+use uuid::Uuid;
+
+use crate::native::{get_native_ref, projected_through};
+use crate::store::{add_session_uid_alias, session_uid_aliases};
+use crate::{Backend, MergeStrategy};
+
+pub struct MergeOutcome {
+    pub session: SteadSession,
+    pub conflicts: Vec<Value>,
+    pub branch: Option<SteadSession>,
+    /// The peer backend's divergent tail, split off the anchor after a handoff.
+    pub sibling: Option<(Backend, SteadSession)>,
+}
+
+/// Merges a freshly imported projection into its stored anchor. When the two timelines diverged
+/// (always checked after a handoff, otherwise only for `BranchOnDivergence`) the incoming tail is
+/// split into a branch session instead.
+pub fn merge_sessions(
+    mut anchor: SteadSession,
+    mut incoming: SteadSession,
+    strategy: MergeStrategy,
+) -> Result<MergeOutcome> {
+    // Artifact refs are derived; they are stripped here and re-attached to every result.
+    for session in [&mut anchor, &mut incoming] {
+        session
+            .events
+            .retain(|event| event.kind != EventKind::ArtifactRef);
+    }
+    let incoming_backend = Backend::from_kind(incoming.source.backend);
+    let peer_backend = incoming_backend.other();
+    let handoff = get_native_ref(&anchor, peer_backend).is_some();
+    if (handoff || strategy == MergeStrategy::BranchOnDivergence)
+        && let Some(divergence) = find_divergence(
+            &anchor,
+            &incoming,
+            handoff
+                .then(|| projected_through(&anchor, incoming_backend))
+                .flatten(),
+        )
+    {
+        let origin = anchor.events[divergence.origin_index].event_uid.clone();
+        let lineage_strategy = if handoff {
+            "handoff_divergence"
+        } else {
+            "branch_on_divergence"
+        };
+        let anchor_only_count = divergence.anchor_only.len();
+        let incoming_count = divergence.incoming_after_prefix.len();
+        let mut branch = branch_from(
+            &anchor,
+            &origin,
+            lineage_strategy,
+            divergence.incoming_after_prefix,
+        )?;
+        branch.metadata.updated_at = branch.metadata.updated_at.max(incoming.metadata.updated_at);
+        merge_redaction_records(&mut branch, &incoming);
+        upsert_backend_raw_lines(&mut branch.raw_vendor_payload, &incoming);
+
+        let sibling = if handoff {
+            let sibling = branch_from(&anchor, &origin, lineage_strategy, divergence.anchor_only)?;
+            anchor.events.truncate(divergence.origin_index + 1);
+            anchor.metadata.updated_at = anchor.events[divergence.origin_index].timestamp;
+            Some((peer_backend, sibling))
+        } else {
+            None
+        };
+        attach_artifacts(&mut anchor);
+        let mut conflict = json!({
+            "type": lineage_strategy,
+            "fork_origin_event_uid": origin,
+            "anchor_only_events": anchor_only_count,
+            "incoming_only_events": incoming_count,
+            "branch_session_uid": branch.session_uid,
+            "sibling_session_uid": sibling.as_ref().map(|(_, session)| session.session_uid.clone())
+        });
+        // Handoff timelines are never interleaved, so the requested strategy did not apply.
+        if handoff && strategy != MergeStrategy::BranchOnDivergence {
+            conflict["overrides_strategy"] = json!(strategy);
+        }
+        let conflicts = vec![conflict];
+        return Ok(MergeOutcome {
+            session: anchor,
+            conflicts,
+            branch: Some(branch),
+            sibling,
+        });
+    }
+
+    let anchor_shared = anchor
+        .shared_session_uid
+        .clone()
+        .unwrap_or_else(|| anchor.session_uid.clone());
+    anchor.shared_session_uid = Some(anchor_shared);
+
+    if incoming.session_uid != anchor.session_uid {
+        add_session_uid_alias(&mut anchor, &incoming.session_uid);
+    }
+    for alias in session_uid_aliases(&incoming) {
+        add_session_uid_alias(&mut anchor, &alias);
+    }
+    if let Some(shared) = incoming.shared_session_uid.as_ref()
+        && shared != &anchor.session_uid
+    {
+        add_session_uid_alias(&mut anchor, shared);
+    }
+
+    if incoming.metadata.created_at < anchor.metadata.created_at {
+        anchor.metadata.created_at = incoming.metadata.created_at;
+    }
+    if incoming.metadata.updated_at > anchor.metadata.updated_at {
+        anchor.metadata.updated_at = incoming.metadata.updated_at;
+    }
+    if anchor.metadata.title.is_none() {
+        anchor.metadata.title = incoming.metadata.title.clone();
+    }
+    if anchor.metadata.project_root == "/unknown" && incoming.metadata.project_root != "/unknown" {
+        anchor.metadata.project_root = incoming.metadata.project_root.clone();
+    }
+
+    let mut source_files: Vec<String> = anchor
+        .source
+        .source_files
+        .iter()
+        .chain(incoming.source.source_files.iter())
+        .cloned()
+        .collect();
+    source_files = dedupe_strings(source_files);
+    anchor.source.source_files = source_files;
+    merge_redaction_records(&mut anchor, &incoming);
+    upsert_backend_raw_lines(&mut anchor.raw_vendor_payload, &incoming);
+
+    let mut index_by_key: HashMap<String, usize> = HashMap::new();
+    // After a handoff the peer backend re-imports the projected events under its own uids, so
+    // those are matched by content; each anchor event absorbs at most one incoming copy.
+    let mut unmatched_by_content: HashMap<String, usize> = HashMap::new();
+    if handoff {
+        for event in &anchor.events {
+            *unmatched_by_content
+                .entry(event_content_key(event))
+                .or_default() += 1;
+        }
+    }
+    let mut merged_events: Vec<SteadEvent> = Vec::new();
+    let mut conflicts = Vec::new();
+    let anchor_len = anchor.events.len();
+    for (position, event) in anchor.events.into_iter().chain(incoming.events).enumerate() {
+        let from_incoming = position >= anchor_len;
+        let key = event_merge_key(&event);
+        if let Some(index) = index_by_key.get(&key).copied() {
+            let existing = &merged_events[index];
+            if from_incoming && (existing.payload != event.payload || existing.actor != event.actor)
+            {
+                let kept = if strategy == MergeStrategy::PreferAnchor {
+                    "anchor"
+                } else {
+                    "incoming"
+                };
+                conflicts.push(json!({
+                    "type": "event_conflict",
+                    "event_uid": event.event_uid,
+                    "stream_id": event.stream_id,
+                    "kept": kept
+                }));
+                if strategy == MergeStrategy::PreferAnchor {
+                    continue;
+                }
+            }
+            merged_events[index] = event;
+        } else if from_incoming
+            && let Some(unmatched) = unmatched_by_content
+                .get_mut(&event_content_key(&event))
+                .filter(|unmatched| **unmatched > 0)
+        {
+            *unmatched -= 1;
+        } else {
+            index_by_key.insert(key, merged_events.len());
+            merged_events.push(event);
+        }
+    }
+    stead_session_model::canonical_sort_events(&mut merged_events);
+    anchor.events = merged_events;
+    attach_artifacts(&mut anchor);
+    Ok(MergeOutcome {
+        session: anchor,
+        conflicts,
+        branch: None,
+        sibling: None,
+    })
+}
+
+fn merge_redaction_records(anchor: &mut SteadSession, incoming: &SteadSession) {
+    let Some(records) = incoming
+        .extensions
+        .get("redactions")
+        .and_then(Value::as_array)
+    else {
+        return;
+    };
+    let entry = anchor
+        .extensions
+        .entry("redactions".to_string())
+        .or_insert_with(|| json!([]));
+    if !entry.is_array() {
+        *entry = json!([]);
+    }
+    if let Some(existing) = entry.as_array_mut() {
+        for record in records {
+            if !existing.contains(record) {
+                existing.push(record.clone());
+            }
+        }
+    }
+}
+
+fn branch_from(
+    anchor: &SteadSession,
+    origin: &str,
+    lineage_strategy: &str,
+    events: Vec<SteadEvent>,
+) -> Result<SteadSession> {
+    let mut branch = anchor.fork_at(origin, &Uuid::new_v4().to_string())?;
+    if let Some(lineage) = branch.lineage.as_mut() {
+        lineage.strategy = Some(lineage_strategy.to_string());
+    }
+    branch.events.extend(events);
+    stead_session_model::canonical_sort_events(&mut branch.events);
+    if let Some(last) = branch.events.last() {
+        branch.metadata.updated_at = last.timestamp;
+    }
+    attach_artifacts(&mut branch);
+    Ok(branch)
+}
+
+fn event_merge_key(event: &SteadEvent) -> String {
+    format!(
+        "{}|{}|{}|{:?}",
+        event.stream_id, event.event_uid, event.timestamp, event.kind
+    )
+}
+
+fn event_content_key(event: &SteadEvent) -> String {
+    format!("{}|{}", event.stream_id, event_fingerprint(event))
+}
+
+struct Divergence {
+    origin_index: usize,
+    anchor_only: Vec<SteadEvent>,
+    incoming_after_prefix: Vec<SteadEvent>,
+}
+
+fn find_divergence(
+    anchor: &SteadSession,
+    incoming: &SteadSession,
+    projected_through: Option<&str>,
+) -> Option<Divergence> {
+    let keys = |events: &[SteadEvent]| -> (HashSet<String>, HashSet<String>) {
+        (
+            events.iter().map(event_merge_key).collect(),
+            events.iter().map(event_content_key).collect(),
+        )
+    };
+    let (anchor_keys, anchor_contents) = keys(&anchor.events);
+    let (incoming_keys, incoming_contents) = keys(&incoming.events);
+    let in_incoming = |event: &SteadEvent| {
+        incoming_keys.contains(&event_merge_key(event))
+            || incoming_contents.contains(&event_content_key(event))
+    };
+
+    // Anchor events the incoming projection was materialized with are not divergence even if
+    // the backend could not represent them.
+    let known = projected_through
+        .and_then(|uid| {
+            anchor
+                .events
+                .iter()
+                .position(|event| event.event_uid == uid)
+        })
+        .map_or(0, |index| index + 1);
+    let first_anchor_only = known
+        + anchor.events[known..]
+            .iter()
+            .position(|event| !in_incoming(event))?;
+    if first_anchor_only == 0
+        || incoming.events.iter().all(|event| {
+            anchor_keys.contains(&event_merge_key(event))
+                || anchor_contents.contains(&event_content_key(event))
+        })
+    {
+        return None;
+    }
+
+    let (prefix_keys, prefix_contents) = keys(&anchor.events[..first_anchor_only]);
+    Some(Divergence {
+        origin_index: first_anchor_only - 1,
+        anchor_only: anchor.events[first_anchor_only..]
+            .iter()
+            .filter(|event| !in_incoming(event))
+            .cloned()
+            .collect(),
+        incoming_after_prefix: incoming
+            .events
+            .iter()
+            .filter(|event| {
+                !prefix_keys.contains(&event_merge_key(event))
+                    && !prefix_contents.contains(&event_content_key(event))
+            })
+            .cloned()
+            .collect(),
+    })
+}
+
+fn dedupe_strings(values: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for value in values {
+        if seen.insert(value.clone()) {
+            out.push(value);
+        }
+    }
+    out
+}
+
+fn upsert_backend_raw_lines(anchor_raw: &mut Value, incoming: &SteadSession) {
+    let Some(lines) = incoming
+        .raw_vendor_payload
+        .get("lines")
+        .and_then(|v| v.as_array())
+        .cloned()
+    else {
+        return;
+    };
+
+    if !anchor_raw.is_object() {
+        *anchor_raw = json!({});
+    }
+    let Some(raw_obj) = anchor_raw.as_object_mut() else {
+        return;
+    };
+    let backend_lines = raw_obj
+        .entry("backend_lines".to_string())
+        .or_insert_with(|| json!({}));
+    if !backend_lines.is_object() {
+        *backend_lines = json!({});
+    }
+    if let Some(map) = backend_lines.as_object_mut() {
+        map.insert(
+            incoming.source.backend.as_str().to_string(),
+            Value::Array(lines),
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! Bookkeeping for `extensions.native_refs`, which records where a canonical session was
+//! projected into each backend.
+
+use anyhow::Result;
+use chrono::{Datelike, Utc};
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+use stead_session_adapters::NativeSessionRef;
+use stead_session_adapters::remap::RootMapping;
+use stead_session_model::{EventKind, SteadSession};
+use uuid::Uuid;
+
+use crate::Backend;
+
+pub fn set_native_ref(session: &mut SteadSession, backend: Backend, native_id: &str, path: &Path) {
+    let key = backend.as_str();
+    let entry = session
+        .extensions
+        .entry("native_refs".to_string())
+        .or_insert_with(|| json!({}));
+    if !entry.is_object() {
+        *entry = json!({});
+    }
+    if let Some(map) = entry.as_object_mut() {
+        let same_session = map.get(key).filter(|existing| {
+            existing.get("session_id").and_then(Value::as_str) == Some(native_id)
+        });
+        let mut native_ref = json!({
+            "session_id": native_id,
+            "path": path.display().to_string()
+        });
+        for preserved in ["projected_through", "root_map"] {
+            if let Some(value) = same_session.and_then(|existing| existing.get(preserved)) {
+                native_ref[preserved] = value.clone();
+            }
+        }
+        map.insert(key.to_string(), native_ref);
+    }
+}
+
+pub fn get_native_ref(session: &SteadSession, backend: Backend) -> Option<(String, PathBuf)> {
+    let refs = session.extensions.get("native_refs")?.as_object()?;
+    let selected = refs.get(backend.as_str())?.as_object()?;
+    let session_id = selected.get("session_id")?.as_str()?.to_string();
+    let path = selected.get("path")?.as_str()?.to_string();
+    Some((session_id, PathBuf::from(path)))
+}
+
+pub fn remove_native_ref(session: &mut SteadSession, backend: Backend) {
+    if let Some(map) = session
+        .extensions
+        .get_mut("native_refs")
+        .and_then(Value::as_object_mut)
+    {
+        map.remove(backend.as_str());
+    }
+}
+
+pub fn mark_projected_through(session: &mut SteadSession, backend: Backend) {
+    let Some(last) = session
+        .events
+        .iter()
+        .rfind(|event| event.kind != EventKind::ArtifactRef)
+        .map(|event| event.event_uid.clone())
+    else {
+        return;
+    };
+    if let Some(native_ref) = native_ref_mut(session, backend) {
+        native_ref.insert("projected_through".to_string(), Value::String(last));
+    }
+}
+
+pub fn projected_through(session: &SteadSession, backend: Backend) -> Option<&str> {
+    session
+        .extensions
+        .get("native_refs")?
+        .get(backend.as_str())?
+        .get("projected_through")?
+        .as_str()
+}
+
+pub fn set_native_root_map(session: &mut SteadSession, backend: Backend, mappings: &[RootMapping]) {
+    if let Some(native_ref) = native_ref_mut(session, backend) {
+        if mappings.is_empty() {
+            native_ref.remove("root_map");
+        } else if let Ok(value) = serde_json::to_value(mappings) {
+            native_ref.insert("root_map".to_string(), value);
+        }
+    }
+}
+
+/// Root mappings applied when `native_id` was materialized, so re-imports can be mapped back.
+pub fn native_root_map(
+    session: &SteadSession,
+    backend: Backend,
+    native_id: &str,
+) -> Vec<RootMapping> {
+    session
+        .extensions
+        .get("native_refs")
+        .and_then(|refs| refs.get(backend.as_str()))
+        .filter(|native_ref| {
+            native_ref.get("session_id").and_then(Value::as_str) == Some(native_id)
+        })
+        .and_then(|native_ref| native_ref.get("root_map"))
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Reuses the existing projection id, then the source id for the source backend, and otherwise
+/// derives a stable UUID from the shared session uid.
+pub fn choose_native_id(session: &SteadSession, backend: Backend) -> String {
+    if let Some((id, _)) = get_native_ref(session, backend) {
+        return id;
+    }
+    if Backend::from_kind(session.source.backend) == backend {
+        return session.source.original_session_id.clone();
+    }
+    deterministic_backend_uuid(session, backend).to_string()
+}
+
+pub(crate) fn deterministic_backend_uuid(session: &SteadSession, backend: Backend) -> Uuid {
+    let shared = session
+        .shared_session_uid
+        .as_deref()
+        .unwrap_or(session.session_uid.as_str());
+    let key = format!("stead-native:{}:{}", backend.as_str(), shared);
+    Uuid::new_v5(&Uuid::NAMESPACE_URL, key.as_bytes())
+}
+
+pub fn default_materialized_path(
+    base_dir: &Path,
+    repo: &Path,
+    backend: Backend,
+    native_id: &str,
+) -> PathBuf {
+    let now = Utc::now();
+    let timestamp = now.format("%Y-%m-%dT%H-%M-%S").to_string();
+    match backend {
+        Backend::Codex => codex_sessions_root(base_dir)
+            .join(format!("{:04}", now.year()))
+            .join(format!("{:02}", now.month()))
+            .join(format!("{:02}", now.day()))
+            .join(format!("rollout-{timestamp}-{native_id}.jsonl")),
+        Backend::Claude => {
+            let slug = repo.display().to_string().replace(['/', '\\'], "-");
+            base_dir
+                .join("projects")
+                .join(slug)
+                .join(format!("{}.jsonl", native_id))
+        }
+    }
+}
+
+pub fn codex_sessions_root(base_dir: &Path) -> PathBuf {
+    if base_dir
+        .file_name()
+        .is_some_and(|v| v.to_string_lossy().eq_ignore_ascii_case("sessions"))
+    {
+        base_dir.to_path_buf()
+    } else {
+        base_dir.join("sessions")
+    }
+}
+
+pub fn claude_projects_root(base_dir: &Path) -> PathBuf {
+    if base_dir
+        .file_name()
+        .is_some_and(|v| v.to_string_lossy().eq_ignore_ascii_case("projects"))
+    {
+        base_dir.to_path_buf()
+    } else {
+        base_dir.join("projects")
+    }
+}
+
+/// Removes older rollouts for `native_id` so Codex only ever sees the latest projection.
+pub fn prune_codex_rollouts_for_native_id(
+    base_dir: &Path,
+    native_id: &str,
+    keep: &Path,
+) -> Result<()> {
+    let root = codex_sessions_root(base_dir);
+    if !root.exists() {
+        return Ok(());
+    }
+    prune_codex_rollouts_in_dir(&root, native_id, keep)
+}
+
+fn prune_codex_rollouts_in_dir(dir: &Path, native_id: &str, keep: &Path) -> Result<()> {
+    let suffix = format!("-{native_id}.jsonl");
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            prune_codex_rollouts_in_dir(&path, native_id, keep)?;
+            continue;
+        }
+        if !file_type.is_file() || path == keep {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|v| v.to_str()) else {
+            continue;
+        };
+        if name.ends_with(&suffix) {
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Keeps native sessions whose project root is `repo`, or all of them when none match.
+pub fn scope_sessions_to_repo(
+    repo: &Path,
+    sessions: Vec<NativeSessionRef>,
+) -> Vec<NativeSessionRef> {
+    let (repo_scoped, others): (Vec<_>, Vec<_>) = sessions
+        .into_iter()
+        .partition(|session| session_matches_repo(repo, session.project_root.as_deref()));
+    if repo_scoped.is_empty() {
+        others
+    } else {
+        repo_scoped
+    }
+}
+
+fn session_matches_repo(repo: &Path, project_root: Option<&str>) -> bool {
+    let Some(project_root) = project_root else {
+        return false;
+    };
+    normalize_path(repo) == normalize_path(Path::new(project_root))
+}
+
+pub fn normalize_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn native_ref_mut(
+    session: &mut SteadSession,
+    backend: Backend,
+) -> Option<&mut serde_json::Map<String, Value>> {
+    session
+        .extensions
+        .get_mut("native_refs")
+        .and_then(|refs| refs.get_mut(backend.as_str()))
+        .and_then(Value::as_object_mut)
+}
@@ -0,0 +1,490 @@
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::hash::Hasher;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::thread::ThreadId;
+use stead_session_adapters::blobs::BlobStore;
+use stead_session_model::SteadSession;
+use stead_session_model::jsonl::{is_header_line, read_session_jsonl, write_session_jsonl};
+use twox_hash::XxHash64;
+
+pub const DEFAULT_BLOB_THRESHOLD: usize = 16 * 1024;
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const TEMP_SUFFIX: &str = ".tmp";
+
+/// Store locks held by this process and the thread holding each, so nested operations on one
+/// repo do not wait on themselves while other threads still wait their turn.
+static HELD_LOCKS: OnceLock<(Mutex<HashMap<PathBuf, HeldLock>>, Condvar)> = OnceLock::new();
+
+struct HeldLock {
+    owner: ThreadId,
+    /// `None` while the owner is still waiting for the file lock.
+    file: Option<File>,
+    depth: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreOptions {
+    /// Write new session files as `.json.zst`. Sessions already stored compressed stay compressed.
+    pub compress: bool,
+    /// Write new session files as `.jsonl`: a header line, then one event per line. Sessions
+    /// already stored as JSONL stay JSONL, and JSONL wins over `compress`.
+    pub jsonl: bool,
+    /// Payloads at least this large are moved to the blob store; `0` disables externalization.
+    pub blob_threshold: usize,
+    pub blob_compress: bool,
+}
+
+impl Default for StoreOptions {
+    fn default() -> Self {
+        Self {
+            compress: false,
+            jsonl: false,
+            blob_threshold: DEFAULT_BLOB_THRESHOLD,
+            blob_compress: false,
+        }
+    }
+}
+
+/// The canonical session store under `<repo>/.stead-core`.
+#[derive(Debug, Clone)]
+pub struct Store {
+    repo: PathBuf,
+    options: StoreOptions,
+}
+
+impl Store {
+    /// Opens the store with default options; see [`crate::Config::store_options`].
+    pub fn open(repo: impl Into<PathBuf>) -> Self {
+        Self::with_options(repo, StoreOptions::default())
+    }
+
+    pub fn with_options(repo: impl Into<PathBuf>, options: StoreOptions) -> Self {
+        Self {
+            repo: repo.into(),
+            options,
+        }
+    }
+
+    pub fn repo(&self) -> &Path {
+        &self.repo
+    }
+
+    pub fn options(&self) -> &StoreOptions {
+        &self.options
+    }
+
+    pub fn sessions_dir(&self) -> PathBuf {
+        self.repo.join(".stead-core").join("sessions")
+    }
+
+    pub fn blobs(&self) -> BlobStore {
+        BlobStore::new(
+            self.repo.join(".stead-core").join("blobs"),
+            self.options.blob_compress,
+        )
+    }
+
+    /// Plain JSON file path for `session_uid`; see [`session_file_variants`] for the others.
+    pub fn session_path(&self, session_uid: &str) -> PathBuf {
+        let sanitized: String = session_uid
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let file_name = if sanitized.is_empty() {
+            format!("session-{}", short_hash(session_uid))
+        } else {
+            format!("{}-{}", sanitized, short_hash(session_uid))
+        };
+        self.sessions_dir().join(format!("{}.json", file_name))
+    }
+
+    pub fn existing_session_path(&self, session_uid: &str) -> Option<PathBuf> {
+        session_file_variants(&self.session_path(session_uid))
+            .into_iter()
+            .find(|path| path.exists())
+    }
+
+    /// Session files in the store, sorted, without temp files of interrupted writes.
+    pub fn session_files(&self) -> Result<Vec<PathBuf>> {
+        let dir = self.sessions_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_file() && !is_temp_file(&path) {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Loads a session by canonical uid, shared uid or alias, with blob references rehydrated.
+    pub fn load(&self, session_uid: &str) -> Result<SteadSession> {
+        let store = self.sessions_dir();
+        if !store.exists() {
+            return Err(anyhow!(
+                "canonical store does not exist at {}",
+                store.display()
+            ));
+        }
+        self.find(session_uid)?
+            .ok_or_else(|| anyhow!("canonical session not found: {}", session_uid))
+    }
+
+    /// Like [`Store::load`], but `None` when no stored session matches. Only the matching
+    /// session is rehydrated.
+    pub fn find(&self, session_uid: &str) -> Result<Option<SteadSession>> {
+        if let Some(direct) = self.existing_session_path(session_uid) {
+            let mut session = parse_canonical_file(&direct)?;
+            self.blobs().rehydrate_session(&mut session)?;
+            return Ok(Some(session));
+        }
+        for path in self.session_files()? {
+            let mut session = parse_canonical_file(&path)?;
+            if canonical_lookup_matches(&session, session_uid) {
+                self.blobs().rehydrate_session(&mut session)?;
+                return Ok(Some(session));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn load_all(&self) -> Result<Vec<SteadSession>> {
+        let blobs = self.blobs();
+        let mut out = Vec::new();
+        for path in self.session_files()? {
+            let mut session = parse_canonical_file(&path)?;
+            blobs.rehydrate_session(&mut session)?;
+            ensure_shared_session_uid(&mut session);
+            out.push(session);
+        }
+        Ok(out)
+    }
+
+    pub fn save(&self, session: &SteadSession) -> Result<PathBuf> {
+        let variants = session_file_variants(&self.session_path(&session.session_uid));
+        let [json_path, zst_path, jsonl_path] = &variants;
+        // Once a session is stored as JSONL or compressed it keeps that format.
+        let path = if self.options.jsonl || jsonl_path.exists() {
+            jsonl_path
+        } else if self.options.compress || zst_path.exists() {
+            zst_path
+        } else {
+            json_path
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let threshold = self.options.blob_threshold;
+        let stored = if threshold > 0 {
+            let mut stored = session.clone();
+            self.blobs().externalize_session(&mut stored, threshold)?;
+            Cow::Owned(stored)
+        } else {
+            Cow::Borrowed(session)
+        };
+        let serialized = if path == jsonl_path {
+            let mut buf = Vec::new();
+            write_session_jsonl(&stored, &mut buf)?;
+            buf
+        } else {
+            serde_json::to_vec_pretty(&stored)?
+        };
+        if path == zst_path {
+            write_atomic(path, &zstd::encode_all(serialized.as_slice(), 0)?)?;
+        } else {
+            write_atomic(path, &serialized)?;
+        }
+        for stale in variants.iter().filter(|stale| *stale != path) {
+            if stale.exists() {
+                std::fs::remove_file(stale)?;
+            }
+        }
+        Ok(path.clone())
+    }
+
+    pub fn lock_path(&self) -> PathBuf {
+        self.repo.join(".stead-core").join("lock")
+    }
+
+    /// Takes the advisory store lock, waiting while another process or thread holds it. The lock
+    /// is re-entrant within a thread and released when the last guard drops. Whoever takes it
+    /// first also [recovers](Self::recover) temp files left by interrupted writes.
+    pub fn lock(&self) -> Result<StoreLock> {
+        // Keyed on the canonical path, so every spelling of the repo shares one in-process claim.
+        let dir = self.repo.join(".stead-core");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir
+            .canonicalize()
+            .with_context(|| format!("failed to resolve {}", dir.display()))?
+            .join("lock");
+        let owner = std::thread::current().id();
+        let mut held = held_locks();
+        while let Some(lock) = held.get_mut(&path) {
+            if lock.owner == owner {
+                lock.depth += 1;
+                return Ok(StoreLock { path });
+            }
+            held = held_locks_released()
+                .wait(held)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        held.insert(
+            path.clone(),
+            HeldLock {
+                owner,
+                file: None,
+                depth: 1,
+            },
+        );
+        drop(held);
+        // From here on the guard releases the claim, also when taking the file lock fails.
+        let guard = StoreLock { path };
+        let file = open_lock_file(&guard.path)?;
+        if let Some(lock) = held_locks().get_mut(&guard.path) {
+            lock.file = Some(file);
+        }
+        self.recover()?;
+        Ok(guard)
+    }
+
+    /// Cleans up temp files left by an interrupted [`Store::save`]. A temp file that parses and
+    /// whose session has no file yet is moved into place; every other one is deleted. Only call
+    /// this while holding the [store lock](Self::lock).
+    pub fn recover(&self) -> Result<Recovery> {
+        let mut recovery = Recovery::default();
+        let Ok(entries) = std::fs::read_dir(self.sessions_dir()) else {
+            return Ok(recovery);
+        };
+        for entry in entries {
+            let temp = entry?.path();
+            let Some(target) = temp_target(&temp) else {
+                continue;
+            };
+            let json_path = target
+                .to_str()
+                .and_then(|target| target.strip_suffix(".zst"))
+                .map_or_else(|| target.clone(), PathBuf::from)
+                .with_extension("json");
+            let taken = session_file_variants(&json_path)
+                .iter()
+                .any(|path| path.exists());
+            if !taken && parse_canonical_file(&temp).is_ok() {
+                std::fs::rename(&temp, &target)?;
+                recovery.promoted.push(target);
+            } else {
+                std::fs::remove_file(&temp)?;
+                recovery.removed.push(temp);
+            }
+        }
+        Ok(recovery)
+    }
+}
+
+/// Guard for the advisory store lock; see [`Store::lock`].
+#[derive(Debug)]
+#[must_use]
+pub struct StoreLock {
+    path: PathBuf,
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        let mut held = held_locks();
+        let Some(lock) = held.get_mut(&self.path) else {
+            return;
+        };
+        lock.depth -= 1;
+        if lock.depth == 0
+            && let Some(lock) = held.remove(&self.path)
+        {
+            if let Some(file) = lock.file {
+                let _ = file.unlock();
+            }
+            held_locks_released().notify_all();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Recovery {
+    /// Session files completed from a temp file.
+    pub promoted: Vec<PathBuf>,
+    /// Temp files that were incomplete or superseded.
+    pub removed: Vec<PathBuf>,
+}
+
+fn held_locks() -> MutexGuard<'static, HashMap<PathBuf, HeldLock>> {
+    HELD_LOCKS
+        .get_or_init(Default::default)
+        .0
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+fn held_locks_released() -> &'static Condvar {
+    &HELD_LOCKS.get_or_init(Default::default).1
+}
+
+fn open_lock_file(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("failed to open store lock {}", path.display()))?;
+    file.lock()
+        .with_context(|| format!("failed to lock {}", path.display()))?;
+    Ok(file)
+}
+
+/// Writes `bytes` next to `path` under a temp name, syncs it and renames it into place, so
+/// readers see either the old file or the complete new one.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("invalid store path {}", path.display()))?
+        .to_string_lossy();
+    let temp = path.with_file_name(format!(".{file_name}.{}{TEMP_SUFFIX}", std::process::id()));
+    let mut file =
+        File::create(&temp).with_context(|| format!("failed to create {}", temp.display()))?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&temp, path)
+        .with_context(|| format!("failed to move {} into place", temp.display()))?;
+    // The rename only survives a crash once the directory is synced too. Platforms that cannot
+    // open a directory as a file skip this.
+    if let Some(parent) = path.parent()
+        && let Ok(dir) = File::open(parent)
+    {
+        dir.sync_all()
+            .with_context(|| format!("failed to sync {}", parent.display()))?;
+    }
+    Ok(())
+}
+
+pub fn is_temp_file(path: &Path) -> bool {
+    temp_target(path).is_some()
+}
+
+/// The file a temp file from [`write_atomic`] was meant to become.
+fn temp_target(temp: &Path) -> Option<PathBuf> {
+    let name = temp.file_name()?.to_str()?;
+    let (final_name, _pid) = name
+        .strip_prefix('.')?
+        .strip_suffix(TEMP_SUFFIX)?
+        .rsplit_once('.')?;
+    Some(temp.with_file_name(final_name))
+}
+
+pub fn compressed_session_path(path: &Path) -> PathBuf {
+    path.with_extension("json.zst")
+}
+
+pub fn jsonl_session_path(path: &Path) -> PathBuf {
+    path.with_extension("jsonl")
+}
+
+/// The plain, compressed and JSONL files a session may be stored in, given its plain path.
+pub fn session_file_variants(path: &Path) -> [PathBuf; 3] {
+    [
+        path.to_path_buf(),
+        compressed_session_path(path),
+        jsonl_session_path(path),
+    ]
+}
+
+/// Parses a canonical session file, decompressing it first if it starts with the zstd magic.
+/// Files whose first line is a session header are read as JSONL.
+pub fn parse_canonical_file(path: &Path) -> Result<SteadSession> {
+    let mut raw = std::fs::read(path)?;
+    if raw.starts_with(&ZSTD_MAGIC) {
+        raw = zstd::decode_all(raw.as_slice())
+            .with_context(|| format!("invalid zstd data in {}", path.display()))?;
+    }
+    let first_line = raw.split(|byte| *byte == b'\n').next().unwrap_or_default();
+    if serde_json::from_slice::<Value>(first_line).is_ok_and(|line| is_header_line(&line)) {
+        return read_session_jsonl(raw.as_slice())
+            .with_context(|| format!("invalid canonical JSONL in {}", path.display()));
+    }
+    serde_json::from_slice(&raw)
+        .with_context(|| format!("invalid canonical JSON in {}", path.display()))
+}
+
+pub fn canonical_lookup_matches(session: &SteadSession, lookup: &str) -> bool {
+    if session.session_uid == lookup {
+        return true;
+    }
+    if session.shared_session_uid.as_deref() == Some(lookup) {
+        return true;
+    }
+    session_uid_aliases(session)
+        .iter()
+        .any(|alias| alias == lookup)
+}
+
+pub fn ensure_shared_session_uid(session: &mut SteadSession) -> bool {
+    if session.shared_session_uid.is_none() {
+        session.shared_session_uid = Some(session.session_uid.clone());
+        return true;
+    }
+    false
+}
+
+pub fn add_session_uid_alias(session: &mut SteadSession, alias: &str) {
+    if alias.is_empty() || alias == session.session_uid {
+        return;
+    }
+    let entry = session
+        .extensions
+        .entry("session_uid_aliases".to_string())
+        .or_insert_with(|| json!([]));
+    if !entry.is_array() {
+        *entry = json!([]);
+    }
+    if let Some(arr) = entry.as_array_mut() {
+        if arr.iter().any(|v| v.as_str() == Some(alias)) {
+            return;
+        }
+        arr.push(Value::String(alias.to_string()));
+    }
+}
+
+pub fn session_uid_aliases(session: &SteadSession) -> Vec<String> {
+    session
+        .extensions
+        .get("session_uid_aliases")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn short_hash(value: &str) -> String {
+    let mut hasher = XxHash64::with_seed(0);
+    hasher.write(value.as_bytes());
+    format!("{:016x}", hasher.finish())[..8].to_string()
+}
@@ -0,0 +1,475 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use stead_session_adapters::artifacts::attach_artifacts;
+use stead_session_adapters::claude::ClaudeAdapter;
+use stead_session_adapters::codex::CodexAdapter;
+use stead_session_adapters::compact::{compact_session, rehydrate_session};
+use stead_session_adapters::redact::Redactor;
+use stead_session_adapters::remap::{RootMapping, remap_session};
+use stead_session_adapters::{ExportReport, NativeSessionRef};
+use stead_session_model::{SteadSession, build_session_uid};
+
+use crate::config::Config;
+use crate::journal::PendingSync;
+use crate::merge::merge_sessions;
+use crate::native::{
+    choose_native_id, default_materialized_path, get_native_ref, mark_projected_through,
+    native_root_map, prune_codex_rollouts_for_native_id, remove_native_ref, scope_sessions_to_repo,
+    set_native_ref, set_native_root_map,
+};
+use crate::store::{Store, ensure_shared_session_uid, session_uid_aliases};
+use crate::{Backend, MergeStrategy, StorageProfile};
+
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    pub codex_base: PathBuf,
+    pub claude_base: PathBuf,
+    pub strategy: MergeStrategy,
+    pub profile: StorageProfile,
+    pub redactor: Option<Redactor>,
+}
+
+/// One native session visited by [`Workspace::sync`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncEntry {
+    pub backend: Backend,
+    pub native_id: String,
+    pub outcome: SyncOutcome,
+    pub session_uid: String,
+    /// `None` when the session failed to sync.
+    pub stored_at: Option<PathBuf>,
+    pub conflicts: Vec<Value>,
+    pub branch_session_uid: Option<String>,
+    pub sibling_session_uid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncOutcome {
+    /// Stored as a new canonical session, including branches split off on divergence.
+    Imported,
+    Merged,
+    /// Already fully contained in the store; nothing was written.
+    Unchanged,
+    Failed,
+}
+
+impl SyncOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Imported => "imported",
+            Self::Merged => "merged",
+            Self::Unchanged => "unchanged",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Materialized {
+    pub native_id: String,
+    pub output_path: PathBuf,
+    pub report: ExportReport,
+}
+
+/// A session made resumable in a backend.
+#[derive(Debug, Clone, Serialize)]
+pub struct Handoff {
+    pub session_uid: String,
+    pub backend: Backend,
+    pub native_id: String,
+    pub native_path: PathBuf,
+    /// `false` when an existing projection was reused.
+    pub materialized: bool,
+}
+
+impl Handoff {
+    /// The interactive command a user runs to pick the session up.
+    pub fn resume_command(&self) -> String {
+        match self.backend {
+            Backend::Codex => format!("codex resume {}", self.native_id),
+            Backend::Claude => format!("claude --resume {}", self.native_id),
+        }
+    }
+}
+
+/// How [`Workspace::resume`] launches the backend CLI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResumeLauncher {
+    /// Called as `<runner> <backend> --resume <native-id> <prompt>` instead of the backend CLI.
+    pub runner: Option<String>,
+    pub codex_bin: String,
+    pub claude_bin: String,
+}
+
+impl Default for ResumeLauncher {
+    fn default() -> Self {
+        Self {
+            runner: None,
+            codex_bin: "codex".to_string(),
+            claude_bin: "claude".to_string(),
+        }
+    }
+}
+
+/// Orchestrates the canonical store of one repository and its native backend projections.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    store: Store,
+}
+
+impl Workspace {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+
+    /// Opens the store of `repo` with the store options from its layered [`Config`].
+    pub fn open(repo: impl Into<PathBuf>) -> Result<Self> {
+        let repo = repo.into();
+        let options = Config::load(Some(&repo))?.store_options();
+        Ok(Self::new(Store::with_options(repo, options)))
+    }
+
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    pub fn repo(&self) -> &Path {
+        self.store.repo()
+    }
+
+    /// Imports every Codex and Claude session for this repository and merges it into the store.
+    /// A session that fails to import or merge is reported as [`SyncOutcome::Failed`] and the
+    /// rest are still synced. Every sync is recorded in the journal for
+    /// [`Workspace::rollback_sync`].
+    pub fn sync(&self, options: &SyncOptions) -> Result<Vec<SyncEntry>> {
+        let _lock = self.store.lock()?;
+        let codex = CodexAdapter::from_base_dir(&options.codex_base);
+        let claude = ClaudeAdapter::from_base_dir(&options.claude_base);
+        let natives: Vec<(Backend, NativeSessionRef)> =
+            scope_sessions_to_repo(self.repo(), codex.list_sessions()?)
+                .into_iter()
+                .map(|native| (Backend::Codex, native))
+                .chain(
+                    scope_sessions_to_repo(self.repo(), claude.list_sessions()?)
+                        .into_iter()
+                        .map(|native| (Backend::Claude, native)),
+                )
+                .collect();
+
+        std::fs::create_dir_all(self.store.sessions_dir())?;
+        let mut stored = self.store.load_all()?;
+        let mut journal = PendingSync::begin(&self.store)?;
+        let mut synced = Vec::new();
+        for (backend, native) in natives {
+            let session = match backend {
+                Backend::Codex => codex.import_from_file(&native.file_path),
+                Backend::Claude => claude.import_session(&native.native_id),
+            };
+            let entry = session
+                .map_err(anyhow::Error::from)
+                .and_then(|session| {
+                    self.sync_native(
+                        &mut journal,
+                        &mut stored,
+                        session,
+                        backend,
+                        &native,
+                        options,
+                    )
+                })
+                .unwrap_or_else(|err| SyncEntry {
+                    backend,
+                    native_id: native.native_id.clone(),
+                    outcome: SyncOutcome::Failed,
+                    session_uid: build_session_uid(backend.kind(), &native.native_id),
+                    stored_at: None,
+                    conflicts: Vec::new(),
+                    branch_session_uid: None,
+                    sibling_session_uid: None,
+                    error: Some(format!("{err:#}")),
+                });
+            synced.push(entry);
+        }
+        journal.finish(&self.store, &synced)?;
+        Ok(synced)
+    }
+
+    fn sync_native(
+        &self,
+        journal: &mut PendingSync,
+        stored: &mut Vec<SteadSession>,
+        mut session: SteadSession,
+        backend: Backend,
+        native: &NativeSessionRef,
+        options: &SyncOptions,
+    ) -> Result<SyncEntry> {
+        if let Some(redactor) = options.redactor.as_ref() {
+            redactor.redact_session(&mut session);
+        }
+        if options.profile == StorageProfile::Compact {
+            compact_session(&mut session);
+        }
+        self.upsert_synced_session(journal, stored, session, backend, native, options.strategy)
+    }
+
+    fn upsert_synced_session(
+        &self,
+        journal: &mut PendingSync,
+        stored: &mut Vec<SteadSession>,
+        mut imported: SteadSession,
+        backend: Backend,
+        native: &NativeSessionRef,
+        strategy: MergeStrategy,
+    ) -> Result<SyncEntry> {
+        let (native_id, native_path) = (native.native_id.as_str(), native.file_path.as_path());
+        // Importers only derive `artifacts`; stored sessions also carry their timeline events.
+        attach_artifacts(&mut imported);
+        ensure_shared_session_uid(&mut imported);
+        set_native_ref(&mut imported, backend, native_id, native_path);
+        let entry = |outcome: SyncOutcome,
+                     session_uid: String,
+                     stored_at: PathBuf,
+                     conflicts: Vec<Value>| SyncEntry {
+            backend,
+            native_id: native_id.to_string(),
+            outcome,
+            session_uid,
+            stored_at: Some(stored_at),
+            conflicts,
+            branch_session_uid: None,
+            sibling_session_uid: None,
+            error: None,
+        };
+
+        let target_index = stored.iter().position(|existing| {
+            get_native_ref(existing, backend).is_some_and(|(id, _)| id == native_id)
+        });
+
+        let target_index = target_index.or_else(|| {
+            stored
+                .iter()
+                .position(|existing| existing.session_uid == imported.session_uid)
+        });
+
+        let target_index = target_index.or_else(|| {
+            let imported_shared = imported
+                .shared_session_uid
+                .clone()
+                .unwrap_or_else(|| imported.session_uid.clone());
+            stored.iter().position(|existing| {
+                existing
+                    .shared_session_uid
+                    .as_deref()
+                    .unwrap_or(existing.session_uid.as_str())
+                    == imported_shared
+            })
+        });
+
+        let target_index = target_index.or_else(|| {
+            stored.iter().position(|existing| {
+                session_uid_aliases(existing)
+                    .iter()
+                    .any(|alias| alias == &imported.session_uid)
+            })
+        });
+
+        let Some(index) = target_index else {
+            let stored_path = journal.save(&self.store, &imported)?;
+            let session_uid = imported.session_uid.clone();
+            stored.push(imported);
+            return Ok(entry(
+                SyncOutcome::Imported,
+                session_uid,
+                stored_path,
+                Vec::new(),
+            ));
+        };
+
+        let inverse: Vec<RootMapping> = native_root_map(&stored[index], backend, native_id)
+            .iter()
+            .map(RootMapping::inverse)
+            .collect();
+        remap_session(&mut imported, &inverse);
+        let outcome = merge_sessions(stored[index].clone(), imported, strategy)?;
+        let mut merged = outcome.session;
+        if let Some(mut branch) = outcome.branch {
+            let mut sibling_session_uid = None;
+            if let Some((peer_backend, mut sibling)) = outcome.sibling
+                && let Some((peer_id, peer_path)) = get_native_ref(&merged, peer_backend)
+            {
+                remove_native_ref(&mut merged, peer_backend);
+                set_native_ref(&mut sibling, peer_backend, &peer_id, &peer_path);
+                ensure_shared_session_uid(&mut sibling);
+                journal.save(&self.store, &sibling)?;
+                sibling_session_uid = Some(sibling.session_uid.clone());
+                stored.push(sibling);
+            }
+            if get_native_ref(&merged, backend).is_some_and(|(id, _)| id == native_id) {
+                remove_native_ref(&mut merged, backend);
+            }
+            journal.save(&self.store, &merged)?;
+            stored[index] = merged;
+
+            set_native_ref(&mut branch, backend, native_id, native_path);
+            ensure_shared_session_uid(&mut branch);
+            let stored_path = journal.save(&self.store, &branch)?;
+            let branch_uid = branch.session_uid.clone();
+            stored.push(branch);
+            return Ok(SyncEntry {
+                branch_session_uid: Some(branch_uid.clone()),
+                sibling_session_uid,
+                ..entry(
+                    SyncOutcome::Imported,
+                    branch_uid,
+                    stored_path,
+                    outcome.conflicts,
+                )
+            });
+        }
+        set_native_ref(&mut merged, backend, native_id, native_path);
+        ensure_shared_session_uid(&mut merged);
+        let session_uid = merged.session_uid.clone();
+        if merged == stored[index]
+            && let Some(stored_path) = self.store.existing_session_path(&session_uid)
+        {
+            return Ok(entry(
+                SyncOutcome::Unchanged,
+                session_uid,
+                stored_path,
+                outcome.conflicts,
+            ));
+        }
+        let stored_path = journal.save(&self.store, &merged)?;
+        stored[index] = merged;
+        Ok(entry(
+            SyncOutcome::Merged,
+            session_uid,
+            stored_path,
+            outcome.conflicts,
+        ))
+    }
+
+    /// Writes `session` as a native `to` session and records the projection in its native refs.
+    /// `mappings` rewrite paths in the written copy only; sync maps them back on re-import.
+    pub fn materialize(
+        &self,
+        session: &mut SteadSession,
+        to: Backend,
+        base_dir: &Path,
+        out: Option<PathBuf>,
+        mappings: &[RootMapping],
+    ) -> Result<Materialized> {
+        let _lock = self.store.lock()?;
+        ensure_shared_session_uid(session);
+        let native_id = choose_native_id(session, to);
+        let output_path =
+            out.unwrap_or_else(|| default_materialized_path(base_dir, self.repo(), to, &native_id));
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Rehydrate before pruning so compact raw refs into a stale rollout still resolve.
+        let mut export_session = session.clone();
+        export_session.source.original_session_id = native_id.clone();
+        rehydrate_session(&mut export_session);
+        remap_session(&mut export_session, mappings);
+        if to == Backend::Codex {
+            prune_codex_rollouts_for_native_id(base_dir, &native_id, &output_path)?;
+        }
+
+        let report = match to {
+            Backend::Codex => CodexAdapter::from_base_dir(base_dir)
+                .export_session(&export_session, &output_path)?,
+            Backend::Claude => ClaudeAdapter::from_base_dir(base_dir)
+                .export_session(&export_session, &output_path)?,
+        };
+
+        set_native_ref(session, to, &native_id, &output_path);
+        mark_projected_through(session, to);
+        set_native_root_map(session, to, mappings);
+        self.store.save(session)?;
+        Ok(Materialized {
+            native_id,
+            output_path,
+            report,
+        })
+    }
+
+    /// Reuses the session's projection for `to` while its file still exists, otherwise
+    /// materializes it under `base_dir`.
+    pub fn handoff(
+        &self,
+        session_uid: &str,
+        to: Backend,
+        base_dir: Option<&Path>,
+        out: Option<PathBuf>,
+    ) -> Result<Handoff> {
+        let _lock = self.store.lock()?;
+        let mut session = self.store.load(session_uid)?;
+        let existing = get_native_ref(&session, to).filter(|(_, path)| path.exists());
+        let (native_id, native_path, materialized) = match existing {
+            Some((native_id, native_path)) => {
+                if ensure_shared_session_uid(&mut session) {
+                    self.store.save(&session)?;
+                }
+                (native_id, native_path, false)
+            }
+            None => {
+                let Some(base_dir) = base_dir else {
+                    bail!(
+                        "missing native projection for backend `{}`; provide a base dir to materialize",
+                        to.as_str()
+                    );
+                };
+                let materialized = self.materialize(&mut session, to, base_dir, out, &[])?;
+                (materialized.native_id, materialized.output_path, true)
+            }
+        };
+        Ok(Handoff {
+            session_uid: session.session_uid,
+            backend: to,
+            native_id,
+            native_path,
+            materialized,
+        })
+    }
+
+    /// Hands the session off to `backend` and runs one non-interactive turn with `prompt`.
+    pub fn resume(
+        &self,
+        session_uid: &str,
+        backend: Backend,
+        prompt: &str,
+        base_dir: Option<&Path>,
+        out: Option<PathBuf>,
+        launcher: &ResumeLauncher,
+    ) -> Result<Handoff> {
+        let handoff = self.handoff(session_uid, backend, base_dir, out)?;
+        let native_id = handoff.native_id.as_str();
+        let status = if let Some(runner) = launcher.runner.as_deref() {
+            Command::new(runner)
+                .args([backend.as_str(), "--resume", native_id, prompt])
+                .status()?
+        } else {
+            let mut command = match backend {
+                Backend::Codex => Command::new(&launcher.codex_bin),
+                Backend::Claude => Command::new(&launcher.claude_bin),
+            };
+            command.current_dir(self.repo());
+            match backend {
+                Backend::Codex => command.args(["exec", "resume", native_id, prompt]),
+                Backend::Claude => command.args(["-p", "-r", native_id, prompt]),
+            };
+            command.status()?
+        };
+        if !status.success() {
+            bail!("resume command failed for backend {}", backend.as_str());
+        }
+        Ok(handoff)
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::Write;
+use std::path::Path;
+use stead_core_runtime::archive::archive_location;
+use stead_core_runtime::native::get_native_ref;
+use stead_core_runtime::{
+    Backend, GcOptions, MergeStrategy, StorageProfile, Store, StoreOptions, SyncOptions, Workspace,
+};
+use tempfile::TempDir;
+
+fn copy_tree(from: &Path, to: &Path) {
+    for entry in std::fs::read_dir(from).unwrap() {
+        let path = entry.unwrap().path();
+        let target = to.join(path.file_name().unwrap());
+        if path.is_dir() {
+            std::fs::create_dir_all(&target).unwrap();
+            copy_tree(&path, &target);
+        } else {
+            std::fs::copy(&path, &target).unwrap();
+        }
+    }
+}
+
+fn synced_workspace(repo: &Path, codex_home: &Path, claude_home: &Path) -> Workspace {
+    copy_tree(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../stead-session-adapters/tests/fixtures/codex"),
+        codex_home,
+    );
+    let workspace = Workspace::new(Store::with_options(repo, StoreOptions::default()));
+    workspace
+        .sync(&SyncOptions {
+            codex_base: codex_home.to_path_buf(),
+            claude_base: claude_home.to_path_buf(),
+            strategy: MergeStrategy::PreferIncoming,
+            profile: StorageProfile::Full,
+            redactor: None,
+        })
+        .unwrap();
+    workspace
+}
+
+#[test]
+fn archive_moves_native_files_and_restore_puts_them_back() {
+    let repo = TempDir::new().unwrap();
+    let codex_home = TempDir::new().unwrap();
+    let claude_home = TempDir::new().unwrap();
+    let workspace = synced_workspace(repo.path(), codex_home.path(), claude_home.path());
+    let handoff = workspace
+        .handoff(
+            "stead:codex:s-new",
+            Backend::Claude,
+            Some(claude_home.path()),
+            None,
+        )
+        .unwrap();
+    let session = workspace.store().load("stead:codex:s-new").unwrap();
+    let rollout = get_native_ref(&session, Backend::Codex).unwrap().1;
+
+    let archived = workspace.archive("stead:codex:s-new", None).unwrap();
+    assert_eq!(archived.files.len(), 2);
+    assert!(
+        archived
+            .archive_dir
+            .starts_with(workspace.default_archive_root())
+    );
+    assert!(!rollout.exists());
+    assert!(!handoff.native_path.exists());
+    assert!(
+        archived
+            .archive_dir
+            .join("codex/2026/02/17/rollout-2026-02-17T20-00-00-s-new.jsonl")
+            .is_file()
+    );
+    let stored = workspace.store().load("stead:codex:s-new").unwrap();
+    assert_eq!(
+        archive_location(&stored),
+        Some(archived.archive_dir.clone())
+    );
+    let err = workspace.archive("stead:codex:s-new", None).unwrap_err();
+    assert!(err.to_string().contains("already archived"));
+
+    // Archived sessions are not garbage, even with every native file gone.
+    let gc = workspace
+        .gc(&GcOptions {
+            older_than: chrono::Duration::zero(),
+            dry_run: true,
+            ..GcOptions::default()
+        })
+        .unwrap();
+    assert!(gc.sessions.is_empty());
+
+    let moved_claude = TempDir::new().unwrap();
+    let restored = workspace
+        .restore("stead:codex:s-new", None, Some(moved_claude.path()))
+        .unwrap();
+    assert_eq!(restored.files.len(), 2);
+    assert!(rollout.is_file());
+    let claude_layout = handoff
+        .native_path
+        .strip_prefix(claude_home.path())
+        .unwrap();
+    assert!(moved_claude.path().join(claude_layout).is_file());
+    assert!(!archived.archive_dir.exists());
+    let stored = workspace.store().load("stead:codex:s-new").unwrap();
+    assert!(archive_location(&stored).is_none());
+    assert_eq!(
+        get_native_ref(&stored, Backend::Claude).unwrap().1,
+        moved_claude.path().join(claude_layout)
+    );
+}
+
+#[test]
+fn archive_refuses_native_files_with_unsynced_events() {
+    let repo = TempDir::new().unwrap();
+    let codex_home = TempDir::new().unwrap();
+    let claude_home = TempDir::new().unwrap();
+    let workspace = synced_workspace(repo.path(), codex_home.path(), claude_home.path());
+    let session = workspace.store().load("stead:codex:s-new").unwrap();
+    let rollout = get_native_ref(&session, Backend::Codex).unwrap().1;
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&rollout)
+        .unwrap();
+    writeln!(
+        file,
+        r#"{{"timestamp":"2026-02-17T21:00:00Z","type":"response_item","payload":{{"type":"message","role":"user","content":[{{"type":"input_text","text":"One more thing"}}]}}}}"#
+    )
+    .unwrap();
+
+    let err = workspace.archive("stead:codex:s-new", None).unwrap_err();
+    assert!(err.to_string().contains("run sync first"), "{err}");
+    assert!(rollout.is_file());
+}
@@ -0,0 +1,79 @@
+use std::path::Path;
+use stead_core_runtime::config::repo_config_path;
+use stead_core_runtime::{Backend, Config, MergeStrategy, StorageProfile};
+use tempfile::TempDir;
+
+fn write(path: &Path, raw: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, raw).unwrap();
+}
+
+#[test]
+fn repo_layer_overrides_user_layer_key_by_key() {
+    let dir = TempDir::new().unwrap();
+    let user = dir.path().join("user.toml");
+    write(
+        &user,
+        r#"
+[codex]
+base_dir = "/home/me/.codex"
+bin = "codex-nightly"
+
+[sync]
+merge_strategy = "branch_on_divergence"
+storage_profile = "compact"
+
+[redaction]
+builtin = false
+rules = [{ name = "ticket", pattern = "TICKET-[0-9]+" }]
+"#,
+    );
+    let repo_config = repo_config_path(dir.path());
+    write(
+        &repo_config,
+        r#"
+[codex]
+bin = "codex"
+
+[sync]
+merge_strategy = "prefer_anchor"
+
+[store]
+compress = true
+"#,
+    );
+
+    let mut config = Config::from_file(&user).unwrap();
+    config.overlay(Config::from_file(&repo_config).unwrap());
+
+    assert_eq!(
+        config.base_dir(Backend::Codex),
+        Some(Path::new("/home/me/.codex"))
+    );
+    assert_eq!(config.resume_launcher().codex_bin, "codex");
+    assert_eq!(config.resume_launcher().claude_bin, "claude");
+    assert_eq!(config.merge_strategy(), MergeStrategy::PreferAnchor);
+    assert_eq!(config.storage_profile(), StorageProfile::Compact);
+    assert!(config.store_options().compress);
+    assert_eq!(config.redaction.as_ref().unwrap().rules.len(), 1);
+    assert_eq!(
+        config.sources,
+        [
+            user.display().to_string(),
+            repo_config.display().to_string()
+        ]
+    );
+
+    let effective = config.effective();
+    assert_eq!(effective.sync.redact, Some(false));
+    assert_eq!(effective.store.blob_threshold, Some(16 * 1024));
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    write(&path, "[codex]\nbase = \"/tmp\"\n");
+    let err = Config::from_file(&path).unwrap_err();
+    assert!(format!("{err:#}").contains("unknown field `base`"));
+}
@@ -0,0 +1,148 @@
+use chrono::{TimeZone, Utc};
+use serde_json::json;
+use std::path::Path;
+use stead_core_runtime::native::get_native_ref;
+use stead_core_runtime::store::{add_session_uid_alias, session_uid_aliases};
+use stead_core_runtime::{
+    Backend, GcOptions, MergeStrategy, StorageProfile, Store, StoreOptions, SyncOptions, Workspace,
+};
+use stead_session_model::SessionArtifactRef;
+use tempfile::TempDir;
+
+fn copy_tree(from: &Path, to: &Path) {
+    for entry in std::fs::read_dir(from).unwrap() {
+        let path = entry.unwrap().path();
+        let target = to.join(path.file_name().unwrap());
+        if path.is_dir() {
+            std::fs::create_dir_all(&target).unwrap();
+            copy_tree(&path, &target);
+        } else {
+            std::fs::copy(&path, &target).unwrap();
+        }
+    }
+}
+
+fn codex_home() -> TempDir {
+    let home = TempDir::new().unwrap();
+    copy_tree(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../stead-session-adapters/tests/fixtures/codex"),
+        home.path(),
+    );
+    home
+}
+
+#[test]
+fn gc_prunes_abandoned_sessions_projections_aliases_and_blobs() {
+    let repo = TempDir::new().unwrap();
+    let codex_home = codex_home();
+    let claude_home = TempDir::new().unwrap();
+    let workspace = Workspace::new(Store::with_options(repo.path(), StoreOptions::default()));
+    workspace
+        .sync(&SyncOptions {
+            codex_base: codex_home.path().to_path_buf(),
+            claude_base: claude_home.path().to_path_buf(),
+            strategy: MergeStrategy::PreferIncoming,
+            profile: StorageProfile::Full,
+            redactor: None,
+        })
+        .unwrap();
+    let store = workspace.store();
+
+    // s-old lost its rollout long ago.
+    let mut old = store.load("stead:codex:s-old").unwrap();
+    old.metadata.updated_at = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+    store.save(&old).unwrap();
+    std::fs::remove_file(get_native_ref(&old, Backend::Codex).unwrap().1).unwrap();
+
+    // s-new has a leftover rollout copy, redundant aliases and an unbundled artifact blob.
+    let mut new = store.load("stead:codex:s-new").unwrap();
+    let rollout = get_native_ref(&new, Backend::Codex).unwrap().1;
+    let leftover = codex_home
+        .path()
+        .join("sessions/2026/02/18/rollout-2026-02-18T20-00-00-s-new.jsonl");
+    std::fs::create_dir_all(leftover.parent().unwrap()).unwrap();
+    std::fs::copy(&rollout, &leftover).unwrap();
+    new.shared_session_uid = Some("shared-new".to_string());
+    for alias in ["shared-new", "legacy-id", "stead:codex:s-old"] {
+        add_session_uid_alias(&mut new, alias);
+    }
+    let artifact_blob = store.blobs().put(b"artifact body").unwrap();
+    new.artifacts.push(SessionArtifactRef {
+        artifact_uid: "artifact-1".to_string(),
+        kind: "file".to_string(),
+        source_event_uid: new.events[0].event_uid.clone(),
+        path: Some("notes.md".to_string()),
+        mime_type: None,
+        sha256: Some(artifact_blob.clone()),
+        extensions: [("content_blob".to_string(), json!(artifact_blob))]
+            .into_iter()
+            .collect(),
+    });
+    store.save(&new).unwrap();
+    let orphan_blob = store.blobs().put(b"nobody points here").unwrap();
+
+    let options = GcOptions {
+        codex_base: Some(codex_home.path().to_path_buf()),
+        claude_base: Some(claude_home.path().to_path_buf()),
+        dry_run: true,
+        ..GcOptions::default()
+    };
+    let planned = workspace.gc(&options).unwrap();
+    assert!(planned.dry_run);
+    let removed_sessions: Vec<&str> = planned
+        .sessions
+        .iter()
+        .map(|session| session.session_uid.as_str())
+        .collect();
+    assert_eq!(removed_sessions, ["stead:codex:s-old"]);
+    let projections: Vec<&Path> = planned
+        .projections
+        .iter()
+        .map(|projection| projection.path.as_path())
+        .collect();
+    assert_eq!(projections, [leftover.as_path()]);
+    let aliases: Vec<&str> = planned
+        .aliases
+        .iter()
+        .map(|alias| alias.alias.as_str())
+        .collect();
+    assert_eq!(aliases, ["shared-new"]);
+    let blobs: Vec<&str> = planned
+        .blobs
+        .iter()
+        .map(|blob| blob.sha256.as_str())
+        .collect();
+    assert_eq!(blobs, [orphan_blob.as_str()]);
+    assert!(leftover.exists());
+    assert_eq!(store.load_all().unwrap().len(), 2);
+
+    let applied = workspace
+        .gc(&GcOptions {
+            dry_run: false,
+            ..options.clone()
+        })
+        .unwrap();
+    assert_eq!(applied.sessions.len(), 1);
+    assert!(!leftover.exists());
+    assert!(rollout.exists());
+    assert!(store.blobs().find(&orphan_blob).is_none());
+    assert!(store.blobs().find(&artifact_blob).is_some());
+    let remaining = store.load_all().unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(
+        session_uid_aliases(&remaining[0]),
+        ["legacy-id", "stead:codex:s-old"]
+    );
+    // The removed session's uid still resolves through the surviving alias.
+    assert_eq!(
+        store.load("stead:codex:s-old").unwrap().session_uid,
+        "stead:codex:s-new"
+    );
+
+    let again = workspace.gc(&options).unwrap();
+    assert!(again.sessions.is_empty());
+    assert!(again.projections.is_empty());
+    assert!(again.aliases.is_empty());
+    assert!(again.blobs.is_empty());
+}
@@ -0,0 +1,244 @@
+use std::io::Write;
+use std::path::Path;
+use stead_core_runtime::gc::GcOptions;
+use stead_core_runtime::journal::{ChangeKind, SYNC_JOURNAL_LIMIT, journal_entries};
+use stead_core_runtime::native::get_native_ref;
+use stead_core_runtime::{
+    Backend, MergeStrategy, StorageProfile, Store, StoreOptions, SyncEntry, SyncOptions,
+    SyncOutcome, Workspace,
+};
+use tempfile::TempDir;
+
+fn copy_tree(from: &Path, to: &Path) {
+    for entry in std::fs::read_dir(from).unwrap() {
+        let path = entry.unwrap().path();
+        let target = to.join(path.file_name().unwrap());
+        if path.is_dir() {
+            std::fs::create_dir_all(&target).unwrap();
+            copy_tree(&path, &target);
+        } else {
+            std::fs::copy(&path, &target).unwrap();
+        }
+    }
+}
+
+fn sync(workspace: &Workspace, codex_home: &Path, claude_home: &Path) -> Vec<SyncEntry> {
+    workspace
+        .sync(&SyncOptions {
+            codex_base: codex_home.to_path_buf(),
+            claude_base: claude_home.to_path_buf(),
+            strategy: MergeStrategy::PreferIncoming,
+            profile: StorageProfile::Full,
+            redactor: None,
+        })
+        .unwrap()
+}
+
+fn outcomes(entries: &[SyncEntry]) -> Vec<(&str, SyncOutcome)> {
+    entries
+        .iter()
+        .map(|entry| (entry.native_id.as_str(), entry.outcome))
+        .collect()
+}
+
+#[test]
+fn sync_reports_outcomes_and_rolls_back_from_the_journal() {
+    let repo = TempDir::new().unwrap();
+    let codex_home = TempDir::new().unwrap();
+    let claude_home = TempDir::new().unwrap();
+    copy_tree(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../stead-session-adapters/tests/fixtures/codex"),
+        codex_home.path(),
+    );
+    let workspace = Workspace::new(Store::with_options(repo.path(), StoreOptions::default()));
+    let store = workspace.store();
+
+    let first = sync(&workspace, codex_home.path(), claude_home.path());
+    assert_eq!(
+        outcomes(&first),
+        [
+            ("s-new", SyncOutcome::Imported),
+            ("s-old", SyncOutcome::Imported)
+        ]
+    );
+    // The first merge folds the raw lines into the per-backend payload; after that the store is
+    // settled and syncing writes nothing.
+    let second = sync(&workspace, codex_home.path(), claude_home.path());
+    assert!(
+        second
+            .iter()
+            .all(|entry| entry.outcome == SyncOutcome::Merged)
+    );
+    let settled = sync(&workspace, codex_home.path(), claude_home.path());
+    assert_eq!(
+        outcomes(&settled),
+        [
+            ("s-new", SyncOutcome::Unchanged),
+            ("s-old", SyncOutcome::Unchanged)
+        ]
+    );
+    let synced_new =
+        std::fs::read(store.existing_session_path("stead:codex:s-new").unwrap()).unwrap();
+
+    // s-new gains an event and a Claude session shows up whose subagent transcript is corrupt;
+    // the Claude import fails and the rest still syncs.
+    let new_rollout = get_native_ref(&store.load("stead:codex:s-new").unwrap(), Backend::Codex)
+        .unwrap()
+        .1;
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&new_rollout)
+        .unwrap();
+    writeln!(
+        file,
+        r#"{{"timestamp":"2026-02-17T21:00:00Z","type":"response_item","payload":{{"type":"message","role":"user","content":[{{"type":"input_text","text":"One more thing"}}]}}}}"#
+    )
+    .unwrap();
+    copy_tree(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../stead-session-adapters/tests/fixtures/claude"),
+        claude_home.path(),
+    );
+    let subagent = claude_home
+        .path()
+        .join("projects/-Users-jonas-repos-stead-core/subagents/agent-a123.jsonl");
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&subagent)
+        .unwrap();
+    writeln!(file, "{{not json").unwrap();
+
+    let partial = sync(&workspace, codex_home.path(), claude_home.path());
+    assert_eq!(
+        outcomes(&partial),
+        [
+            ("s-new", SyncOutcome::Merged),
+            ("s-old", SyncOutcome::Unchanged),
+            ("claude-main", SyncOutcome::Failed)
+        ]
+    );
+    let failed = &partial[2];
+    assert_eq!(failed.session_uid, "stead:claude_code:claude-main");
+    assert!(failed.stored_at.is_none());
+    assert!(failed.error.is_some());
+
+    let journal = journal_entries(store).unwrap();
+    assert_eq!(journal.len(), 4);
+    assert_eq!(journal[0].changes.len(), 2);
+    assert!(
+        journal[0]
+            .changes
+            .iter()
+            .all(|change| change.kind == ChangeKind::Created)
+    );
+    assert!(journal[2].changes.is_empty());
+    assert_eq!(journal[3].changes.len(), 1);
+    assert_eq!(journal[3].changes[0].kind, ChangeKind::Modified);
+    assert_eq!(journal[3].sessions[2].outcome, SyncOutcome::Failed);
+
+    let rolled_back = workspace.rollback_sync().unwrap();
+    assert_eq!(rolled_back.id, journal[3].id);
+    assert_eq!(rolled_back.restored.len(), 1);
+    assert_eq!(
+        std::fs::read(store.existing_session_path("stead:codex:s-new").unwrap()).unwrap(),
+        synced_new
+    );
+
+    // Rolling back past the settled and folding syncs undoes the first one as well.
+    assert!(workspace.rollback_sync().unwrap().restored.is_empty());
+    assert_eq!(workspace.rollback_sync().unwrap().restored.len(), 2);
+    let rolled_back = workspace.rollback_sync().unwrap();
+    assert_eq!(rolled_back.removed.len(), 2);
+    assert!(store.load_all().unwrap().is_empty());
+    let err = workspace.rollback_sync().unwrap_err();
+    assert!(err.to_string().contains("no sync to roll back"), "{err}");
+}
+
+#[test]
+fn rollback_refuses_to_overwrite_session_files_changed_after_the_sync() {
+    let repo = TempDir::new().unwrap();
+    let claude_home = TempDir::new().unwrap();
+    let codex_home = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../stead-session-adapters/tests/fixtures/codex");
+    let workspace = Workspace::new(Store::open(repo.path()));
+    let store = workspace.store();
+    sync(&workspace, &codex_home, claude_home.path());
+
+    let journal = journal_entries(store).unwrap();
+    assert!(
+        journal[0]
+            .changes
+            .iter()
+            .all(|change| change.sha256.is_some())
+    );
+
+    let mut session = store.load("stead:codex:s-new").unwrap();
+    session.metadata.title = Some("edited after sync".to_string());
+    let path = store.save(&session).unwrap();
+    let edited = std::fs::read(&path).unwrap();
+
+    let err = workspace.rollback_sync().unwrap_err().to_string();
+    assert!(err.contains("changed after it"), "{err}");
+    assert!(
+        err.contains(path.file_name().unwrap().to_str().unwrap()),
+        "{err}"
+    );
+    assert_eq!(std::fs::read(&path).unwrap(), edited);
+    assert_eq!(store.load_all().unwrap().len(), 2);
+    assert_eq!(journal_entries(store).unwrap().len(), 1);
+}
+
+#[test]
+fn sync_journal_keeps_the_most_recent_syncs() {
+    let repo = TempDir::new().unwrap();
+    let codex_home = TempDir::new().unwrap();
+    let claude_home = TempDir::new().unwrap();
+    let workspace = Workspace::new(Store::open(repo.path()));
+    for _ in 0..SYNC_JOURNAL_LIMIT + 2 {
+        sync(&workspace, codex_home.path(), claude_home.path());
+    }
+    let journal = journal_entries(workspace.store()).unwrap();
+    assert_eq!(journal.len(), SYNC_JOURNAL_LIMIT);
+    assert!(journal.windows(2).all(|pair| pair[0].id < pair[1].id));
+}
+
+#[test]
+fn gc_keeps_blobs_a_rollback_still_needs() {
+    let repo = TempDir::new().unwrap();
+    let codex_home = TempDir::new().unwrap();
+    let claude_home = TempDir::new().unwrap();
+    copy_tree(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../stead-session-adapters/tests/fixtures/codex"),
+        codex_home.path(),
+    );
+    let workspace = Workspace::new(Store::with_options(
+        repo.path(),
+        StoreOptions {
+            blob_threshold: 16,
+            ..StoreOptions::default()
+        },
+    ));
+    let store = workspace.store();
+    sync(&workspace, codex_home.path(), claude_home.path());
+    let before = store.load("stead:codex:s-new").unwrap();
+
+    // The second sync replaces the externalized tool output, so only the journal snapshot still
+    // references the old blob.
+    let rollout = codex_home
+        .path()
+        .join("sessions/2026/02/17/rollout-2026-02-17T20-00-00-s-new.jsonl");
+    let raw = std::fs::read_to_string(&rollout).unwrap();
+    std::fs::write(&rollout, raw.replace("README.md", "Cargo.toml")).unwrap();
+    sync(&workspace, codex_home.path(), claude_home.path());
+
+    workspace
+        .gc(&GcOptions {
+            older_than: chrono::Duration::zero(),
+            ..GcOptions::default()
+        })
+        .unwrap();
+    workspace.rollback_sync().unwrap();
+    assert_eq!(store.load("stead:codex:s-new").unwrap(), before);
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+use stead_core_runtime::MergeStrategy;
+use stead_core_runtime::merge::merge_sessions;
+use stead_session_adapters::codex::CodexAdapter;
+use stead_session_model::{EventKind, EventPayload, SteadSession};
+use tempfile::TempDir;
+
+fn user_texts(session: &SteadSession) -> Vec<&str> {
+    session
+        .events
+        .iter()
+        .filter(|event| event.kind == EventKind::MessageUser)
+        .filter_map(|event| match &event.payload {
+            EventPayload::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn same_backend_merge_keeps_repeated_events() {
+    let rollout = Path::new(env!("CARGO_MANIFEST_DIR")).join(
+        "../stead-session-adapters/tests/fixtures/codex/sessions/2026/02/17/rollout-2026-02-17T20-00-00-s-new.jsonl",
+    );
+    let dir = TempDir::new().unwrap();
+    let grown = dir.path().join("rollout.jsonl");
+    let mut raw = std::fs::read_to_string(&rollout).unwrap();
+    // Identical lines within the same second are still two events.
+    for _ in 0..2 {
+        raw.push_str(r#"{"timestamp":"2026-02-17T20:00:10Z","type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"continue"}]}}"#);
+        raw.push('\n');
+    }
+    std::fs::write(&grown, raw).unwrap();
+
+    let adapter = CodexAdapter::from_base_dir(dir.path());
+    let anchor = adapter.import_from_file(&rollout).unwrap();
+    let incoming = adapter.import_from_file(&grown).unwrap();
+    let outcome = merge_sessions(anchor, incoming.clone(), MergeStrategy::PreferIncoming).unwrap();
+
+    assert!(outcome.conflicts.is_empty());
+    assert!(outcome.branch.is_none());
+    assert_eq!(
+        user_texts(&outcome.session),
+        ["Import this codex session", "continue", "continue"]
+    );
+    assert_eq!(
+        outcome
+            .session
+            .events
+            .iter()
+            .filter(|event| event.kind != EventKind::ArtifactRef)
+            .count(),
+        incoming
+            .events
+            .iter()
+            .filter(|event| event.kind != EventKind::ArtifactRef)
+            .count()
+    );
+}
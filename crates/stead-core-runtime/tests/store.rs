@@ -0,0 +1,201 @@
+use std::fs::{File, TryLockError};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+use stead_core_runtime::store::{Recovery, is_temp_file};
+use stead_core_runtime::{
+    MergeStrategy, StorageProfile, Store, StoreOptions, SyncOptions, Workspace,
+};
+use tempfile::TempDir;
+
+fn synced_store(repo: &Path) -> Store {
+    synced_store_with(repo, StoreOptions::default())
+}
+
+fn synced_store_with(repo: &Path, options: StoreOptions) -> Store {
+    let claude_home = TempDir::new().unwrap();
+    let workspace = Workspace::new(Store::with_options(repo, options));
+    workspace
+        .sync(&SyncOptions {
+            codex_base: Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../stead-session-adapters/tests/fixtures/codex"),
+            claude_base: claude_home.path().to_path_buf(),
+            strategy: MergeStrategy::PreferIncoming,
+            profile: StorageProfile::Full,
+            redactor: None,
+        })
+        .unwrap();
+    workspace.store().clone()
+}
+
+#[test]
+fn interrupted_writes_are_ignored_and_recovered() {
+    let repo = TempDir::new().unwrap();
+    let store = synced_store(repo.path());
+    let mut session = store.load("stead:codex:s-new").unwrap();
+
+    // A truncated rewrite of an existing session, and a complete first write that never got
+    // renamed into place.
+    let existing = store.existing_session_path(&session.session_uid).unwrap();
+    let truncated = existing.with_file_name(format!(
+        ".{}.4242.tmp",
+        existing.file_name().unwrap().to_string_lossy()
+    ));
+    std::fs::write(&truncated, "{\"session_uid\": ").unwrap();
+    session.session_uid = "stead:codex:s-crashed".to_string();
+    let target = store.session_path(&session.session_uid);
+    let complete = target.with_file_name(format!(
+        ".{}.4243.tmp",
+        target.file_name().unwrap().to_string_lossy()
+    ));
+    std::fs::write(&complete, serde_json::to_string(&session).unwrap()).unwrap();
+    assert!(is_temp_file(&truncated) && is_temp_file(&complete));
+
+    assert_eq!(store.load_all().unwrap().len(), 2);
+    assert_eq!(store.session_files().unwrap().len(), 2);
+
+    assert_eq!(
+        store.recover().unwrap(),
+        Recovery {
+            promoted: vec![target.clone()],
+            removed: vec![truncated.clone()],
+        }
+    );
+    assert!(!complete.exists());
+    assert_eq!(
+        store.load("stead:codex:s-crashed").unwrap().session_uid,
+        "stead:codex:s-crashed"
+    );
+
+    // Taking the lock recovers as well.
+    std::fs::write(&truncated, "{").unwrap();
+    drop(store.lock().unwrap());
+    assert!(!truncated.exists());
+    assert_eq!(store.load_all().unwrap().len(), 3);
+}
+
+#[test]
+fn store_lock_is_exclusive_and_reentrant() {
+    let repo = TempDir::new().unwrap();
+    let store = synced_store(repo.path());
+    let other = File::options().write(true).open(store.lock_path()).unwrap();
+
+    let outer = store.lock().unwrap();
+    let inner = Store::open(repo.path()).lock().unwrap();
+    assert!(matches!(other.try_lock(), Err(TryLockError::WouldBlock)));
+    drop(inner);
+    assert!(matches!(other.try_lock(), Err(TryLockError::WouldBlock)));
+    drop(outer);
+    other.try_lock().unwrap();
+}
+
+#[test]
+fn store_lock_is_reentrant_across_spellings_of_the_repo_path() {
+    let repo = TempDir::new().unwrap();
+    let store = synced_store(repo.path());
+    std::fs::create_dir(repo.path().join("nested")).unwrap();
+    let spelled = repo.path().join("nested").join("..");
+
+    // Without a shared key the second claim would wait on this thread's own file lock forever.
+    let (acquired_tx, acquired_rx) = mpsc::channel();
+    let holder = {
+        let store = store.clone();
+        std::thread::spawn(move || {
+            let outer = store.lock().unwrap();
+            let inner = Store::open(&spelled).lock().unwrap();
+            acquired_tx.send(()).unwrap();
+            drop(inner);
+            drop(outer);
+        })
+    };
+    acquired_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    holder.join().unwrap();
+
+    let other = File::options().write(true).open(store.lock_path()).unwrap();
+    other.try_lock().unwrap();
+}
+
+#[test]
+fn store_lock_is_only_reentrant_on_the_owning_thread() {
+    let repo = TempDir::new().unwrap();
+    let store = synced_store(repo.path());
+    let outer = store.lock().unwrap();
+
+    let (acquired_tx, acquired_rx) = mpsc::channel();
+    let waiter = {
+        let store = store.clone();
+        std::thread::spawn(move || {
+            let _guard = store.lock().unwrap();
+            acquired_tx.send(()).unwrap();
+        })
+    };
+    assert!(
+        acquired_rx
+            .recv_timeout(Duration::from_millis(200))
+            .is_err()
+    );
+    drop(outer);
+    acquired_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    waiter.join().unwrap();
+
+    let other = File::options().write(true).open(store.lock_path()).unwrap();
+    other.try_lock().unwrap();
+}
+
+#[test]
+fn jsonl_store_writes_one_event_per_line_and_keeps_the_format() {
+    let repo = TempDir::new().unwrap();
+    let jsonl = StoreOptions {
+        jsonl: true,
+        ..StoreOptions::default()
+    };
+    let store = synced_store_with(repo.path(), jsonl);
+    let mut session = store.load("stead:codex:s-new").unwrap();
+    let path = store.existing_session_path(&session.session_uid).unwrap();
+    assert!(path.to_string_lossy().ends_with(".jsonl"));
+    let raw = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<serde_json::Value> = raw
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), session.events.len() + 1);
+    assert_eq!(lines[0]["record"], "session_header");
+    assert_eq!(lines[1]["event_uid"], session.events[0].event_uid);
+
+    // A plain or compressing store keeps writing the session as JSONL once it is.
+    session.metadata.title = Some("renamed".to_string());
+    let compressed = Store::with_options(
+        repo.path(),
+        StoreOptions {
+            compress: true,
+            ..StoreOptions::default()
+        },
+    );
+    assert_eq!(compressed.save(&session).unwrap(), path);
+    assert_eq!(
+        Store::open(repo.path()).load("stead:codex:s-new").unwrap(),
+        session
+    );
+    assert_eq!(store.session_files().unwrap().len(), 2);
+
+    // An interrupted first write in JSONL form is promoted like any other.
+    session.session_uid = "stead:codex:s-crashed".to_string();
+    let target = store
+        .session_path(&session.session_uid)
+        .with_extension("jsonl");
+    let temp = target.with_file_name(format!(
+        ".{}.4243.tmp",
+        target.file_name().unwrap().to_string_lossy()
+    ));
+    let mut buf = Vec::new();
+    stead_session_model::jsonl::write_session_jsonl(&session, &mut buf).unwrap();
+    std::fs::write(&temp, buf).unwrap();
+    assert_eq!(
+        store.recover().unwrap(),
+        Recovery {
+            promoted: vec![target],
+            removed: Vec::new(),
+        }
+    );
+    assert_eq!(store.load("stead:codex:s-crashed").unwrap(), session);
+}
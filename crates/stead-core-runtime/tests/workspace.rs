@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+use stead_core_runtime::native::get_native_ref;
+use stead_core_runtime::store::add_session_uid_alias;
+use stead_core_runtime::{
+    Backend, MergeStrategy, StorageProfile, Store, StoreOptions, SyncOptions, Workspace,
+};
+use stead_session_model::EventKind;
+use tempfile::TempDir;
+
+fn codex_fixture() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../stead-session-adapters/tests/fixtures/codex")
+}
+
+fn sync_options(claude_base: &Path) -> SyncOptions {
+    SyncOptions {
+        codex_base: codex_fixture(),
+        claude_base: claude_base.to_path_buf(),
+        strategy: MergeStrategy::PreferIncoming,
+        profile: StorageProfile::Full,
+        redactor: None,
+    }
+}
+
+#[test]
+fn store_keeps_compressed_sessions_and_resolves_aliases() {
+    let repo = TempDir::new().unwrap();
+    let claude_home = TempDir::new().unwrap();
+    let workspace = Workspace::new(Store::with_options(repo.path(), StoreOptions::default()));
+    workspace.sync(&sync_options(claude_home.path())).unwrap();
+
+    let store = workspace.store();
+    let mut session = store.load("stead:codex:s-new").unwrap();
+    add_session_uid_alias(&mut session, "legacy-id");
+    let compressed = Store::with_options(
+        repo.path(),
+        StoreOptions {
+            compress: true,
+            ..StoreOptions::default()
+        },
+    );
+    let path = compressed.save(&session).unwrap();
+    assert!(path.to_string_lossy().ends_with(".json.zst"));
+
+    // The plain store keeps writing the session compressed once it is.
+    assert_eq!(store.save(&session).unwrap(), path);
+    assert_eq!(store.load("legacy-id").unwrap(), session);
+    assert_eq!(store.find("legacy-id").unwrap(), Some(session));
+    assert!(store.find("stead:codex:missing").unwrap().is_none());
+    assert_eq!(store.load_all().unwrap().len(), 2);
+}
+
+#[test]
+fn handoff_materializes_once_and_sync_keeps_the_projection() {
+    let repo = TempDir::new().unwrap();
+    let claude_home = TempDir::new().unwrap();
+    let workspace = Workspace::new(Store::with_options(repo.path(), StoreOptions::default()));
+    let synced = workspace.sync(&sync_options(claude_home.path())).unwrap();
+    assert_eq!(synced.len(), 2);
+    assert!(synced.iter().all(|entry| entry.backend == Backend::Codex));
+
+    let err = workspace
+        .handoff("stead:codex:s-new", Backend::Claude, None, None)
+        .unwrap_err();
+    assert!(err.to_string().contains("missing native projection"));
+
+    let first = workspace
+        .handoff(
+            "stead:codex:s-new",
+            Backend::Claude,
+            Some(claude_home.path()),
+            None,
+        )
+        .unwrap();
+    assert!(first.materialized);
+    assert!(first.native_path.exists());
+    assert_eq!(
+        first.resume_command(),
+        format!("claude --resume {}", first.native_id)
+    );
+
+    let second = workspace
+        .handoff("stead:codex:s-new", Backend::Claude, None, None)
+        .unwrap();
+    assert!(!second.materialized);
+    assert_eq!(second.native_id, first.native_id);
+
+    let resynced = workspace.sync(&sync_options(claude_home.path())).unwrap();
+    let claude_entry = resynced
+        .iter()
+        .find(|entry| entry.backend == Backend::Claude)
+        .unwrap();
+    assert_eq!(claude_entry.session_uid, "stead:codex:s-new");
+    assert!(claude_entry.branch_session_uid.is_none());
+    let session = workspace.store().load("stead:codex:s-new").unwrap();
+    assert_eq!(
+        get_native_ref(&session, Backend::Claude).unwrap().0,
+        first.native_id
+    );
+}
+
+#[test]
+fn synced_sessions_carry_artifact_ref_events() {
+    let repo = TempDir::new().unwrap();
+    let codex_home = TempDir::new().unwrap();
+    let claude_home = TempDir::new().unwrap();
+    let rollout_dir = codex_home.path().join("sessions/2026/02/18");
+    std::fs::create_dir_all(&rollout_dir).unwrap();
+    let patch = "*** Begin Patch\n*** Add File: notes.md\n+hello\n*** End Patch\n";
+    let lines = [
+        serde_json::json!({"timestamp":"2026-02-18T00:00:00Z","type":"session_meta","payload":{"id":"s-patch","cwd":"/repo"}}),
+        serde_json::json!({"timestamp":"2026-02-18T00:00:01Z","type":"response_item","payload":{"type":"function_call","name":"apply_patch","call_id":"call_patch","arguments":serde_json::json!({"input": patch}).to_string()}}),
+    ];
+    std::fs::write(
+        rollout_dir.join("rollout-2026-02-18T00-00-00-s-patch.jsonl"),
+        lines
+            .iter()
+            .map(|line| format!("{line}\n"))
+            .collect::<String>(),
+    )
+    .unwrap();
+
+    let workspace = Workspace::new(Store::open(repo.path()));
+    workspace
+        .sync(&SyncOptions {
+            codex_base: codex_home.path().to_path_buf(),
+            ..sync_options(claude_home.path())
+        })
+        .unwrap();
+
+    let session = workspace.store().load("stead:codex:s-patch").unwrap();
+    let refs: Vec<(&str, &str)> = session
+        .events
+        .iter()
+        .filter(|event| event.kind == EventKind::ArtifactRef)
+        .map(|event| (event.event_uid.as_str(), event.stream_id.as_str()))
+        .collect();
+    assert_eq!(session.artifacts.len(), 1);
+    assert_eq!(refs, [("artifact-call_patch-0", "main")]);
+}
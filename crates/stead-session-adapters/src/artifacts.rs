@@ -0,0 +1,332 @@
+use crate::patch::{PatchOp, contains_apply_patch, parse_apply_patch};
+use crate::sha256_hex;
+use crate::shell::{ShellWriteKind, shell_file_writes};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use stead_session_model::{
+    EventKind, EventPayload, SessionArtifactRef, SteadEvent, SteadSession, canonical_sort_events,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    Created,
+    Modified,
+    Deleted,
+    Read,
+}
+
+impl ArtifactKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Modified => "modified",
+            Self::Deleted => "deleted",
+            Self::Read => "read",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileTouch {
+    path: String,
+    kind: ArtifactKind,
+    content: Option<String>,
+}
+
+pub fn derive_artifacts(session: &SteadSession) -> Vec<SessionArtifactRef> {
+    let results = tool_results(session);
+    let mut artifacts = Vec::new();
+    for event in &session.events {
+        let (EventKind::ToolCall, EventPayload::ToolCall { tool_name, input }) =
+            (&event.kind, &event.payload)
+        else {
+            continue;
+        };
+        let result = results.get(event.event_uid.as_str()).copied();
+        if result.is_some_and(|(ok, _)| !ok) {
+            continue;
+        }
+        let base_dir = call_base_dir(session, input);
+        let touches = file_touches(tool_name, input, result.and_then(|(_, output)| output));
+        for (index, touch) in touches.into_iter().enumerate() {
+            let mut extensions = Map::new();
+            extensions.insert("tool_name".to_string(), Value::String(tool_name.clone()));
+            artifacts.push(SessionArtifactRef {
+                artifact_uid: format!("artifact-{}-{}", event.event_uid, index),
+                kind: touch.kind.as_str().to_string(),
+                source_event_uid: event.event_uid.clone(),
+                path: Some(resolve_path(base_dir, &touch.path)),
+                mime_type: None,
+                sha256: touch.content.as_deref().map(sha256_hex),
+                extensions,
+            });
+        }
+    }
+    artifacts
+}
+
+/// Recomputes `artifacts` and the `artifact_ref` events that mirror them in the timeline, one
+/// right after each originating tool call.
+pub fn attach_artifacts(session: &mut SteadSession) {
+    session.artifacts = derive_artifacts(session);
+    session
+        .events
+        .retain(|event| event.kind != EventKind::ArtifactRef);
+    let sources: HashMap<&str, &SteadEvent> = session
+        .events
+        .iter()
+        .map(|event| (event.event_uid.as_str(), event))
+        .collect();
+    let events: Vec<SteadEvent> = session
+        .artifacts
+        .iter()
+        .filter_map(|artifact| {
+            let source = sources.get(artifact.source_event_uid.as_str())?;
+            Some(SteadEvent {
+                event_uid: artifact.artifact_uid.clone(),
+                stream_id: source.stream_id.clone(),
+                line_number: source.line_number,
+                sequence: None,
+                timestamp: source.timestamp,
+                kind: EventKind::ArtifactRef,
+                actor: None,
+                payload: EventPayload::Json {
+                    value: serde_json::to_value(artifact).unwrap_or(Value::Null),
+                },
+                raw_vendor_payload: Value::Null,
+                extensions: Map::new(),
+            })
+        })
+        .collect();
+    session.events.extend(events);
+    canonical_sort_events(&mut session.events);
+}
+
+/// Recomputes `artifacts` after event payloads changed, rebuilding the `artifact_ref` events
+/// only if the session already carries them.
+pub fn refresh_artifacts(session: &mut SteadSession) {
+    if session
+        .events
+        .iter()
+        .any(|event| event.kind == EventKind::ArtifactRef)
+    {
+        attach_artifacts(session);
+    } else {
+        session.artifacts = derive_artifacts(session);
+    }
+}
+
+pub(crate) fn tool_results(session: &SteadSession) -> HashMap<&str, (bool, Option<&str>)> {
+    session
+        .events
+        .iter()
+        .filter_map(|event| match &event.payload {
+            EventPayload::ToolResult {
+                call_id,
+                ok,
+                output_text,
+                ..
+            } => Some((call_id.as_str(), (*ok, output_text.as_deref()))),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether a `Write` result says the file is new. Without a result the write may just as well
+/// have replaced an existing file.
+pub(crate) fn write_created_file(output: Option<&str>) -> bool {
+    output.is_some_and(|text| text.contains("File created successfully"))
+}
+
+pub(crate) fn call_base_dir<'a>(session: &'a SteadSession, input: &'a Value) -> Option<&'a str> {
+    input
+        .get("workdir")
+        .and_then(Value::as_str)
+        .or(Some(session.metadata.project_root.as_str()))
+        .filter(|dir| *dir != "/unknown")
+}
+
+pub(crate) enum ShellInput {
+    Patch(String),
+    Script(String),
+}
+
+pub(crate) fn shell_input(input: &Value) -> Option<ShellInput> {
+    let script = match input.get("command").or_else(|| input.get("cmd"))? {
+        Value::String(script) => script.clone(),
+        Value::Array(parts) => {
+            let parts: Vec<&str> = parts.iter().filter_map(Value::as_str).collect();
+            match parts.as_slice() {
+                ["apply_patch", patch, ..] => return Some(ShellInput::Patch(patch.to_string())),
+                [shell, flag, script, ..]
+                    if flag.starts_with('-') && flag.contains('c') && is_shell(shell) =>
+                {
+                    script.to_string()
+                }
+                _ => parts
+                    .iter()
+                    .map(|part| shell_quote(part))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            }
+        }
+        _ => return None,
+    };
+    if contains_apply_patch(&script) {
+        Some(ShellInput::Patch(script))
+    } else {
+        Some(ShellInput::Script(script))
+    }
+}
+
+pub(crate) fn patch_input(input: &Value) -> Option<String> {
+    ["input", "patch", "raw"]
+        .iter()
+        .find_map(|key| input.get(*key).and_then(Value::as_str))
+        .map(str::to_string)
+}
+
+pub(crate) fn is_shell_tool(tool_name: &str) -> bool {
+    matches!(
+        tool_name,
+        "shell" | "local_shell" | "exec_command" | "container.exec"
+    )
+}
+
+fn file_touches(tool_name: &str, input: &Value, output: Option<&str>) -> Vec<FileTouch> {
+    let str_field = |key: &str| input.get(key).and_then(Value::as_str).map(str::to_string);
+    match tool_name {
+        "Write" => str_field("file_path")
+            .map(|path| {
+                vec![FileTouch {
+                    path,
+                    kind: if write_created_file(output) {
+                        ArtifactKind::Created
+                    } else {
+                        ArtifactKind::Modified
+                    },
+                    content: str_field("content"),
+                }]
+            })
+            .unwrap_or_default(),
+        "Edit" | "MultiEdit" => str_field("file_path")
+            .map(|path| {
+                vec![FileTouch {
+                    path,
+                    kind: ArtifactKind::Modified,
+                    content: None,
+                }]
+            })
+            .unwrap_or_default(),
+        "NotebookEdit" => str_field("notebook_path")
+            .map(|path| {
+                vec![FileTouch {
+                    path,
+                    kind: ArtifactKind::Modified,
+                    content: None,
+                }]
+            })
+            .unwrap_or_default(),
+        "Read" | "NotebookRead" => str_field("file_path")
+            .or_else(|| str_field("notebook_path"))
+            .map(|path| {
+                vec![FileTouch {
+                    path,
+                    kind: ArtifactKind::Read,
+                    content: None,
+                }]
+            })
+            .unwrap_or_default(),
+        "apply_patch" => patch_input(input)
+            .map(|patch| patch_touches(&patch))
+            .unwrap_or_default(),
+        name if is_shell_tool(name) => shell_touches(input),
+        _ => Vec::new(),
+    }
+}
+
+fn shell_touches(input: &Value) -> Vec<FileTouch> {
+    let script = match shell_input(input) {
+        Some(ShellInput::Patch(patch)) => return patch_touches(&patch),
+        Some(ShellInput::Script(script)) => script,
+        None => return Vec::new(),
+    };
+    shell_file_writes(&script)
+        .into_iter()
+        .map(|write| FileTouch {
+            path: write.path,
+            kind: match write.kind {
+                ShellWriteKind::Write => ArtifactKind::Modified,
+                ShellWriteKind::Create => ArtifactKind::Created,
+                ShellWriteKind::Delete => ArtifactKind::Deleted,
+            },
+            content: write.content,
+        })
+        .collect()
+}
+
+fn patch_touches(patch: &str) -> Vec<FileTouch> {
+    let mut touches = Vec::new();
+    for op in parse_apply_patch(patch) {
+        match op {
+            PatchOp::Add { path, content } => touches.push(FileTouch {
+                path,
+                kind: ArtifactKind::Created,
+                content: Some(content),
+            }),
+            PatchOp::Delete { path } => touches.push(FileTouch {
+                path,
+                kind: ArtifactKind::Deleted,
+                content: None,
+            }),
+            PatchOp::Update { path, move_to, .. } => match move_to {
+                Some(target) => {
+                    touches.push(FileTouch {
+                        path,
+                        kind: ArtifactKind::Deleted,
+                        content: None,
+                    });
+                    touches.push(FileTouch {
+                        path: target,
+                        kind: ArtifactKind::Created,
+                        content: None,
+                    });
+                }
+                None => touches.push(FileTouch {
+                    path,
+                    kind: ArtifactKind::Modified,
+                    content: None,
+                }),
+            },
+        }
+    }
+    touches
+}
+
+fn is_shell(program: &str) -> bool {
+    matches!(
+        Path::new(program)
+            .file_name()
+            .and_then(|name| name.to_str()),
+        Some("bash" | "sh" | "zsh" | "dash")
+    )
+}
+
+fn shell_quote(part: &str) -> String {
+    if part.is_empty() || part.contains(|c: char| c.is_whitespace() || "'\"\\;&|<>".contains(c)) {
+        format!("'{}'", part.replace('\'', "'\\''"))
+    } else {
+        part.to_string()
+    }
+}
+
+pub(crate) fn resolve_path(base_dir: Option<&str>, path: &str) -> String {
+    let path = path.strip_prefix("./").unwrap_or(path);
+    match base_dir {
+        Some(base) if !Path::new(path).is_absolute() => {
+            Path::new(base).join(path).display().to_string()
+        }
+        _ => path.to_string(),
+    }
+}
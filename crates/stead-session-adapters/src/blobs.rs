@@ -0,0 +1,204 @@
+use crate::compact::{raw_line_arrays, raw_line_arrays_ref};
+use crate::{AdapterError, sha256_hex};
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+use stead_session_model::{EventPayload, SteadSession};
+
+pub const BLOB_REF_KEY: &str = "stead_blob_ref";
+const EVENT_BLOBS_KEY: &str = "blob_refs";
+
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    pub root: PathBuf,
+    pub compress: bool,
+}
+
+impl BlobStore {
+    pub fn new(root: impl AsRef<Path>, compress: bool) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            compress,
+        }
+    }
+
+    pub fn put(&self, bytes: &[u8]) -> Result<String, AdapterError> {
+        let sha256 = sha256_hex(bytes);
+        if self.find(&sha256).is_some() {
+            return Ok(sha256);
+        }
+        let path = self.blob_path(&sha256, self.compress);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write under a temp name first so a crash never leaves a truncated blob behind.
+        let temp = path.with_file_name(format!(".{sha256}.{}.tmp", std::process::id()));
+        if self.compress {
+            std::fs::write(&temp, zstd::encode_all(bytes, 0)?)?;
+        } else {
+            std::fs::write(&temp, bytes)?;
+        }
+        std::fs::rename(&temp, &path)?;
+        Ok(sha256)
+    }
+
+    pub fn get(&self, sha256: &str) -> Result<Vec<u8>, AdapterError> {
+        let path = self
+            .find(sha256)
+            .ok_or_else(|| AdapterError::InvalidFormat(format!("missing blob {sha256}")))?;
+        let raw = std::fs::read(&path)?;
+        if path.extension().is_some_and(|ext| ext == "zst") {
+            Ok(zstd::decode_all(raw.as_slice())?)
+        } else {
+            Ok(raw)
+        }
+    }
+
+    pub fn find(&self, sha256: &str) -> Option<PathBuf> {
+        [false, true]
+            .into_iter()
+            .map(|compressed| self.blob_path(sha256, compressed))
+            .find(|path| path.is_file())
+    }
+
+    /// Moves tool output bodies and raw vendor lines of at least `threshold` bytes into the blob
+    /// directory. Output bodies are recorded in the event's `extensions.blob_refs`, raw lines are
+    /// replaced by a `stead_blob_ref` object.
+    pub fn externalize_session(
+        &self,
+        session: &mut SteadSession,
+        threshold: usize,
+    ) -> Result<usize, AdapterError> {
+        let mut moved = 0;
+        for event in &mut session.events {
+            if let EventPayload::ToolResult {
+                output_text,
+                error_text,
+                ..
+            } = &mut event.payload
+            {
+                for (field, text) in [("output_text", output_text), ("error_text", error_text)] {
+                    let Some(body) = text.as_ref().filter(|body| body.len() >= threshold) else {
+                        continue;
+                    };
+                    let sha256 = self.put(body.as_bytes())?;
+                    let refs = event
+                        .extensions
+                        .entry(EVENT_BLOBS_KEY.to_string())
+                        .or_insert_with(|| json!({}));
+                    refs[field] = json!({ "sha256": sha256, "size": body.len() });
+                    *text = None;
+                    moved += 1;
+                }
+            }
+            moved += self.externalize_value(&mut event.raw_vendor_payload, threshold)?;
+        }
+        for lines in raw_line_arrays(&mut session.raw_vendor_payload) {
+            for line in lines {
+                moved += self.externalize_value(line, threshold)?;
+            }
+        }
+        Ok(moved)
+    }
+
+    pub fn rehydrate_session(&self, session: &mut SteadSession) -> Result<usize, AdapterError> {
+        let mut restored = 0;
+        for event in &mut session.events {
+            if let Some(refs) = event.extensions.remove(EVENT_BLOBS_KEY)
+                && let EventPayload::ToolResult {
+                    output_text,
+                    error_text,
+                    ..
+                } = &mut event.payload
+            {
+                for (field, text) in [("output_text", output_text), ("error_text", error_text)] {
+                    let Some(sha256) = refs
+                        .get(field)
+                        .and_then(|blob| blob.get("sha256"))
+                        .and_then(Value::as_str)
+                    else {
+                        continue;
+                    };
+                    let body = String::from_utf8(self.get(sha256)?).map_err(|err| {
+                        AdapterError::InvalidFormat(format!("blob {sha256} is not utf-8: {err}"))
+                    })?;
+                    *text = Some(body);
+                    restored += 1;
+                }
+            }
+            restored += self.rehydrate_value(&mut event.raw_vendor_payload)?;
+        }
+        for lines in raw_line_arrays(&mut session.raw_vendor_payload) {
+            for line in lines {
+                restored += self.rehydrate_value(line)?;
+            }
+        }
+        Ok(restored)
+    }
+
+    fn externalize_value(
+        &self,
+        value: &mut Value,
+        threshold: usize,
+    ) -> Result<usize, AdapterError> {
+        if !value.is_object() || value.get(BLOB_REF_KEY).is_some() {
+            return Ok(0);
+        }
+        let serialized = serde_json::to_vec(value)?;
+        if serialized.len() < threshold {
+            return Ok(0);
+        }
+        let sha256 = self.put(&serialized)?;
+        *value = json!({ BLOB_REF_KEY: { "sha256": sha256, "size": serialized.len() } });
+        Ok(1)
+    }
+
+    fn rehydrate_value(&self, value: &mut Value) -> Result<usize, AdapterError> {
+        let Some(sha256) = blob_ref_sha256(value) else {
+            return Ok(0);
+        };
+        *value = serde_json::from_slice(&self.get(&sha256)?)?;
+        Ok(1)
+    }
+
+    fn blob_path(&self, sha256: &str, compressed: bool) -> PathBuf {
+        let prefix = sha256.get(..2).unwrap_or("00");
+        let name = if compressed {
+            format!("{sha256}.zst")
+        } else {
+            sha256.to_string()
+        };
+        self.root.join(prefix).join(name)
+    }
+}
+
+/// Blob hashes referenced by a stored (externalized) session.
+pub fn referenced_blobs(session: &SteadSession) -> Vec<String> {
+    let mut out = Vec::new();
+    for event in &session.events {
+        if let Some(refs) = event
+            .extensions
+            .get(EVENT_BLOBS_KEY)
+            .and_then(Value::as_object)
+        {
+            out.extend(
+                refs.values()
+                    .filter_map(|blob| blob.get("sha256")?.as_str().map(str::to_string)),
+            );
+        }
+        out.extend(blob_ref_sha256(&event.raw_vendor_payload));
+    }
+    for lines in raw_line_arrays_ref(&session.raw_vendor_payload) {
+        out.extend(lines.iter().filter_map(blob_ref_sha256));
+    }
+    out.sort();
+    out.dedup();
+    out
+}
+
+fn blob_ref_sha256(value: &Value) -> Option<String> {
+    value
+        .get(BLOB_REF_KEY)?
+        .get("sha256")?
+        .as_str()
+        .map(str::to_string)
+}
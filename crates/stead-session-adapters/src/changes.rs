@@ -0,0 +1,546 @@
+use crate::artifacts::{
+    ArtifactKind, ShellInput, call_base_dir, is_shell_tool, patch_input, resolve_path, shell_input,
+    tool_results, write_created_file,
+};
+use crate::patch::{PatchHunk, PatchOp, parse_apply_patch};
+use crate::shell::{ShellWriteKind, shell_file_writes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use similar::TextDiff;
+use std::collections::HashMap;
+use std::path::Path;
+use stead_session_model::{EventKind, EventPayload, SteadSession};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_path: Option<String>,
+    pub kind: String,
+    pub source_event_uid: String,
+    pub tool_name: String,
+    pub timestamp: DateTime<Utc>,
+    pub exact: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChanges {
+    pub path: String,
+    pub changes: Vec<FileChange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub net_diff: Option<String>,
+    #[serde(skip)]
+    pub final_content: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FileState {
+    Unknown,
+    Absent,
+    Known(String),
+}
+
+impl FileState {
+    fn text(&self) -> Option<&str> {
+        match self {
+            Self::Known(text) => Some(text),
+            Self::Absent => Some(""),
+            Self::Unknown => None,
+        }
+    }
+}
+
+enum FileOp {
+    Write {
+        path: String,
+        content: String,
+        created_hint: bool,
+    },
+    Edit {
+        path: String,
+        edits: Vec<(String, String, bool)>,
+    },
+    Patch {
+        path: String,
+        move_to: Option<String>,
+        hunks: Vec<PatchHunk>,
+    },
+    Delete {
+        path: String,
+    },
+    Opaque {
+        path: String,
+        kind: ArtifactKind,
+    },
+}
+
+#[derive(Default)]
+struct Tracker {
+    states: HashMap<String, FileState>,
+    initial: HashMap<String, FileState>,
+    order: Vec<String>,
+    changes: HashMap<String, Vec<FileChange>>,
+}
+
+pub fn session_file_changes(session: &SteadSession) -> Vec<FileChanges> {
+    let results = tool_results(session);
+    let mut tracker = Tracker::default();
+
+    for event in &session.events {
+        let (EventKind::ToolCall, EventPayload::ToolCall { tool_name, input }) =
+            (&event.kind, &event.payload)
+        else {
+            continue;
+        };
+        let result = results.get(event.event_uid.as_str()).copied();
+        if result.is_some_and(|(ok, _)| !ok) {
+            continue;
+        }
+        let output = result.and_then(|(_, output)| output);
+        let base_dir = call_base_dir(session, input);
+        for op in file_ops(tool_name, input, output) {
+            tracker.apply(op, base_dir, tool_name, &event.event_uid, event.timestamp);
+        }
+    }
+    tracker.finish()
+}
+
+pub fn render_git_patch(files: &[FileChanges], project_root: &str) -> String {
+    let mut out = String::new();
+    let mut skipped = Vec::new();
+    for file in files {
+        if let Some(net_diff) = file.net_diff.as_deref() {
+            let first = &file.changes[0];
+            let last = &file.changes[file.changes.len() - 1];
+            let created = first.kind == ArtifactKind::Created.as_str();
+            let deleted = last.kind == ArtifactKind::Deleted.as_str();
+            if !(created && deleted) {
+                out.push_str(&git_file_header(
+                    &relative_path(&file.path, project_root),
+                    None,
+                    created,
+                    deleted,
+                ));
+                out.push_str(net_diff);
+            }
+            continue;
+        }
+        for change in &file.changes {
+            let diff = match change.diff.as_deref() {
+                Some(diff) if change.exact => diff,
+                // Inexact diffs are built from snippets and would not apply to the real file.
+                other => {
+                    skipped.push(format!(
+                        "# skipped {} {} from {} ({})\n",
+                        change.kind,
+                        relative_path(&change.path, project_root),
+                        change.source_event_uid,
+                        if other.is_none() {
+                            "content not recorded"
+                        } else {
+                            "prior content unknown"
+                        }
+                    ));
+                    continue;
+                }
+            };
+            let previous = change
+                .previous_path
+                .as_deref()
+                .map(|path| relative_path(path, project_root));
+            out.push_str(&git_file_header(
+                &relative_path(&change.path, project_root),
+                previous.as_deref(),
+                change.kind == ArtifactKind::Created.as_str(),
+                change.kind == ArtifactKind::Deleted.as_str(),
+            ));
+            out.push_str(diff);
+        }
+    }
+    skipped.concat() + &out
+}
+
+impl Tracker {
+    fn apply(
+        &mut self,
+        op: FileOp,
+        base_dir: Option<&str>,
+        tool_name: &str,
+        event_uid: &str,
+        timestamp: DateTime<Utc>,
+    ) {
+        let change =
+            |path: &str, kind: ArtifactKind, exact: bool, diff: Option<String>| FileChange {
+                path: path.to_string(),
+                previous_path: None,
+                kind: kind.as_str().to_string(),
+                source_event_uid: event_uid.to_string(),
+                tool_name: tool_name.to_string(),
+                timestamp,
+                exact,
+                diff,
+            };
+
+        match op {
+            FileOp::Write {
+                path,
+                content,
+                created_hint,
+            } => {
+                let path = resolve_path(base_dir, &path);
+                let before = match self.states.get(&path) {
+                    Some(state) => state.clone(),
+                    None if created_hint => FileState::Absent,
+                    None => FileState::Unknown,
+                };
+                let kind = if before == FileState::Absent {
+                    ArtifactKind::Created
+                } else {
+                    ArtifactKind::Modified
+                };
+                let exact = before.text().is_some();
+                let diff = unified(before.text().unwrap_or_default(), &content);
+                self.record(
+                    &path,
+                    before,
+                    FileState::Known(content),
+                    change(&path, kind, exact, Some(diff)),
+                );
+            }
+            FileOp::Edit { path, edits } => {
+                let path = resolve_path(base_dir, &path);
+                let before = self.state(&path);
+                let applied = before.text().and_then(|text| {
+                    edits
+                        .iter()
+                        .try_fold(text.to_string(), |text, (old, new, all)| {
+                            replace_in(&text, old, new, *all)
+                        })
+                });
+                let (after, exact, diff) = match (before.text(), applied) {
+                    (Some(old_text), Some(new_text)) => {
+                        let diff = unified(old_text, &new_text);
+                        (FileState::Known(new_text), true, diff)
+                    }
+                    _ => {
+                        let diff = edits
+                            .iter()
+                            .map(|(old, new, _)| unified(&with_newline(old), &with_newline(new)))
+                            .collect::<String>();
+                        (FileState::Unknown, false, diff)
+                    }
+                };
+                self.record(
+                    &path,
+                    before,
+                    after,
+                    change(&path, ArtifactKind::Modified, exact, Some(diff)),
+                );
+            }
+            FileOp::Patch {
+                path,
+                move_to,
+                hunks,
+            } => {
+                let path = resolve_path(base_dir, &path);
+                let target = move_to
+                    .as_deref()
+                    .map(|target| resolve_path(base_dir, target))
+                    .unwrap_or_else(|| path.clone());
+                let before = self.state(&path);
+                let applied = before.text().and_then(|text| apply_hunks(text, &hunks));
+                let (after, exact, diff) = match (before.text(), applied) {
+                    (Some(old_text), Some(new_text)) => {
+                        let diff = unified(old_text, &new_text);
+                        (FileState::Known(new_text), true, diff)
+                    }
+                    _ => {
+                        let diff = hunks
+                            .iter()
+                            .map(|hunk| {
+                                unified(&join_lines(&hunk.old_lines), &join_lines(&hunk.new_lines))
+                            })
+                            .collect::<String>();
+                        (FileState::Unknown, false, diff)
+                    }
+                };
+                let mut file_change = change(&target, ArtifactKind::Modified, exact, Some(diff));
+                if target != path {
+                    file_change.previous_path = Some(path.clone());
+                    self.states.insert(path.clone(), FileState::Absent);
+                }
+                self.record(&target, before, after, file_change);
+            }
+            FileOp::Delete { path } => {
+                let path = resolve_path(base_dir, &path);
+                let before = self.state(&path);
+                let diff = before.text().map(|text| unified(text, ""));
+                let exact = diff.is_some();
+                self.record(
+                    &path,
+                    before,
+                    FileState::Absent,
+                    change(&path, ArtifactKind::Deleted, exact, diff),
+                );
+            }
+            FileOp::Opaque { path, kind } => {
+                let path = resolve_path(base_dir, &path);
+                let before = self.state(&path);
+                self.record(
+                    &path,
+                    before,
+                    FileState::Unknown,
+                    change(&path, kind, false, None),
+                );
+            }
+        }
+    }
+
+    fn state(&self, path: &str) -> FileState {
+        self.states.get(path).cloned().unwrap_or(FileState::Unknown)
+    }
+
+    fn record(&mut self, path: &str, before: FileState, after: FileState, change: FileChange) {
+        if !self.changes.contains_key(path) {
+            self.order.push(path.to_string());
+            self.initial.insert(path.to_string(), before);
+        }
+        self.states.insert(path.to_string(), after);
+        self.changes
+            .entry(path.to_string())
+            .or_default()
+            .push(change);
+    }
+
+    fn finish(mut self) -> Vec<FileChanges> {
+        self.order
+            .iter()
+            .map(|path| {
+                let changes = self.changes.remove(path).unwrap_or_default();
+                let initial = self.initial.get(path).and_then(FileState::text);
+                let current = self.states.get(path).and_then(FileState::text);
+                let net_diff = match (initial, current) {
+                    (Some(initial), Some(current))
+                        if changes
+                            .iter()
+                            .all(|change| change.exact && change.previous_path.is_none()) =>
+                    {
+                        Some(unified(initial, current))
+                    }
+                    _ => None,
+                };
+                let final_content = match self.states.get(path) {
+                    Some(FileState::Known(text)) => Some(text.clone()),
+                    _ => None,
+                };
+                FileChanges {
+                    path: path.clone(),
+                    changes,
+                    net_diff,
+                    final_content,
+                }
+            })
+            .collect()
+    }
+}
+
+fn file_ops(tool_name: &str, input: &Value, output: Option<&str>) -> Vec<FileOp> {
+    let str_field = |key: &str| input.get(key).and_then(Value::as_str).map(str::to_string);
+    let edit = |value: &Value| {
+        Some((
+            value.get("old_string")?.as_str()?.to_string(),
+            value.get("new_string")?.as_str()?.to_string(),
+            value
+                .get("replace_all")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        ))
+    };
+    match tool_name {
+        "Write" => match (str_field("file_path"), str_field("content")) {
+            (Some(path), Some(content)) => vec![FileOp::Write {
+                path,
+                content,
+                created_hint: write_created_file(output),
+            }],
+            _ => Vec::new(),
+        },
+        "Edit" => match (str_field("file_path"), edit(input)) {
+            (Some(path), Some(edit)) => vec![FileOp::Edit {
+                path,
+                edits: vec![edit],
+            }],
+            _ => Vec::new(),
+        },
+        "MultiEdit" => match (
+            str_field("file_path"),
+            input.get("edits").and_then(Value::as_array),
+        ) {
+            (Some(path), Some(edits)) => vec![FileOp::Edit {
+                path,
+                edits: edits.iter().filter_map(edit).collect(),
+            }],
+            _ => Vec::new(),
+        },
+        "NotebookEdit" => str_field("notebook_path")
+            .map(|path| {
+                vec![FileOp::Opaque {
+                    path,
+                    kind: ArtifactKind::Modified,
+                }]
+            })
+            .unwrap_or_default(),
+        "apply_patch" => patch_input(input)
+            .map(|patch| patch_ops(&patch))
+            .unwrap_or_default(),
+        name if is_shell_tool(name) => match shell_input(input) {
+            Some(ShellInput::Patch(patch)) => patch_ops(&patch),
+            Some(ShellInput::Script(script)) => shell_file_writes(&script)
+                .into_iter()
+                .map(|write| match (write.kind, write.content) {
+                    (ShellWriteKind::Delete, _) => FileOp::Delete { path: write.path },
+                    (ShellWriteKind::Write, Some(content)) => FileOp::Write {
+                        path: write.path,
+                        content,
+                        created_hint: false,
+                    },
+                    (ShellWriteKind::Write, None) => FileOp::Opaque {
+                        path: write.path,
+                        kind: ArtifactKind::Modified,
+                    },
+                    (ShellWriteKind::Create, _) => FileOp::Opaque {
+                        path: write.path,
+                        kind: ArtifactKind::Created,
+                    },
+                })
+                .collect(),
+            None => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+fn patch_ops(patch: &str) -> Vec<FileOp> {
+    parse_apply_patch(patch)
+        .into_iter()
+        .map(|op| match op {
+            PatchOp::Add { path, content } => FileOp::Write {
+                path,
+                content,
+                created_hint: true,
+            },
+            PatchOp::Delete { path } => FileOp::Delete { path },
+            PatchOp::Update {
+                path,
+                move_to,
+                hunks,
+            } => FileOp::Patch {
+                path,
+                move_to,
+                hunks,
+            },
+        })
+        .collect()
+}
+
+fn replace_in(text: &str, old: &str, new: &str, all: bool) -> Option<String> {
+    if old.is_empty() || !text.contains(old) {
+        return None;
+    }
+    if all {
+        Some(text.replace(old, new))
+    } else {
+        Some(text.replacen(old, new, 1))
+    }
+}
+
+fn apply_hunks(text: &str, hunks: &[PatchHunk]) -> Option<String> {
+    let trailing_newline = text.is_empty() || text.ends_with('\n');
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let mut cursor = 0;
+    for hunk in hunks {
+        let position = if hunk.old_lines.is_empty() {
+            lines.len()
+        } else {
+            find_block(&lines, &hunk.old_lines, cursor)
+                .or_else(|| find_block(&lines, &hunk.old_lines, 0))?
+        };
+        lines.splice(
+            position..position + hunk.old_lines.len(),
+            hunk.new_lines.iter().cloned(),
+        );
+        cursor = position + hunk.new_lines.len();
+    }
+    let mut out = lines.join("\n");
+    if trailing_newline && !lines.is_empty() {
+        out.push('\n');
+    }
+    Some(out)
+}
+
+fn find_block(lines: &[String], block: &[String], from: usize) -> Option<usize> {
+    if block.len() > lines.len() {
+        return None;
+    }
+    (from..=lines.len() - block.len()).find(|start| {
+        lines[*start..*start + block.len()]
+            .iter()
+            .zip(block)
+            .all(|(line, expected)| line.trim_end() == expected.trim_end())
+    })
+}
+
+fn unified(old: &str, new: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .to_string()
+}
+
+fn with_newline(text: &str) -> String {
+    if text.is_empty() || text.ends_with('\n') {
+        text.to_string()
+    } else {
+        format!("{text}\n")
+    }
+}
+
+fn join_lines(lines: &[String]) -> String {
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+fn relative_path(path: &str, project_root: &str) -> String {
+    Path::new(path)
+        .strip_prefix(project_root)
+        .map(|relative| relative.display().to_string())
+        .unwrap_or_else(|_| path.trim_start_matches('/').to_string())
+}
+
+fn git_file_header(path: &str, previous: Option<&str>, created: bool, deleted: bool) -> String {
+    let source = previous.unwrap_or(path);
+    let mut header = format!("diff --git a/{source} b/{path}\n");
+    if let Some(previous) = previous {
+        header.push_str(&format!("rename from {previous}\nrename to {path}\n"));
+    }
+    if created {
+        header.push_str("new file mode 100644\n");
+    }
+    if deleted {
+        header.push_str("deleted file mode 100644\n");
+    }
+    let old = if created {
+        "/dev/null".to_string()
+    } else {
+        format!("a/{source}")
+    };
+    let new = if deleted {
+        "/dev/null".to_string()
+    } else {
+        format!("b/{path}")
+    };
+    header.push_str(&format!("--- {old}\n+++ {new}\n"));
+    header
+}
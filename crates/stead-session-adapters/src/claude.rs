@@ -1,3 +1,5 @@
+use crate::artifacts::derive_artifacts;
+use crate::compact::rehydrated;
 use crate::{AdapterError, ExportReport, NativeSessionRef};
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
@@ -102,6 +104,7 @@ impl ClaudeAdapter {
         session.events = dedupe_events_by_identity(std::mem::take(&mut session.events));
         canonical_sort_events(&mut session.events);
         session.source.source_files = dedupe_strings_preserve_order(source_files);
+        session.artifacts = derive_artifacts(&session);
         Ok(session)
     }
 
@@ -360,7 +363,7 @@ impl ClaudeAdapter {
                 .to_string()
         });
 
-        Ok(SteadSession {
+        let mut session = SteadSession {
             schema_version: schema_version().to_string(),
             session_uid: build_session_uid(BackendKind::ClaudeCode, &session_id),
             shared_session_uid: None,
@@ -381,7 +384,9 @@ impl ClaudeAdapter {
             extensions: Map::new(),
             lineage: None,
             raw_vendor_payload: json!({ "lines": raw_lines }),
-        })
+        };
+        session.artifacts = derive_artifacts(&session);
+        Ok(session)
     }
 
     pub fn export_session(
@@ -389,6 +394,8 @@ impl ClaudeAdapter {
         session: &SteadSession,
         output_path: impl AsRef<Path>,
     ) -> Result<ExportReport, AdapterError> {
+        let rehydrated = rehydrated(session);
+        let session = rehydrated.as_ref();
         let mut file = File::create(output_path.as_ref())?;
         let claude_version =
             first_raw_string_field(session, "version").unwrap_or_else(|| "2.1.47".to_string());
@@ -396,7 +403,13 @@ impl ClaudeAdapter {
             first_raw_string_field(session, "gitBranch").unwrap_or_else(|| "main".to_string());
         let mut parent_uuid: Option<String> = None;
 
-        for event in &session.events {
+        // Artifact refs are derived from tool calls and re-created on import.
+        let events: Vec<&SteadEvent> = session
+            .events
+            .iter()
+            .filter(|event| event.kind != EventKind::ArtifactRef)
+            .collect();
+        for event in &events {
             let line = merge_with_raw_unknowns(
                 event_to_claude_line(
                     event,
@@ -417,7 +430,7 @@ impl ClaudeAdapter {
 
         Ok(ExportReport {
             output_path: output_path.as_ref().to_path_buf(),
-            events_exported: session.events.len(),
+            events_exported: events.len(),
             warnings: vec![],
             losses: vec![],
         })
@@ -1,3 +1,5 @@
+use crate::artifacts::derive_artifacts;
+use crate::compact::rehydrated;
 use crate::{AdapterError, ExportReport, NativeSessionRef};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -199,7 +201,7 @@ impl CodexAdapter {
                 .to_string()
         });
 
-        Ok(SteadSession {
+        let mut session = SteadSession {
             schema_version: schema_version().to_string(),
             session_uid: build_session_uid(BackendKind::Codex, &original_id),
             shared_session_uid: None,
@@ -220,7 +222,9 @@ impl CodexAdapter {
             extensions: Map::new(),
             lineage: None,
             raw_vendor_payload: json!({ "lines": raw_lines }),
-        })
+        };
+        session.artifacts = derive_artifacts(&session);
+        Ok(session)
     }
 
     pub fn export_session(
@@ -228,6 +232,8 @@ impl CodexAdapter {
         session: &SteadSession,
         output_path: impl AsRef<Path>,
     ) -> Result<ExportReport, AdapterError> {
+        let rehydrated = rehydrated(session);
+        let session = rehydrated.as_ref();
         let mut file = File::create(output_path.as_ref())?;
         let ts = session.metadata.created_at.to_rfc3339();
         let session_meta = json!({
@@ -257,7 +263,13 @@ impl CodexAdapter {
         );
         writeln!(file, "{}", serde_json::to_string(&session_meta)?)?;
 
-        for event in &session.events {
+        // Artifact refs are derived from tool calls and re-created on import.
+        let events: Vec<&SteadEvent> = session
+            .events
+            .iter()
+            .filter(|event| event.kind != EventKind::ArtifactRef)
+            .collect();
+        for event in &events {
             let line = merge_with_raw_unknowns(
                 event_to_codex_line(event),
                 Some(&event.raw_vendor_payload),
@@ -267,7 +279,7 @@ impl CodexAdapter {
 
         Ok(ExportReport {
             output_path: output_path.as_ref().to_path_buf(),
-            events_exported: session.events.len(),
+            events_exported: events.len(),
             warnings: vec![],
             losses: vec![],
         })
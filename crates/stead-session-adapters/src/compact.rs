@@ -0,0 +1,217 @@
+use crate::sha256_hex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use stead_session_model::SteadSession;
+
+pub const RAW_REF_KEY: &str = "stead_raw_ref";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactReport {
+    pub compacted: usize,
+    pub kept_inline: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RehydrateReport {
+    pub restored: usize,
+    pub missing: usize,
+}
+
+pub fn raw_line_sha256(line: &Value) -> String {
+    let serialized = serde_json::to_string(line).unwrap_or_default();
+    sha256_hex(serialized)
+}
+
+/// Replaces raw vendor lines that can still be found in `source.source_files` with a
+/// `{sha256, source_file, line_number}` reference that keeps the line's `timestamp`. Lines that
+/// no longer match their native file (redacted, edited or deleted) or are smaller than their
+/// reference stay inline.
+pub fn compact_session(session: &mut SteadSession) -> CompactReport {
+    let mut index = SourceIndex::default();
+    for file in &session.source.source_files {
+        index.add_file(file);
+    }
+    let mut report = CompactReport::default();
+    for event in &mut session.events {
+        compact_value(&mut event.raw_vendor_payload, &index, &mut report);
+    }
+    for lines in raw_line_arrays(&mut session.raw_vendor_payload) {
+        for line in lines {
+            compact_value(line, &index, &mut report);
+        }
+    }
+    report
+}
+
+/// Restores referenced raw vendor lines from their native files.
+pub fn rehydrate_session(session: &mut SteadSession) -> RehydrateReport {
+    let mut files = FileCache::default();
+    let mut report = RehydrateReport::default();
+    for event in &mut session.events {
+        rehydrate_value(&mut event.raw_vendor_payload, &mut files, &mut report);
+    }
+    for lines in raw_line_arrays(&mut session.raw_vendor_payload) {
+        for line in lines {
+            rehydrate_value(line, &mut files, &mut report);
+        }
+    }
+    report
+}
+
+pub fn is_compact(session: &SteadSession) -> bool {
+    session
+        .events
+        .iter()
+        .any(|event| raw_ref(&event.raw_vendor_payload).is_some())
+        || raw_line_arrays_ref(&session.raw_vendor_payload)
+            .into_iter()
+            .flatten()
+            .any(|line| raw_ref(line).is_some())
+}
+
+pub(crate) fn rehydrated(session: &SteadSession) -> Cow<'_, SteadSession> {
+    if !is_compact(session) {
+        return Cow::Borrowed(session);
+    }
+    let mut owned = session.clone();
+    rehydrate_session(&mut owned);
+    Cow::Owned(owned)
+}
+
+#[derive(Debug, Clone)]
+struct RawRef {
+    sha256: String,
+    source_file: String,
+    line_number: usize,
+}
+
+fn raw_ref(value: &Value) -> Option<RawRef> {
+    let inner = value.get(RAW_REF_KEY)?;
+    Some(RawRef {
+        sha256: inner.get("sha256")?.as_str()?.to_string(),
+        source_file: inner.get("source_file")?.as_str()?.to_string(),
+        line_number: inner.get("line_number")?.as_u64()? as usize,
+    })
+}
+
+fn compact_value(value: &mut Value, index: &SourceIndex, report: &mut CompactReport) {
+    if !value.is_object() || raw_ref(value).is_some() {
+        return;
+    }
+    let sha256 = raw_line_sha256(value);
+    match index.locations.get(&sha256) {
+        Some((source_file, line_number)) => {
+            let mut reference = json!({
+                RAW_REF_KEY: {
+                    "sha256": sha256,
+                    "source_file": source_file,
+                    "line_number": line_number
+                }
+            });
+            if let Some(timestamp) = value.get("timestamp") {
+                reference["timestamp"] = timestamp.clone();
+            }
+            if serialized_len(&reference) >= serialized_len(value) {
+                report.kept_inline += 1;
+                return;
+            }
+            *value = reference;
+            report.compacted += 1;
+        }
+        None => report.kept_inline += 1,
+    }
+}
+
+fn serialized_len(value: &Value) -> usize {
+    serde_json::to_string(value).map_or(0, |raw| raw.len())
+}
+
+fn rehydrate_value(value: &mut Value, files: &mut FileCache, report: &mut RehydrateReport) {
+    let Some(reference) = raw_ref(value) else {
+        return;
+    };
+    let lines = files.lines(&reference.source_file);
+    let at_line = lines
+        .get(reference.line_number)
+        .filter(|(sha256, _)| *sha256 == reference.sha256);
+    let found = at_line.or_else(|| lines.iter().find(|(sha256, _)| *sha256 == reference.sha256));
+    match found {
+        Some((_, line)) => {
+            *value = line.clone();
+            report.restored += 1;
+        }
+        None => report.missing += 1,
+    }
+}
+
+pub(crate) fn raw_line_arrays(raw: &mut Value) -> Vec<&mut Vec<Value>> {
+    let Some(raw) = raw.as_object_mut() else {
+        return Vec::new();
+    };
+    let mut arrays = Vec::new();
+    for (key, value) in raw.iter_mut() {
+        match (key.as_str(), value) {
+            ("lines", Value::Array(lines)) => arrays.push(lines),
+            ("backend_lines", Value::Object(backends)) => {
+                arrays.extend(backends.values_mut().filter_map(Value::as_array_mut))
+            }
+            _ => {}
+        }
+    }
+    arrays
+}
+
+pub(crate) fn raw_line_arrays_ref(raw: &Value) -> Vec<&Vec<Value>> {
+    let mut arrays = Vec::new();
+    if let Some(lines) = raw.get("lines").and_then(Value::as_array) {
+        arrays.push(lines);
+    }
+    if let Some(backends) = raw.get("backend_lines").and_then(Value::as_object) {
+        arrays.extend(backends.values().filter_map(Value::as_array));
+    }
+    arrays
+}
+
+#[derive(Default)]
+struct SourceIndex {
+    locations: HashMap<String, (String, usize)>,
+}
+
+impl SourceIndex {
+    fn add_file(&mut self, path: &str) {
+        for (line_number, (sha256, _)) in read_lines(path).into_iter().enumerate() {
+            if !sha256.is_empty() {
+                self.locations
+                    .entry(sha256)
+                    .or_insert_with(|| (path.to_string(), line_number));
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct FileCache {
+    files: HashMap<String, Vec<(String, Value)>>,
+}
+
+impl FileCache {
+    fn lines(&mut self, path: &str) -> &[(String, Value)] {
+        self.files
+            .entry(path.to_string())
+            .or_insert_with(|| read_lines(path))
+    }
+}
+
+fn read_lines(path: &str) -> Vec<(String, Value)> {
+    let Ok(raw) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    raw.lines()
+        .map(|line| match serde_json::from_str::<Value>(line) {
+            Ok(value) => (raw_line_sha256(&value), value),
+            Err(_) => (String::new(), Value::Null),
+        })
+        .collect()
+}
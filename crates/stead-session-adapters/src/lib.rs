@@ -1,8 +1,18 @@
+pub mod artifacts;
+pub mod blobs;
+pub mod changes;
 pub mod claude;
 pub mod codex;
+pub mod compact;
+mod patch;
+pub mod redact;
+pub mod remap;
+mod shell;
+pub mod verify;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -23,6 +33,11 @@ pub struct ExportReport {
     pub losses: Vec<String>,
 }
 
+/// Lowercase hex SHA-256, the form every hash in sessions, blobs and the store uses.
+pub fn sha256_hex(bytes: impl AsRef<[u8]>) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
 #[derive(Debug, Error)]
 pub enum AdapterError {
     #[error("io error: {0}")]
@@ -0,0 +1,110 @@
+const BEGIN_PATCH: &str = "*** Begin Patch";
+const END_PATCH: &str = "*** End Patch";
+const ADD_FILE: &str = "*** Add File: ";
+const DELETE_FILE: &str = "*** Delete File: ";
+const UPDATE_FILE: &str = "*** Update File: ";
+const MOVE_TO: &str = "*** Move to: ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PatchOp {
+    Add {
+        path: String,
+        content: String,
+    },
+    Delete {
+        path: String,
+    },
+    Update {
+        path: String,
+        move_to: Option<String>,
+        hunks: Vec<PatchHunk>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct PatchHunk {
+    pub old_lines: Vec<String>,
+    pub new_lines: Vec<String>,
+}
+
+pub(crate) fn contains_apply_patch(text: &str) -> bool {
+    text.contains(BEGIN_PATCH)
+}
+
+pub(crate) fn parse_apply_patch(text: &str) -> Vec<PatchOp> {
+    let Some(start) = text.find(BEGIN_PATCH) else {
+        return Vec::new();
+    };
+    let mut ops = Vec::new();
+    let mut current: Option<PatchOp> = None;
+
+    for line in text[start + BEGIN_PATCH.len()..].lines() {
+        if line.trim_end() == END_PATCH {
+            break;
+        }
+        if let Some(path) = line.strip_prefix(ADD_FILE) {
+            ops.extend(current.take());
+            current = Some(PatchOp::Add {
+                path: path.trim().to_string(),
+                content: String::new(),
+            });
+        } else if let Some(path) = line.strip_prefix(DELETE_FILE) {
+            ops.extend(current.take());
+            ops.push(PatchOp::Delete {
+                path: path.trim().to_string(),
+            });
+        } else if let Some(path) = line.strip_prefix(UPDATE_FILE) {
+            ops.extend(current.take());
+            current = Some(PatchOp::Update {
+                path: path.trim().to_string(),
+                move_to: None,
+                hunks: Vec::new(),
+            });
+        } else if let Some(target) = line.strip_prefix(MOVE_TO) {
+            if let Some(PatchOp::Update { move_to, .. }) = current.as_mut() {
+                *move_to = Some(target.trim().to_string());
+            }
+        } else {
+            match current.as_mut() {
+                Some(PatchOp::Add { content, .. }) => {
+                    if let Some(added) = line.strip_prefix('+') {
+                        content.push_str(added);
+                        content.push('\n');
+                    }
+                }
+                Some(PatchOp::Update { hunks, .. }) => push_hunk_line(hunks, line),
+                _ => {}
+            }
+        }
+    }
+    ops.extend(current);
+    ops
+}
+
+fn push_hunk_line(hunks: &mut Vec<PatchHunk>, line: &str) {
+    if line.starts_with("@@") {
+        hunks.push(PatchHunk::default());
+        return;
+    }
+    if line.starts_with("*** ") {
+        return;
+    }
+    if hunks.is_empty() {
+        hunks.push(PatchHunk::default());
+    }
+    let Some(hunk) = hunks.last_mut() else {
+        return;
+    };
+    match line.chars().next() {
+        Some('+') => hunk.new_lines.push(line[1..].to_string()),
+        Some('-') => hunk.old_lines.push(line[1..].to_string()),
+        Some(' ') => {
+            hunk.old_lines.push(line[1..].to_string());
+            hunk.new_lines.push(line[1..].to_string());
+        }
+        _ => {
+            hunk.old_lines.push(line.to_string());
+            hunk.new_lines.push(line.to_string());
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::AdapterError;
+use crate::artifacts::refresh_artifacts;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use stead_session_model::{EventPayload, SteadSession};
+
+const BUILTIN_RULES: &[(&str, &str)] = &[
+    (
+        "private_key",
+        r"(?s)-----BEGIN [A-Z ]*PRIVATE KEY-----.*?-----END [A-Z ]*PRIVATE KEY-----",
+    ),
+    ("aws_access_key_id", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    (
+        "github_token",
+        r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})",
+    ),
+    ("anthropic_api_key", r"\bsk-ant-[A-Za-z0-9_-]{20,}"),
+    ("openai_api_key", r"\bsk-(?:proj-)?[A-Za-z0-9_-]{20,}"),
+    ("slack_token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}"),
+    (
+        "jwt",
+        r"\beyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}",
+    ),
+    (
+        "bearer_token",
+        r"(?i)\bbearer\s+(?P<secret>[A-Za-z0-9._~+/-]{20,}=*)",
+    ),
+    (
+        "secret_assignment",
+        r#"\b[A-Z0-9_]*(?:SECRET|TOKEN|PASSWORD|PASSWD|API_KEY|APIKEY|ACCESS_KEY)[A-Z0-9_]*\s*[=:]\s*["']?(?P<secret>[^\s"'\[][^\s"']{7,})"#,
+    ),
+    (
+        "email",
+        r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b",
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    #[serde(default = "default_builtin")]
+    pub builtin: bool,
+    #[serde(default)]
+    pub rules: Vec<RedactionRuleConfig>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            rules: Vec::new(),
+        }
+    }
+}
+
+fn default_builtin() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionRuleConfig {
+    pub name: String,
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Redaction {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_uid: Option<String>,
+    pub field: String,
+    pub rule: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    rules: Vec<(String, Regex)>,
+}
+
+impl Redactor {
+    pub fn builtin() -> Self {
+        Self::from_config(&RedactionConfig::default()).expect("built-in redaction rules compile")
+    }
+
+    pub fn from_config(config: &RedactionConfig) -> Result<Self, AdapterError> {
+        let builtin = BUILTIN_RULES
+            .iter()
+            .filter(|_| config.builtin)
+            .map(|(name, pattern)| (name.to_string(), pattern.to_string()));
+        let custom = config
+            .rules
+            .iter()
+            .map(|rule| (rule.name.clone(), rule.pattern.clone()));
+        let rules = builtin
+            .chain(custom)
+            .map(|(name, pattern)| {
+                let regex = Regex::new(&pattern).map_err(|err| {
+                    AdapterError::InvalidFormat(format!("invalid redaction rule `{name}`: {err}"))
+                })?;
+                Ok((name, regex))
+            })
+            .collect::<Result<_, AdapterError>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn redact_text(&self, text: &str) -> (String, BTreeMap<String, usize>) {
+        let mut hits = BTreeMap::new();
+        let mut out = text.to_string();
+        for (name, regex) in &self.rules {
+            let marker = format!("[REDACTED:{name}]");
+            let mut count = 0;
+            let replaced = regex.replace_all(&out, |caps: &Captures| {
+                count += 1;
+                let whole = caps.get(0).expect("match has group 0");
+                match caps.name("secret") {
+                    Some(secret) => format!(
+                        "{}{marker}{}",
+                        &whole.as_str()[..secret.start() - whole.start()],
+                        &whole.as_str()[secret.end() - whole.start()..]
+                    ),
+                    None => marker.clone(),
+                }
+            });
+            if count > 0 {
+                out = replaced.into_owned();
+                *hits.entry(name.clone()).or_default() += count;
+            }
+        }
+        (out, hits)
+    }
+
+    /// Masks matches in the session title and tags, event payloads and raw vendor payloads,
+    /// recording each masked field in `extensions.redactions`.
+    pub fn redact_session(&self, session: &mut SteadSession) -> Vec<Redaction> {
+        let mut redactions = Vec::new();
+        if let Some(title) = &mut session.metadata.title {
+            let (redacted, hits) = self.redact_text(title);
+            if !hits.is_empty() {
+                *title = redacted;
+                push_redactions(&mut redactions, None, "metadata.title", hits);
+            }
+        }
+        let mut tag_hits = BTreeMap::new();
+        for tag in &mut session.metadata.tags {
+            let (redacted, hits) = self.redact_text(tag);
+            if !hits.is_empty() {
+                *tag = redacted;
+                merge_hits(&mut tag_hits, hits);
+            }
+        }
+        push_redactions(&mut redactions, None, "metadata.tags", tag_hits);
+        for event in &mut session.events {
+            let mut payload = serde_json::to_value(&event.payload).unwrap_or(Value::Null);
+            let hits = self.redact_value(&mut payload);
+            if !hits.is_empty()
+                && let Ok(redacted) = serde_json::from_value::<EventPayload>(payload)
+            {
+                event.payload = redacted;
+                push_redactions(&mut redactions, Some(&event.event_uid), "payload", hits);
+            }
+            let hits = self.redact_value(&mut event.raw_vendor_payload);
+            push_redactions(
+                &mut redactions,
+                Some(&event.event_uid),
+                "raw_vendor_payload",
+                hits,
+            );
+        }
+        let hits = self.redact_value(&mut session.raw_vendor_payload);
+        push_redactions(&mut redactions, None, "raw_vendor_payload", hits);
+
+        if !redactions.is_empty() {
+            refresh_artifacts(session);
+            let recorded = session
+                .extensions
+                .entry("redactions".to_string())
+                .or_insert_with(|| Value::Array(Vec::new()));
+            if !recorded.is_array() {
+                *recorded = Value::Array(Vec::new());
+            }
+            if let Some(recorded) = recorded.as_array_mut() {
+                recorded.extend(
+                    redactions
+                        .iter()
+                        .filter_map(|redaction| serde_json::to_value(redaction).ok()),
+                );
+            }
+        }
+        redactions
+    }
+
+    fn redact_value(&self, value: &mut Value) -> BTreeMap<String, usize> {
+        let mut hits = BTreeMap::new();
+        match value {
+            Value::String(text) => {
+                let (redacted, found) = self.redact_text(text);
+                if !found.is_empty() {
+                    *text = redacted;
+                    hits = found;
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    merge_hits(&mut hits, self.redact_value(item));
+                }
+            }
+            Value::Object(map) => {
+                for item in map.values_mut() {
+                    merge_hits(&mut hits, self.redact_value(item));
+                }
+            }
+            _ => {}
+        }
+        hits
+    }
+}
+
+fn merge_hits(into: &mut BTreeMap<String, usize>, from: BTreeMap<String, usize>) {
+    for (rule, count) in from {
+        *into.entry(rule).or_default() += count;
+    }
+}
+
+fn push_redactions(
+    out: &mut Vec<Redaction>,
+    event_uid: Option<&str>,
+    field: &str,
+    hits: BTreeMap<String, usize>,
+) {
+    out.extend(hits.into_iter().map(|(rule, count)| Redaction {
+        event_uid: event_uid.map(str::to_string),
+        field: field.to_string(),
+        rule,
+        count,
+    }));
+}
@@ -0,0 +1,141 @@
+use crate::AdapterError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+use stead_session_model::{EventPayload, SteadSession};
+
+/// A `from=to` path prefix mapping. Only whole path components match, so `/home/a/repo` does
+/// not rewrite `/home/a/repo2`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootMapping {
+    pub from: String,
+    pub to: String,
+}
+
+impl RootMapping {
+    pub fn new(from: &str, to: &str) -> Self {
+        Self {
+            from: trim_root(from),
+            to: trim_root(to),
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self {
+            from: self.to.clone(),
+            to: self.from.clone(),
+        }
+    }
+
+    pub fn remap_text(&self, text: &str) -> Option<String> {
+        if self.from.is_empty() || self.from == self.to {
+            return None;
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for (idx, _) in text.match_indices(&self.from) {
+            if idx < last {
+                continue;
+            }
+            let end = idx + self.from.len();
+            let before = text[..idx].chars().next_back();
+            if before.is_some_and(|c| is_path_char(c) || c == '/') || !is_boundary(&text[end..]) {
+                continue;
+            }
+            out.push_str(&text[last..idx]);
+            out.push_str(&self.to);
+            last = end;
+        }
+        if last == 0 {
+            return None;
+        }
+        out.push_str(&text[last..]);
+        Some(out)
+    }
+}
+
+impl FromStr for RootMapping {
+    type Err = AdapterError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (from, to) = value
+            .split_once('=')
+            .filter(|(from, to)| !from.is_empty() && !to.is_empty())
+            .ok_or_else(|| {
+                AdapterError::InvalidFormat(format!(
+                    "invalid root mapping `{value}`, expected FROM=TO"
+                ))
+            })?;
+        Ok(Self::new(from, to))
+    }
+}
+
+/// Rewrites mapped path prefixes in `metadata.project_root`, event payloads (text, tool inputs
+/// and outputs), raw vendor lines and artifact paths. Longer prefixes are applied first. Returns
+/// the number of rewritten strings.
+pub fn remap_session(session: &mut SteadSession, mappings: &[RootMapping]) -> usize {
+    let mut mappings: Vec<&RootMapping> = mappings.iter().collect();
+    mappings.sort_by_key(|mapping| std::cmp::Reverse(mapping.from.len()));
+    let remap = |text: &mut String| {
+        let mut changed = false;
+        for mapping in &mappings {
+            if let Some(rewritten) = mapping.remap_text(text) {
+                *text = rewritten;
+                changed = true;
+            }
+        }
+        changed
+    };
+
+    let mut rewritten = usize::from(remap(&mut session.metadata.project_root));
+    for event in &mut session.events {
+        let mut payload = serde_json::to_value(&event.payload).unwrap_or(Value::Null);
+        let hits = remap_value(&mut payload, &remap);
+        if hits > 0
+            && let Ok(remapped) = serde_json::from_value::<EventPayload>(payload)
+        {
+            event.payload = remapped;
+            rewritten += hits;
+        }
+        rewritten += remap_value(&mut event.raw_vendor_payload, &remap);
+    }
+    rewritten += remap_value(&mut session.raw_vendor_payload, &remap);
+    for artifact in &mut session.artifacts {
+        if let Some(path) = artifact.path.as_mut() {
+            rewritten += usize::from(remap(path));
+        }
+    }
+    rewritten
+}
+
+fn remap_value(value: &mut Value, remap: &impl Fn(&mut String) -> bool) -> usize {
+    match value {
+        Value::String(text) => usize::from(remap(text)),
+        Value::Array(items) => items.iter_mut().map(|item| remap_value(item, remap)).sum(),
+        Value::Object(map) => map.values_mut().map(|item| remap_value(item, remap)).sum(),
+        _ => 0,
+    }
+}
+
+fn trim_root(root: &str) -> String {
+    let trimmed = root.trim_end_matches('/');
+    if trimmed.is_empty() {
+        root.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// A prefix ends at a separator, at the end of the text, or before sentence punctuation.
+fn is_boundary(rest: &str) -> bool {
+    let mut chars = rest.chars();
+    match chars.next() {
+        None => true,
+        Some('.') => chars.next().is_none_or(|c| !is_path_char(c) && c != '/'),
+        Some(c) => !is_path_char(c),
+    }
+}
@@ -0,0 +1,290 @@
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ShellWriteKind {
+    Write,
+    Create,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ShellWrite {
+    pub path: String,
+    pub kind: ShellWriteKind,
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Op(String),
+}
+
+#[derive(Debug, Default)]
+struct SimpleCommand {
+    words: Vec<String>,
+    redirects: Vec<(String, String)>,
+    heredoc: Option<String>,
+}
+
+pub(crate) fn shell_file_writes(script: &str) -> Vec<ShellWrite> {
+    let lines: Vec<&str> = script.lines().collect();
+    let mut writes = Vec::new();
+    let mut cwd: Option<String> = None;
+    let mut index = 0;
+
+    while index < lines.len() {
+        let mut logical = lines[index].to_string();
+        while logical.ends_with('\\') && index + 1 < lines.len() {
+            logical.pop();
+            index += 1;
+            logical.push_str(lines[index]);
+        }
+        index += 1;
+
+        let mut commands = split_commands(tokenize(&logical));
+        for command in commands.iter_mut() {
+            let Some(delimiter) = command
+                .redirects
+                .iter()
+                .find(|(op, _)| op == "<<" || op == "<<-")
+                .map(|(_, target)| target.clone())
+            else {
+                continue;
+            };
+            let mut body = String::new();
+            while index < lines.len() {
+                let line = lines[index];
+                index += 1;
+                if line.trim() == delimiter {
+                    break;
+                }
+                body.push_str(line);
+                body.push('\n');
+            }
+            command.heredoc = Some(body);
+        }
+
+        for command in commands {
+            collect_command_writes(&command, &mut cwd, &mut writes);
+        }
+    }
+    writes
+}
+
+fn collect_command_writes(
+    command: &SimpleCommand,
+    cwd: &mut Option<String>,
+    writes: &mut Vec<ShellWrite>,
+) {
+    let program = command
+        .words
+        .first()
+        .map(|word| {
+            Path::new(word)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or(word)
+                .to_string()
+        })
+        .unwrap_or_default();
+    let args: Vec<&str> = command
+        .words
+        .iter()
+        .skip(1)
+        .map(String::as_str)
+        .filter(|arg| !arg.starts_with('-'))
+        .collect();
+    let mut push = |path: &str, kind: ShellWriteKind, content: Option<String>| {
+        if !is_literal_path(path) {
+            return;
+        }
+        writes.push(ShellWrite {
+            path: join_cwd(cwd.as_deref(), path),
+            kind,
+            content,
+        });
+    };
+
+    for (op, target) in &command.redirects {
+        if !matches!(op.as_str(), ">" | ">>" | ">|" | "&>" | "&>>") || target == "/dev/null" {
+            continue;
+        }
+        let content = if op == ">" && program == "cat" && args.is_empty() {
+            command.heredoc.clone()
+        } else {
+            None
+        };
+        push(target, ShellWriteKind::Write, content);
+    }
+
+    match program.as_str() {
+        "cd" => {
+            if let Some(dir) = args.first()
+                && is_literal_path(dir)
+            {
+                *cwd = Some(join_cwd(cwd.as_deref(), dir));
+            }
+        }
+        "tee" => {
+            let append = command.words.iter().any(|word| word == "-a");
+            for arg in &args {
+                let content = if append {
+                    None
+                } else {
+                    command.heredoc.clone()
+                };
+                push(arg, ShellWriteKind::Write, content);
+            }
+        }
+        "rm" | "unlink" => {
+            for arg in &args {
+                push(arg, ShellWriteKind::Delete, None);
+            }
+        }
+        "touch" => {
+            for arg in &args {
+                push(arg, ShellWriteKind::Create, None);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_literal_path(path: &str) -> bool {
+    !path.is_empty() && !path.contains(['$', '*', '?', '`', '{', '~'])
+}
+
+fn join_cwd(cwd: Option<&str>, path: &str) -> String {
+    match cwd {
+        Some(cwd) if !Path::new(path).is_absolute() => {
+            Path::new(cwd).join(path).display().to_string()
+        }
+        _ => path.to_string(),
+    }
+}
+
+fn split_commands(tokens: Vec<Token>) -> Vec<SimpleCommand> {
+    let mut commands = Vec::new();
+    let mut current = SimpleCommand::default();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Word(word) => current.words.push(word),
+            Token::Op(op) if matches!(op.as_str(), ";" | "&&" | "||" | "|" | "&") => {
+                commands.push(std::mem::take(&mut current));
+            }
+            Token::Op(op) => {
+                if let Some(Token::Word(target)) = tokens.peek().cloned() {
+                    tokens.next();
+                    if !op.ends_with('&') {
+                        current.redirects.push((op, target));
+                    }
+                }
+            }
+        }
+    }
+    commands.push(current);
+    commands
+        .into_iter()
+        .filter(|command| !command.words.is_empty() || !command.redirects.is_empty())
+        .collect()
+}
+
+fn tokenize(line: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars().peekable();
+
+    let flush = |tokens: &mut Vec<Token>, word: &mut String, in_word: &mut bool| {
+        if *in_word {
+            tokens.push(Token::Word(std::mem::take(word)));
+            *in_word = false;
+        }
+    };
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '#' if !in_word => break,
+            '\'' => {
+                in_word = true;
+                for next in chars.by_ref() {
+                    if next == '\'' {
+                        break;
+                    }
+                    word.push(next);
+                }
+            }
+            '"' => {
+                in_word = true;
+                while let Some(next) = chars.next() {
+                    match next {
+                        '"' => break,
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                word.push(escaped);
+                            }
+                        }
+                        other => word.push(other),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                if let Some(escaped) = chars.next() {
+                    word.push(escaped);
+                }
+            }
+            ' ' | '\t' => flush(&mut tokens, &mut word, &mut in_word),
+            ';' => {
+                flush(&mut tokens, &mut word, &mut in_word);
+                tokens.push(Token::Op(";".to_string()));
+            }
+            '&' | '|' => {
+                if ch == '&' && chars.peek() == Some(&'>') {
+                    flush(&mut tokens, &mut word, &mut in_word);
+                    chars.next();
+                    let op = if chars.peek() == Some(&'>') {
+                        chars.next();
+                        "&>>"
+                    } else {
+                        "&>"
+                    };
+                    tokens.push(Token::Op(op.to_string()));
+                    continue;
+                }
+                flush(&mut tokens, &mut word, &mut in_word);
+                if chars.peek() == Some(&ch) {
+                    chars.next();
+                    tokens.push(Token::Op(format!("{ch}{ch}")));
+                } else {
+                    tokens.push(Token::Op(ch.to_string()));
+                }
+            }
+            '>' | '<' => {
+                if in_word && word.chars().all(|c| c.is_ascii_digit()) {
+                    word.clear();
+                    in_word = false;
+                }
+                flush(&mut tokens, &mut word, &mut in_word);
+                let mut op = ch.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next == ch || (ch == '>' && (next == '|' || next == '&')) || next == '-' {
+                        op.push(next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Op(op));
+            }
+            other => {
+                in_word = true;
+                word.push(other);
+            }
+        }
+    }
+    flush(&mut tokens, &mut word, &mut in_word);
+    tokens
+}
@@ -0,0 +1,143 @@
+use crate::artifacts::ArtifactKind;
+use crate::changes::{FileChange, FileChanges};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeStatus {
+    Present,
+    Modified,
+    Reverted,
+    Superseded,
+    Unknown,
+}
+
+impl ChangeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Present => "present",
+            Self::Modified => "modified",
+            Self::Reverted => "reverted",
+            Self::Superseded => "superseded",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+pub fn change_status(
+    file: &FileChanges,
+    change: &FileChange,
+    current: Option<&str>,
+) -> ChangeStatus {
+    if ends_deleted(file) {
+        return if current.is_none() {
+            ChangeStatus::Present
+        } else {
+            ChangeStatus::Reverted
+        };
+    }
+    if let Some(final_content) = file.final_content.as_deref()
+        && line_status(change, final_content) == Some(ChangeStatus::Reverted)
+    {
+        return ChangeStatus::Superseded;
+    }
+    match current {
+        None => ChangeStatus::Reverted,
+        Some(current) => line_status(change, current).unwrap_or(ChangeStatus::Unknown),
+    }
+}
+
+pub fn file_status(file: &FileChanges, current: Option<&str>) -> ChangeStatus {
+    if ends_deleted(file) {
+        return if current.is_none() {
+            ChangeStatus::Present
+        } else {
+            ChangeStatus::Reverted
+        };
+    }
+    let Some(current) = current else {
+        return ChangeStatus::Reverted;
+    };
+    if file.final_content.as_deref() == Some(current) {
+        return ChangeStatus::Present;
+    }
+    let statuses: Vec<ChangeStatus> = file
+        .changes
+        .iter()
+        .map(|change| change_status(file, change, Some(current)))
+        .filter(|status| *status != ChangeStatus::Superseded)
+        .collect();
+    if statuses.is_empty() || statuses.contains(&ChangeStatus::Unknown) {
+        return ChangeStatus::Unknown;
+    }
+    if statuses
+        .iter()
+        .all(|status| *status == ChangeStatus::Reverted)
+    {
+        ChangeStatus::Reverted
+    } else if file.final_content.is_none()
+        && statuses
+            .iter()
+            .all(|status| *status == ChangeStatus::Present)
+    {
+        ChangeStatus::Present
+    } else {
+        ChangeStatus::Modified
+    }
+}
+
+pub fn commit_search_text(change: &FileChange) -> Option<String> {
+    let (added, removed) = significant_lines(change);
+    added
+        .into_iter()
+        .chain(removed)
+        .max_by_key(|line| line.len())
+        .map(str::to_string)
+}
+
+fn ends_deleted(file: &FileChanges) -> bool {
+    file.changes
+        .last()
+        .is_some_and(|change| change.kind == ArtifactKind::Deleted.as_str())
+}
+
+fn line_status(change: &FileChange, content: &str) -> Option<ChangeStatus> {
+    let (added, removed) = significant_lines(change);
+    let lines: HashSet<&str> = content.lines().map(str::trim).collect();
+    let (expected, found) = if added.is_empty() {
+        let remaining = removed.iter().filter(|line| lines.contains(*line)).count();
+        (removed.len(), removed.len() - remaining)
+    } else {
+        let found = added.iter().filter(|line| lines.contains(*line)).count();
+        (added.len(), found)
+    };
+    match (expected, found) {
+        (0, _) => None,
+        (expected, found) if found == expected => Some(ChangeStatus::Present),
+        (_, 0) => Some(ChangeStatus::Reverted),
+        _ => Some(ChangeStatus::Modified),
+    }
+}
+
+fn significant_lines(change: &FileChange) -> (Vec<&str>, Vec<&str>) {
+    let Some(diff) = change.diff.as_deref() else {
+        return (Vec::new(), Vec::new());
+    };
+    let significant = |line: &str| line.chars().any(char::is_alphanumeric);
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    for line in diff.lines() {
+        if let Some(text) = line.strip_prefix('+').map(str::trim)
+            && significant(text)
+        {
+            added.push(text);
+        } else if let Some(text) = line.strip_prefix('-').map(str::trim)
+            && significant(text)
+        {
+            removed.push(text);
+        }
+    }
+    removed.retain(|line| !added.contains(line));
+    (added, removed)
+}
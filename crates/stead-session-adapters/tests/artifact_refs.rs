@@ -0,0 +1,162 @@
+use stead_session_adapters::artifacts::attach_artifacts;
+use stead_session_adapters::claude::ClaudeAdapter;
+use stead_session_adapters::codex::CodexAdapter;
+use stead_session_adapters::sha256_hex;
+use stead_session_model::{EventKind, EventPayload, SessionArtifactRef, SteadEvent};
+use tempfile::TempDir;
+
+fn artifact_for<'a>(artifacts: &'a [SessionArtifactRef], path: &str) -> &'a SessionArtifactRef {
+    artifacts
+        .iter()
+        .find(|artifact| artifact.path.as_deref() == Some(path))
+        .unwrap_or_else(|| panic!("missing artifact for {path}: {artifacts:#?}"))
+}
+
+#[test]
+fn claude_file_tools_produce_artifact_refs() {
+    let temp = TempDir::new().unwrap();
+    let project_dir = temp.path().join("projects").join("-Users-test-repo");
+    std::fs::create_dir_all(&project_dir).unwrap();
+    std::fs::write(
+        project_dir.join("artifacts.jsonl"),
+        concat!(
+            "{\"type\":\"user\",\"timestamp\":\"2026-02-17T00:00:00Z\",\"sessionId\":\"claude-artifacts\",\"cwd\":\"/Users/test/repo\",\"uuid\":\"u1\",\"message\":{\"role\":\"user\",\"content\":\"write some files\"}}\n",
+            "{\"type\":\"assistant\",\"timestamp\":\"2026-02-17T00:00:01Z\",\"sessionId\":\"claude-artifacts\",\"cwd\":\"/Users/test/repo\",\"uuid\":\"a1\",\"message\":{\"role\":\"assistant\",\"content\":[{\"type\":\"tool_use\",\"id\":\"toolu_w\",\"name\":\"Write\",\"input\":{\"file_path\":\"/Users/test/repo/src/new.rs\",\"content\":\"fn main() {}\\n\"}}]}}\n",
+            "{\"type\":\"user\",\"timestamp\":\"2026-02-17T00:00:02Z\",\"sessionId\":\"claude-artifacts\",\"cwd\":\"/Users/test/repo\",\"uuid\":\"u2\",\"message\":{\"role\":\"user\",\"content\":[{\"type\":\"tool_result\",\"tool_use_id\":\"toolu_w\",\"content\":\"File created successfully at: /Users/test/repo/src/new.rs\",\"is_error\":false}]}}\n",
+            "{\"type\":\"assistant\",\"timestamp\":\"2026-02-17T00:00:03Z\",\"sessionId\":\"claude-artifacts\",\"cwd\":\"/Users/test/repo\",\"uuid\":\"a2\",\"message\":{\"role\":\"assistant\",\"content\":[{\"type\":\"tool_use\",\"id\":\"toolu_e\",\"name\":\"Edit\",\"input\":{\"file_path\":\"/Users/test/repo/src/lib.rs\",\"old_string\":\"a\",\"new_string\":\"b\"}},{\"type\":\"tool_use\",\"id\":\"toolu_r\",\"name\":\"Read\",\"input\":{\"file_path\":\"README.md\"}}]}}\n",
+            "{\"type\":\"assistant\",\"timestamp\":\"2026-02-17T00:00:04Z\",\"sessionId\":\"claude-artifacts\",\"cwd\":\"/Users/test/repo\",\"uuid\":\"a3\",\"message\":{\"role\":\"assistant\",\"content\":[{\"type\":\"tool_use\",\"id\":\"toolu_f\",\"name\":\"Write\",\"input\":{\"file_path\":\"/etc/denied\",\"content\":\"x\"}}]}}\n",
+            "{\"type\":\"user\",\"timestamp\":\"2026-02-17T00:00:05Z\",\"sessionId\":\"claude-artifacts\",\"cwd\":\"/Users/test/repo\",\"uuid\":\"u3\",\"message\":{\"role\":\"user\",\"content\":[{\"type\":\"tool_result\",\"tool_use_id\":\"toolu_f\",\"content\":\"permission denied\",\"is_error\":true}]}}\n"
+        ),
+    )
+    .unwrap();
+
+    let adapter = ClaudeAdapter::from_base_dir(temp.path());
+    let session = adapter
+        .import_session("claude-artifacts")
+        .expect("import session");
+
+    assert_eq!(session.artifacts.len(), 3);
+    let written = artifact_for(&session.artifacts, "/Users/test/repo/src/new.rs");
+    assert_eq!(written.kind, "created");
+    assert_eq!(written.source_event_uid, "toolu_w");
+    assert_eq!(
+        written.sha256.as_deref(),
+        Some(sha256_hex("fn main() {}\n").as_str())
+    );
+
+    let edited = artifact_for(&session.artifacts, "/Users/test/repo/src/lib.rs");
+    assert_eq!(edited.kind, "modified");
+    assert!(edited.sha256.is_none());
+
+    let read = artifact_for(&session.artifacts, "/Users/test/repo/README.md");
+    assert_eq!(read.kind, "read");
+    assert!(
+        !session
+            .artifacts
+            .iter()
+            .any(|artifact| artifact.path.as_deref() == Some("/etc/denied"))
+    );
+}
+
+#[test]
+fn codex_apply_patch_and_shell_writes_produce_artifact_refs() {
+    let temp = TempDir::new().unwrap();
+    let path = temp
+        .path()
+        .join("rollout-2026-02-18T00-00-00-codex-artifacts.jsonl");
+    let patch = "*** Begin Patch\n*** Add File: docs/notes.md\n+hello\n+world\n*** Update File: src/lib.rs\n@@\n-a\n+b\n*** Delete File: old.txt\n*** End Patch\n";
+    let lines = [
+        serde_json::json!({"timestamp":"2026-02-18T00:00:00Z","type":"session_meta","payload":{"id":"codex-artifacts","cwd":"/repo"}}),
+        serde_json::json!({"timestamp":"2026-02-18T00:00:01Z","type":"response_item","payload":{"type":"function_call","name":"apply_patch","call_id":"call_patch","arguments":serde_json::json!({"input": patch}).to_string()}}),
+        serde_json::json!({"timestamp":"2026-02-18T00:00:02Z","type":"response_item","payload":{"type":"function_call","name":"shell","call_id":"call_shell","arguments":serde_json::json!({"command":["bash","-lc","cd out && cat > report.txt <<'EOF'\nreport body\nEOF\nrm -f stale.log"],"workdir":"/repo"}).to_string()}}),
+        serde_json::json!({"timestamp":"2026-02-18T00:00:03Z","type":"response_item","payload":{"type":"function_call","name":"shell","call_id":"call_ls","arguments":serde_json::json!({"command":["ls","-la"]}).to_string()}}),
+    ];
+    std::fs::write(
+        &path,
+        lines
+            .iter()
+            .map(|line| format!("{line}\n"))
+            .collect::<String>(),
+    )
+    .unwrap();
+
+    let adapter = CodexAdapter::from_base_dir(temp.path());
+    let session = adapter.import_from_file(&path).expect("import");
+
+    assert_eq!(session.artifacts.len(), 5);
+    let added = artifact_for(&session.artifacts, "/repo/docs/notes.md");
+    assert_eq!(added.kind, "created");
+    assert_eq!(added.source_event_uid, "call_patch");
+    assert_eq!(
+        added.sha256.as_deref(),
+        Some(sha256_hex("hello\nworld\n").as_str())
+    );
+    assert_eq!(
+        artifact_for(&session.artifacts, "/repo/src/lib.rs").kind,
+        "modified"
+    );
+    assert_eq!(
+        artifact_for(&session.artifacts, "/repo/old.txt").kind,
+        "deleted"
+    );
+
+    let report = artifact_for(&session.artifacts, "/repo/out/report.txt");
+    assert_eq!(report.kind, "modified");
+    assert_eq!(report.source_event_uid, "call_shell");
+    assert_eq!(
+        report.sha256.as_deref(),
+        Some(sha256_hex("report body\n").as_str())
+    );
+    assert_eq!(
+        artifact_for(&session.artifacts, "/repo/out/stale.log").kind,
+        "deleted"
+    );
+
+    // Importers only derive `artifacts`. Attaching mirrors each one as an `artifact_ref` event
+    // right after its tool call; exports leave those out and re-attaching derives them again.
+    assert!(
+        session
+            .events
+            .iter()
+            .all(|event| event.kind != EventKind::ArtifactRef)
+    );
+    let mut session = session;
+    attach_artifacts(&mut session);
+    let kinds: Vec<(EventKind, &str)> = session
+        .events
+        .iter()
+        .map(|event| (event.kind, event.event_uid.as_str()))
+        .collect();
+    assert_eq!(
+        kinds[..5],
+        [
+            (EventKind::ToolCall, "call_patch"),
+            (EventKind::ArtifactRef, "artifact-call_patch-0"),
+            (EventKind::ArtifactRef, "artifact-call_patch-1"),
+            (EventKind::ArtifactRef, "artifact-call_patch-2"),
+            (EventKind::ToolCall, "call_shell"),
+        ]
+    );
+    let EventPayload::Json { value } = &session.events[1].payload else {
+        panic!("expected artifact ref payload");
+    };
+    assert_eq!(value["path"], "/repo/docs/notes.md");
+    assert_eq!(value["source_event_uid"], "call_patch");
+
+    let out = temp.path().join("exported.jsonl");
+    let exported = adapter.export_session(&session, &out).unwrap();
+    assert_eq!(exported.events_exported, session.events.len() - 5);
+    let mut reimported = adapter.import_from_file(&out).unwrap();
+    attach_artifacts(&mut reimported);
+    let artifact_events = |events: &[SteadEvent]| {
+        events
+            .iter()
+            .filter(|event| event.kind == EventKind::ArtifactRef)
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        artifact_events(&reimported.events),
+        artifact_events(&session.events)
+    );
+}
@@ -0,0 +1,114 @@
+use stead_session_adapters::blobs::{BLOB_REF_KEY, BlobStore, referenced_blobs};
+use stead_session_adapters::codex::CodexAdapter;
+use stead_session_model::EventPayload;
+use tempfile::TempDir;
+
+mod support;
+
+fn import_with_large_output(temp: &TempDir) -> stead_session_model::SteadSession {
+    support::copy_codex_fixture_tree(temp);
+    let rollout = temp
+        .path()
+        .join("sessions/2026/02/17/rollout-2026-02-17T20-00-00-s-new.jsonl");
+    let mut raw = std::fs::read_to_string(&rollout).unwrap();
+    let line = serde_json::json!({
+        "timestamp": "2026-02-17T20:00:06Z",
+        "type": "response_item",
+        "payload": {
+            "type": "function_call_output",
+            "call_id": "call_1",
+            "output": "running 42 tests\n".repeat(400)
+        }
+    });
+    raw.push_str(&format!("{line}\n"));
+    std::fs::write(rollout, raw).unwrap();
+    CodexAdapter::from_base_dir(temp.path())
+        .import_session("s-new")
+        .unwrap()
+}
+
+fn blob_files(root: &std::path::Path) -> Vec<std::path::PathBuf> {
+    walkdir::WalkDir::new(root)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.path().to_path_buf())
+        .collect()
+}
+
+#[test]
+fn large_outputs_and_raw_lines_move_to_blobs_and_rehydrate() {
+    let temp = TempDir::new().unwrap();
+    let session = import_with_large_output(&temp);
+    let store = BlobStore::new(temp.path().join("blobs"), false);
+
+    let mut stored = session.clone();
+    let moved = store.externalize_session(&mut stored, 4096).unwrap();
+    assert_eq!(moved, 3);
+    let event = stored
+        .events
+        .iter()
+        .find(|event| event.line_number == 6)
+        .unwrap();
+    let EventPayload::ToolResult { output_text, .. } = &event.payload else {
+        panic!("expected tool result");
+    };
+    assert!(output_text.is_none());
+    assert!(event.extensions["blob_refs"]["output_text"]["sha256"].is_string());
+    assert!(event.raw_vendor_payload.get(BLOB_REF_KEY).is_some());
+    assert!(serde_json::to_string(&stored).unwrap().len() < 8192);
+
+    let referenced = referenced_blobs(&stored);
+    assert_eq!(referenced.len(), 2);
+    assert_eq!(blob_files(&store.root).len(), 2);
+    for sha256 in &referenced {
+        let path = store.find(sha256).unwrap();
+        assert_eq!(
+            path.parent()
+                .unwrap()
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            &sha256[..2]
+        );
+    }
+
+    let mut loaded = stored.clone();
+    assert_eq!(store.rehydrate_session(&mut loaded).unwrap(), 3);
+    assert_eq!(loaded, session);
+}
+
+#[test]
+fn compressed_blobs_are_shared_across_sessions() {
+    let temp = TempDir::new().unwrap();
+    let session = import_with_large_output(&temp);
+    let store = BlobStore::new(temp.path().join("blobs"), true);
+
+    let mut first = session.clone();
+    let mut second = session.clone();
+    second.session_uid = "stead:codex:copy".to_string();
+    store.externalize_session(&mut first, 4096).unwrap();
+    store.externalize_session(&mut second, 4096).unwrap();
+
+    let files = blob_files(&store.root);
+    assert_eq!(files.len(), 2);
+    assert!(
+        files
+            .iter()
+            .all(|path| path.extension().is_some_and(|ext| ext == "zst"))
+    );
+    let on_disk: u64 = files
+        .iter()
+        .map(|path| std::fs::metadata(path).unwrap().len())
+        .sum();
+    assert!(on_disk < 2048);
+
+    let plain = BlobStore::new(temp.path().join("blobs"), false);
+    plain.rehydrate_session(&mut second).unwrap();
+    assert_eq!(second.events, session.events);
+
+    std::fs::remove_file(&files[0]).unwrap();
+    let err = plain.rehydrate_session(&mut first).unwrap_err();
+    assert!(err.to_string().contains("missing blob"));
+}
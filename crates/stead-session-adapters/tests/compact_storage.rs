@@ -0,0 +1,115 @@
+use stead_session_adapters::claude::ClaudeAdapter;
+use stead_session_adapters::codex::CodexAdapter;
+use stead_session_adapters::compact::{
+    RAW_REF_KEY, compact_session, is_compact, rehydrate_session,
+};
+use stead_session_model::EventKind;
+use tempfile::TempDir;
+
+mod common;
+mod support;
+
+fn append_large_tool_output(temp: &TempDir) {
+    let rollout = temp
+        .path()
+        .join("sessions/2026/02/17/rollout-2026-02-17T20-00-00-s-new.jsonl");
+    let mut raw = std::fs::read_to_string(&rollout).unwrap();
+    let line = serde_json::json!({
+        "timestamp": "2026-02-17T20:00:06Z",
+        "type": "response_item",
+        "payload": {
+            "type": "function_call_output",
+            "call_id": "call_1",
+            "output": "test result: ok\n".repeat(200),
+            "vendor_only": {"exit_code": 0}
+        }
+    });
+    raw.push_str(&format!("{line}\n"));
+    std::fs::write(rollout, raw).unwrap();
+}
+
+#[test]
+fn compact_codex_session_exports_identically_while_native_file_exists() {
+    let temp = TempDir::new().unwrap();
+    support::copy_codex_fixture_tree(&temp);
+    append_large_tool_output(&temp);
+    let adapter = CodexAdapter::from_base_dir(temp.path());
+    let session = adapter.import_session("s-new").unwrap();
+
+    let mut compact = session.clone();
+    let report = compact_session(&mut compact);
+    assert!(report.compacted >= 2);
+    assert!(is_compact(&compact));
+    let large = compact
+        .events
+        .iter()
+        .find(|event| event.kind == EventKind::ToolResult && event.line_number == 6)
+        .unwrap();
+    assert_eq!(
+        large.raw_vendor_payload[RAW_REF_KEY]["line_number"],
+        serde_json::json!(6)
+    );
+    assert_eq!(
+        large.raw_vendor_payload["timestamp"],
+        "2026-02-17T20:00:06Z"
+    );
+    let full_len = serde_json::to_string(&session).unwrap().len();
+    let compact_len = serde_json::to_string(&compact).unwrap().len();
+    assert!(full_len - compact_len > 2 * "test result: ok\n".repeat(200).len());
+
+    let full_out = temp.path().join("full.jsonl");
+    let compact_out = temp.path().join("compact.jsonl");
+    adapter.export_session(&session, &full_out).unwrap();
+    adapter.export_session(&compact, &compact_out).unwrap();
+    let exported = std::fs::read_to_string(&compact_out).unwrap();
+    assert_eq!(std::fs::read_to_string(&full_out).unwrap(), exported);
+    assert!(exported.contains("vendor_only"));
+
+    let mut rehydrated = compact.clone();
+    let restored = rehydrate_session(&mut rehydrated);
+    assert_eq!(restored.restored, report.compacted);
+    assert_eq!(restored.missing, 0);
+    assert_eq!(rehydrated.raw_vendor_payload, session.raw_vendor_payload);
+    assert_eq!(rehydrated.events, session.events);
+
+    for file in &session.source.source_files {
+        std::fs::remove_file(file).unwrap();
+    }
+    let mut orphaned = compact.clone();
+    assert_eq!(rehydrate_session(&mut orphaned).missing, report.compacted);
+    adapter.export_session(&compact, &compact_out).unwrap();
+    let exported = std::fs::read_to_string(&compact_out).unwrap();
+    assert!(exported.contains("test result: ok"));
+    assert!(!exported.contains("vendor_only"));
+}
+
+#[test]
+fn compact_claude_session_round_trips_main_and_subagent_lines() {
+    let temp = TempDir::new().unwrap();
+    common::copy_claude_fixture_tree(&temp);
+    let adapter = ClaudeAdapter::from_base_dir(temp.path());
+    let session = adapter.import_session("claude-main").unwrap();
+    assert!(session.source.source_files.len() > 1);
+
+    let mut compact = session.clone();
+    let report = compact_session(&mut compact);
+    assert!(report.compacted > 0);
+    let sources: Vec<&str> = compact
+        .events
+        .iter()
+        .filter_map(|event| event.raw_vendor_payload[RAW_REF_KEY]["source_file"].as_str())
+        .collect();
+    assert!(sources.iter().any(|file| file.contains("subagents")));
+
+    let full_out = temp.path().join("full.jsonl");
+    let compact_out = temp.path().join("compact.jsonl");
+    adapter.export_session(&session, &full_out).unwrap();
+    adapter.export_session(&compact, &compact_out).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&full_out).unwrap(),
+        std::fs::read_to_string(&compact_out).unwrap()
+    );
+
+    rehydrate_session(&mut compact);
+    assert_eq!(compact, session);
+}
@@ -0,0 +1,212 @@
+use stead_session_adapters::changes::{render_git_patch, session_file_changes};
+use stead_session_adapters::claude::ClaudeAdapter;
+use stead_session_adapters::codex::CodexAdapter;
+use tempfile::TempDir;
+
+fn write_claude_session(temp: &TempDir, lines: &[serde_json::Value]) {
+    let project_dir = temp.path().join("projects").join("-repo");
+    std::fs::create_dir_all(&project_dir).unwrap();
+    std::fs::write(
+        project_dir.join("changes.jsonl"),
+        lines
+            .iter()
+            .map(|line| format!("{line}\n"))
+            .collect::<String>(),
+    )
+    .unwrap();
+}
+
+fn claude_tool_use(
+    ts: &str,
+    uuid: &str,
+    id: &str,
+    name: &str,
+    input: serde_json::Value,
+) -> serde_json::Value {
+    serde_json::json!({
+        "type": "assistant",
+        "timestamp": ts,
+        "sessionId": "claude-changes",
+        "cwd": "/repo",
+        "uuid": uuid,
+        "message": {"role": "assistant", "content": [{"type": "tool_use", "id": id, "name": name, "input": input}]}
+    })
+}
+
+fn claude_tool_result(ts: &str, uuid: &str, id: &str, content: &str) -> serde_json::Value {
+    serde_json::json!({
+        "type": "user",
+        "timestamp": ts,
+        "sessionId": "claude-changes",
+        "cwd": "/repo",
+        "uuid": uuid,
+        "message": {"role": "user", "content": [{"type": "tool_result", "tool_use_id": id, "content": content, "is_error": false}]}
+    })
+}
+
+#[test]
+fn claude_write_then_edit_reconstructs_exact_net_diff() {
+    let temp = TempDir::new().unwrap();
+    write_claude_session(
+        &temp,
+        &[
+            claude_tool_use(
+                "2026-02-17T00:00:01Z",
+                "a1",
+                "toolu_w",
+                "Write",
+                serde_json::json!({"file_path": "/repo/src/lib.rs", "content": "fn a() {}\nfn b() {}\n"}),
+            ),
+            claude_tool_result(
+                "2026-02-17T00:00:01Z",
+                "u1",
+                "toolu_w",
+                "File created successfully at: /repo/src/lib.rs",
+            ),
+            claude_tool_use(
+                "2026-02-17T00:00:02Z",
+                "a2",
+                "toolu_e",
+                "Edit",
+                serde_json::json!({"file_path": "/repo/src/lib.rs", "old_string": "fn b() {}", "new_string": "fn b() -> u8 { 1 }"}),
+            ),
+            claude_tool_use(
+                "2026-02-17T00:00:03Z",
+                "a3",
+                "toolu_x",
+                "Edit",
+                serde_json::json!({"file_path": "/repo/README.md", "old_string": "old title", "new_string": "new title"}),
+            ),
+        ],
+    );
+
+    let session = ClaudeAdapter::from_base_dir(temp.path())
+        .import_session("claude-changes")
+        .unwrap();
+    let files = session_file_changes(&session);
+
+    assert_eq!(files.len(), 2);
+    let lib = &files[0];
+    assert_eq!(lib.path, "/repo/src/lib.rs");
+    assert_eq!(lib.changes.len(), 2);
+    assert!(lib.changes.iter().all(|change| change.exact));
+    assert_eq!(lib.changes[0].kind, "created");
+    assert_eq!(lib.changes[1].source_event_uid, "toolu_e");
+    let net = lib.net_diff.as_deref().unwrap();
+    assert!(net.contains("+fn a() {}\n"));
+    assert!(net.contains("+fn b() -> u8 { 1 }\n"));
+    assert!(!net.contains("-fn b() {}"));
+
+    let readme = &files[1];
+    assert!(readme.net_diff.is_none());
+    assert!(!readme.changes[0].exact);
+    let diff = readme.changes[0].diff.as_deref().unwrap();
+    assert!(diff.contains("-old title\n"));
+    assert!(diff.contains("+new title\n"));
+
+    let patch = render_git_patch(&files, &session.metadata.project_root);
+    assert!(patch.contains("diff --git a/src/lib.rs b/src/lib.rs\nnew file mode 100644\n--- /dev/null\n+++ b/src/lib.rs\n"));
+    assert!(
+        patch.starts_with("# skipped modified README.md from toolu_x (prior content unknown)\n")
+    );
+    assert!(!patch.contains("diff --git a/README.md"));
+}
+
+#[test]
+fn claude_write_without_a_creation_result_is_not_a_new_file() {
+    let temp = TempDir::new().unwrap();
+    write_claude_session(
+        &temp,
+        &[
+            claude_tool_use(
+                "2026-02-17T00:00:01Z",
+                "a1",
+                "toolu_w",
+                "Write",
+                serde_json::json!({"file_path": "/repo/src/lib.rs", "content": "fn a() {}\n"}),
+            ),
+            claude_tool_use(
+                "2026-02-17T00:00:02Z",
+                "a2",
+                "toolu_u",
+                "Write",
+                serde_json::json!({"file_path": "/repo/src/main.rs", "content": "fn main() {}\n"}),
+            ),
+            claude_tool_result(
+                "2026-02-17T00:00:03Z",
+                "u1",
+                "toolu_u",
+                "The file /repo/src/main.rs has been updated.",
+            ),
+        ],
+    );
+
+    let session = ClaudeAdapter::from_base_dir(temp.path())
+        .import_session("claude-changes")
+        .unwrap();
+    let kinds: Vec<&str> = session
+        .artifacts
+        .iter()
+        .map(|artifact| artifact.kind.as_str())
+        .collect();
+    assert_eq!(kinds, ["modified", "modified"]);
+
+    let files = session_file_changes(&session);
+    assert!(files.iter().all(|file| {
+        file.changes[0].kind == "modified" && !file.changes[0].exact && file.net_diff.is_none()
+    }));
+    let patch = render_git_patch(&files, &session.metadata.project_root);
+    assert!(!patch.contains("new file mode"));
+    assert!(patch.contains("# skipped modified src/lib.rs from toolu_w (prior content unknown)\n"));
+}
+
+#[test]
+fn codex_apply_patch_updates_are_applied_to_known_content() {
+    let temp = TempDir::new().unwrap();
+    let path = temp
+        .path()
+        .join("rollout-2026-02-18T00-00-00-codex-changes.jsonl");
+    let add = "*** Begin Patch\n*** Add File: notes.md\n+one\n+two\n+three\n*** End Patch\n";
+    let update = "*** Begin Patch\n*** Update File: notes.md\n@@\n one\n-two\n+TWO\n three\n*** Delete File: gone.txt\n*** End Patch\n";
+    let lines = [
+        serde_json::json!({"timestamp":"2026-02-18T00:00:00Z","type":"session_meta","payload":{"id":"codex-changes","cwd":"/repo"}}),
+        serde_json::json!({"timestamp":"2026-02-18T00:00:01Z","type":"response_item","payload":{"type":"function_call","name":"apply_patch","call_id":"call_add","arguments":serde_json::json!({"input": add}).to_string()}}),
+        serde_json::json!({"timestamp":"2026-02-18T00:00:02Z","type":"response_item","payload":{"type":"function_call","name":"shell","call_id":"call_update","arguments":serde_json::json!({"command":["apply_patch", update]}).to_string()}}),
+    ];
+    std::fs::write(
+        &path,
+        lines
+            .iter()
+            .map(|line| format!("{line}\n"))
+            .collect::<String>(),
+    )
+    .unwrap();
+
+    let session = CodexAdapter::from_base_dir(temp.path())
+        .import_from_file(&path)
+        .unwrap();
+    let files = session_file_changes(&session);
+
+    assert_eq!(files.len(), 2);
+    let notes = &files[0];
+    assert_eq!(notes.path, "/repo/notes.md");
+    assert_eq!(notes.changes.len(), 2);
+    assert!(notes.changes[1].exact);
+    assert!(
+        notes.changes[1]
+            .diff
+            .as_deref()
+            .unwrap()
+            .contains("@@ -1,3 +1,3 @@\n one\n-two\n+TWO\n three\n")
+    );
+    assert_eq!(
+        notes.net_diff.as_deref(),
+        Some("@@ -0,0 +1,3 @@\n+one\n+TWO\n+three\n")
+    );
+
+    let gone = &files[1];
+    assert_eq!(gone.changes[0].kind, "deleted");
+    assert!(gone.changes[0].diff.is_none());
+    let patch = render_git_patch(&files, "/repo");
+    assert!(patch.starts_with("# skipped deleted gone.txt from call_update"));
+}
@@ -0,0 +1,104 @@
+use stead_session_adapters::claude::ClaudeAdapter;
+use stead_session_adapters::remap::{RootMapping, remap_session};
+use stead_session_model::EventPayload;
+use tempfile::TempDir;
+
+fn import_session(temp: &TempDir) -> stead_session_model::SteadSession {
+    let project_dir = temp.path().join("projects").join("-home-a-repo");
+    std::fs::create_dir_all(&project_dir).unwrap();
+    let lines = [
+        serde_json::json!({
+            "type": "user",
+            "timestamp": "2026-02-17T00:00:00Z",
+            "sessionId": "claude-paths",
+            "cwd": "/home/a/repo",
+            "uuid": "u1",
+            "message": {"role": "user", "content": "fix /home/a/repo/src/lib.rs, not /home/a/repo2/lib.rs"}
+        }),
+        serde_json::json!({
+            "type": "assistant",
+            "timestamp": "2026-02-17T00:00:01Z",
+            "sessionId": "claude-paths",
+            "cwd": "/home/a/repo",
+            "uuid": "a1",
+            "message": {"role": "assistant", "content": [{"type": "tool_use", "id": "toolu_1", "name": "Write", "input": {"file_path": "/home/a/repo/src/lib.rs", "content": "pub fn x() {}\n"}}]}
+        }),
+        serde_json::json!({
+            "type": "user",
+            "timestamp": "2026-02-17T00:00:02Z",
+            "sessionId": "claude-paths",
+            "cwd": "/home/a/repo",
+            "uuid": "u2",
+            "message": {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "toolu_1", "content": "wrote /home/a/repo/src/lib.rs.", "is_error": false}]}
+        }),
+    ];
+    std::fs::write(
+        project_dir.join("claude-paths.jsonl"),
+        lines
+            .iter()
+            .map(|line| format!("{line}\n"))
+            .collect::<String>(),
+    )
+    .unwrap();
+    ClaudeAdapter::from_base_dir(temp.path())
+        .import_session("claude-paths")
+        .unwrap()
+}
+
+#[test]
+fn mapping_rewrites_metadata_payloads_raw_lines_and_artifacts() {
+    let temp = TempDir::new().unwrap();
+    let original = import_session(&temp);
+    let mapping: RootMapping = "/home/a/repo/=/Users/b/repo".parse().unwrap();
+    assert_eq!(mapping, RootMapping::new("/home/a/repo", "/Users/b/repo"));
+
+    let mut session = original.clone();
+    assert!(remap_session(&mut session, std::slice::from_ref(&mapping)) > 0);
+    assert_eq!(session.metadata.project_root, "/Users/b/repo");
+    let EventPayload::Text { text } = &session.events[0].payload else {
+        panic!("expected text");
+    };
+    assert_eq!(
+        text,
+        "fix /Users/b/repo/src/lib.rs, not /home/a/repo2/lib.rs"
+    );
+    let EventPayload::ToolCall { input, .. } = &session.events[1].payload else {
+        panic!("expected tool call");
+    };
+    assert_eq!(input["file_path"], "/Users/b/repo/src/lib.rs");
+    let EventPayload::ToolResult { output_text, .. } = &session.events[2].payload else {
+        panic!("expected tool result");
+    };
+    assert_eq!(
+        output_text.as_deref(),
+        Some("wrote /Users/b/repo/src/lib.rs.")
+    );
+    assert_eq!(
+        session.artifacts[0].path.as_deref(),
+        Some("/Users/b/repo/src/lib.rs")
+    );
+
+    let out = temp.path().join("remapped.jsonl");
+    ClaudeAdapter::from_base_dir(temp.path())
+        .export_session(&session, &out)
+        .unwrap();
+    let exported = std::fs::read_to_string(&out).unwrap();
+    assert!(exported.contains("\"cwd\":\"/Users/b/repo\""));
+    assert!(!exported.contains("/home/a/repo/"));
+
+    remap_session(&mut session, &[mapping.inverse()]);
+    assert_eq!(session, original);
+}
+
+#[test]
+fn mapping_requires_both_sides_and_respects_component_boundaries() {
+    assert!("/home/a/repo".parse::<RootMapping>().is_err());
+    assert!("=/Users/b/repo".parse::<RootMapping>().is_err());
+
+    let mapping = RootMapping::new("/home/a", "/Users/b");
+    assert_eq!(
+        mapping.remap_text("cd /home/a && ls /home/a/x /home/ab /x/home/a"),
+        Some("cd /Users/b && ls /Users/b/x /home/ab /x/home/a".to_string())
+    );
+    assert_eq!(mapping.remap_text("/home/a.bak"), None);
+}
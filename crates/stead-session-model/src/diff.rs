@@ -0,0 +1,246 @@
+use crate::{EventKind, SteadEvent, SteadSession, event_fingerprint};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldDifference {
+    pub field: String,
+    pub left: Value,
+    pub right: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventSummary {
+    pub event_uid: String,
+    pub index: usize,
+    pub stream_id: String,
+    pub kind: EventKind,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventModification {
+    pub left_uid: String,
+    pub right_uid: String,
+    pub left_index: usize,
+    pub right_index: usize,
+    pub fields: Vec<FieldDifference>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventMove {
+    pub event_uid: String,
+    pub left_index: usize,
+    pub right_index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SessionDiff {
+    pub metadata: Vec<FieldDifference>,
+    pub added: Vec<EventSummary>,
+    pub removed: Vec<EventSummary>,
+    pub modified: Vec<EventModification>,
+    pub reordered: Vec<EventMove>,
+}
+
+impl SessionDiff {
+    pub fn is_empty(&self) -> bool {
+        self.metadata.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.reordered.is_empty()
+    }
+}
+
+pub fn diff_sessions(left: &SteadSession, right: &SteadSession) -> SessionDiff {
+    let pairs = align_events(&left.events, &right.events);
+    let mut matched_left = vec![false; left.events.len()];
+    let mut matched_right = vec![false; right.events.len()];
+    for (l, r) in &pairs {
+        matched_left[*l] = true;
+        matched_right[*r] = true;
+    }
+
+    let summary = |events: &[SteadEvent], index: usize| EventSummary {
+        event_uid: events[index].event_uid.clone(),
+        index,
+        stream_id: events[index].stream_id.clone(),
+        kind: events[index].kind,
+        timestamp: events[index].timestamp,
+    };
+    let modified = pairs
+        .iter()
+        .filter_map(|(l, r)| {
+            let fields = event_differences(&left.events[*l], &right.events[*r]);
+            (!fields.is_empty()).then(|| EventModification {
+                left_uid: left.events[*l].event_uid.clone(),
+                right_uid: right.events[*r].event_uid.clone(),
+                left_index: *l,
+                right_index: *r,
+                fields,
+            })
+        })
+        .collect();
+
+    SessionDiff {
+        metadata: metadata_differences(left, right),
+        added: (0..right.events.len())
+            .filter(|index| !matched_right[*index])
+            .map(|index| summary(&right.events, index))
+            .collect(),
+        removed: (0..left.events.len())
+            .filter(|index| !matched_left[*index])
+            .map(|index| summary(&left.events, index))
+            .collect(),
+        modified,
+        reordered: reordered(&pairs)
+            .into_iter()
+            .map(|(l, r)| EventMove {
+                event_uid: left.events[l].event_uid.clone(),
+                left_index: l,
+                right_index: r,
+            })
+            .collect(),
+    }
+}
+
+fn align_events(left: &[SteadEvent], right: &[SteadEvent]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    let mut left_used = vec![false; left.len()];
+    let mut right_used = vec![false; right.len()];
+    let passes: [fn(&SteadEvent) -> String; 3] = [
+        |event| format!("{}|{}", event.event_uid, event_fingerprint(event)),
+        |event| event.event_uid.clone(),
+        event_fingerprint,
+    ];
+    for key_of in passes {
+        let mut candidates: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, event) in right.iter().enumerate().rev() {
+            if !right_used[index] {
+                candidates.entry(key_of(event)).or_default().push(index);
+            }
+        }
+        for (index, event) in left.iter().enumerate() {
+            if left_used[index] {
+                continue;
+            }
+            if let Some(found) = candidates.get_mut(&key_of(event)).and_then(Vec::pop) {
+                left_used[index] = true;
+                right_used[found] = true;
+                pairs.push((index, found));
+            }
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+fn reordered(pairs: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    // Pairs are sorted by left index; the longest increasing run of right
+    // indices stays in place and everything else counts as moved.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; pairs.len()];
+    for (position, (_, right)) in pairs.iter().enumerate() {
+        let slot = tails.partition_point(|tail| pairs[*tail].1 < *right);
+        if slot > 0 {
+            previous[position] = Some(tails[slot - 1]);
+        }
+        if slot == tails.len() {
+            tails.push(position);
+        } else {
+            tails[slot] = position;
+        }
+    }
+    let mut in_place = vec![false; pairs.len()];
+    let mut cursor = tails.last().copied();
+    while let Some(position) = cursor {
+        in_place[position] = true;
+        cursor = previous[position];
+    }
+    pairs
+        .iter()
+        .enumerate()
+        .filter(|(position, _)| !in_place[*position])
+        .map(|(_, pair)| *pair)
+        .collect()
+}
+
+fn event_differences(left: &SteadEvent, right: &SteadEvent) -> Vec<FieldDifference> {
+    let fields = [
+        ("event_uid", json!(left.event_uid), json!(right.event_uid)),
+        ("stream_id", json!(left.stream_id), json!(right.stream_id)),
+        ("timestamp", json!(left.timestamp), json!(right.timestamp)),
+        ("kind", json!(left.kind), json!(right.kind)),
+        ("actor", json!(left.actor), json!(right.actor)),
+        ("payload", json!(left.payload), json!(right.payload)),
+    ];
+    differing(fields)
+}
+
+fn metadata_differences(left: &SteadSession, right: &SteadSession) -> Vec<FieldDifference> {
+    let fields = [
+        (
+            "session_uid",
+            json!(left.session_uid),
+            json!(right.session_uid),
+        ),
+        (
+            "shared_session_uid",
+            json!(left.shared_session_uid),
+            json!(right.shared_session_uid),
+        ),
+        (
+            "source.backend",
+            json!(left.source.backend),
+            json!(right.source.backend),
+        ),
+        (
+            "source.original_session_id",
+            json!(left.source.original_session_id),
+            json!(right.source.original_session_id),
+        ),
+        (
+            "metadata.title",
+            json!(left.metadata.title),
+            json!(right.metadata.title),
+        ),
+        (
+            "metadata.project_root",
+            json!(left.metadata.project_root),
+            json!(right.metadata.project_root),
+        ),
+        (
+            "metadata.created_at",
+            json!(left.metadata.created_at),
+            json!(right.metadata.created_at),
+        ),
+        (
+            "metadata.updated_at",
+            json!(left.metadata.updated_at),
+            json!(right.metadata.updated_at),
+        ),
+        ("lineage", json!(left.lineage), json!(right.lineage)),
+        ("artifacts", json!(left.artifacts), json!(right.artifacts)),
+        (
+            "event_count",
+            json!(left.events.len()),
+            json!(right.events.len()),
+        ),
+    ];
+    differing(fields)
+}
+
+fn differing<const N: usize>(fields: [(&str, Value, Value); N]) -> Vec<FieldDifference> {
+    fields
+        .into_iter()
+        .filter(|(_, left, right)| left != right)
+        .map(|(field, left, right)| FieldDifference {
+            field: field.to_string(),
+            left,
+            right,
+        })
+        .collect()
+}
@@ -0,0 +1,112 @@
+use crate::{SteadEvent, SteadSession};
+use serde_json::{Map, Value};
+use std::io::{BufRead, Write};
+use thiserror::Error;
+
+pub const HEADER_RECORD_KEY: &str = "record";
+pub const HEADER_RECORD: &str = "session_header";
+
+#[derive(Debug, Error)]
+pub enum JsonlError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid JSON on line {line}: {source}")]
+    Json {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("line {line} appears before the session header")]
+    MissingHeader { line: usize },
+    #[error("stream contains no session header")]
+    Empty,
+}
+
+/// Session-level fields (everything except `events`) tagged with `"record": "session_header"`.
+pub fn session_header(session: &SteadSession) -> Map<String, Value> {
+    let mut header = match serde_json::to_value(session) {
+        Ok(Value::Object(header)) => header,
+        _ => Map::new(),
+    };
+    header.remove("events");
+    let mut tagged = Map::new();
+    tagged.insert(
+        HEADER_RECORD_KEY.to_string(),
+        Value::String(HEADER_RECORD.to_string()),
+    );
+    tagged.extend(header);
+    tagged
+}
+
+pub fn is_header_line(value: &Value) -> bool {
+    value.get(HEADER_RECORD_KEY).and_then(Value::as_str) == Some(HEADER_RECORD)
+}
+
+pub fn write_session_jsonl<W: Write>(
+    session: &SteadSession,
+    mut writer: W,
+) -> Result<(), JsonlError> {
+    write_header_jsonl(session, &mut writer)?;
+    for event in &session.events {
+        write_event_jsonl(event, &mut writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_header_jsonl<W: Write>(
+    session: &SteadSession,
+    mut writer: W,
+) -> Result<(), JsonlError> {
+    serde_json::to_writer(&mut writer, &session_header(session))
+        .map_err(|source| JsonlError::Json { line: 0, source })?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+pub fn write_event_jsonl<W: Write>(event: &SteadEvent, mut writer: W) -> Result<(), JsonlError> {
+    serde_json::to_writer(&mut writer, event)
+        .map_err(|source| JsonlError::Json { line: 0, source })?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+/// Reads a header line followed by event lines. Later header lines replace the session-level
+/// fields, so updates can be appended instead of rewriting the file. Blank lines are skipped.
+pub fn read_session_jsonl<R: BufRead>(reader: R) -> Result<SteadSession, JsonlError> {
+    let mut header: Option<(usize, Map<String, Value>)> = None;
+    let mut events = Vec::new();
+    for (idx, line) in reader.lines().enumerate() {
+        let line_number = idx + 1;
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let json_err = |source| JsonlError::Json {
+            line: line_number,
+            source,
+        };
+        let value: Value = serde_json::from_str(&line).map_err(json_err)?;
+        if is_header_line(&value) {
+            let Value::Object(mut fields) = value else {
+                unreachable!("header lines are objects");
+            };
+            fields.remove(HEADER_RECORD_KEY);
+            header = Some((line_number, fields));
+            continue;
+        }
+        if header.is_none() {
+            return Err(JsonlError::MissingHeader { line: line_number });
+        }
+        events.push(serde_json::from_value::<SteadEvent>(value).map_err(json_err)?);
+    }
+    let (header_line, mut header) = header.ok_or(JsonlError::Empty)?;
+    header.insert("events".to_string(), Value::Array(Vec::new()));
+    let mut session: SteadSession =
+        serde_json::from_value(Value::Object(header)).map_err(|source| JsonlError::Json {
+            line: header_line,
+            source,
+        })?;
+    session.events = events;
+    Ok(session)
+}
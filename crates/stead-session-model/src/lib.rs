@@ -2,8 +2,12 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use std::cmp::Ordering;
+use std::collections::HashSet;
 use thiserror::Error;
 
+pub mod diff;
+pub mod jsonl;
+
 pub const SCHEMA_VERSION: &str = "0.1.0";
 pub const ADAPTER_VERSION: &str = "0.1.0";
 
@@ -119,6 +123,32 @@ pub enum EventKind {
     ArtifactRef,
 }
 
+impl EventKind {
+    pub const ALL: [EventKind; 8] = [
+        Self::MessageUser,
+        Self::MessageAssistant,
+        Self::ToolCall,
+        Self::ToolResult,
+        Self::SystemProgress,
+        Self::SystemNote,
+        Self::SessionMarker,
+        Self::ArtifactRef,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MessageUser => "message_user",
+            Self::MessageAssistant => "message_assistant",
+            Self::ToolCall => "tool_call",
+            Self::ToolResult => "tool_result",
+            Self::SystemProgress => "system_progress",
+            Self::SystemNote => "system_note",
+            Self::SessionMarker => "session_marker",
+            Self::ArtifactRef => "artifact_ref",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum EventPayload {
@@ -229,6 +259,8 @@ pub enum SteadSessionError {
         expected: u64,
         found: u64,
     },
+    #[error("fork point `{at}` does not match any event uid or sequence")]
+    ForkPointNotFound { at: String },
 }
 
 impl SteadSession {
@@ -250,6 +282,92 @@ impl SteadSession {
         }
         Ok(())
     }
+
+    pub fn fork_at(&self, at: &str, native_id: &str) -> Result<SteadSession, SteadSessionError> {
+        let index = self
+            .events
+            .iter()
+            .position(|event| event.event_uid == at)
+            .or_else(|| {
+                let sequence = at.parse::<u64>().ok()?;
+                self.events
+                    .iter()
+                    .position(|event| event.sequence == Some(sequence))
+            })
+            .ok_or_else(|| SteadSessionError::ForkPointNotFound { at: at.to_string() })?;
+        let origin = &self.events[index];
+
+        let mut fork = self.clone();
+        fork.session_uid = build_session_uid(self.source.backend, native_id);
+        fork.shared_session_uid = None;
+        fork.source.original_session_id = native_id.to_string();
+        fork.source.source_files.clear();
+        let dropped = fork.events.split_off(index + 1);
+        fork.metadata.updated_at = origin.timestamp;
+        let kept: Vec<&str> = fork.events.iter().map(|e| e.event_uid.as_str()).collect();
+        fork.artifacts
+            .retain(|artifact| kept.contains(&artifact.source_event_uid.as_str()));
+        fork.extensions.remove("native_refs");
+        fork.extensions.remove("session_uid_aliases");
+        truncate_raw_lines(
+            &mut fork.raw_vendor_payload,
+            &fork.events,
+            &dropped,
+            origin.timestamp,
+        );
+        fork.lineage = Some(SessionLineage {
+            root_session_uid: Some(
+                self.lineage
+                    .as_ref()
+                    .and_then(|lineage| lineage.root_session_uid.clone())
+                    .unwrap_or_else(|| self.session_uid.clone()),
+            ),
+            parent_session_uid: Some(self.session_uid.clone()),
+            fork_origin_event_uid: Some(origin.event_uid.clone()),
+            strategy: Some("fork".to_string()),
+        });
+        Ok(fork)
+    }
+}
+
+/// Drops the raw lines of `dropped` events unless a kept event shares them. Lines that belong to
+/// no event (session metadata and the like) are kept up to `cutoff`.
+fn truncate_raw_lines(
+    raw: &mut Value,
+    kept: &[SteadEvent],
+    dropped: &[SteadEvent],
+    cutoff: DateTime<Utc>,
+) {
+    let serialized = |events: &[SteadEvent]| -> HashSet<String> {
+        events
+            .iter()
+            .filter(|event| !event.raw_vendor_payload.is_null())
+            .filter_map(|event| serde_json::to_string(&event.raw_vendor_payload).ok())
+            .collect()
+    };
+    let kept = serialized(kept);
+    let dropped = serialized(dropped);
+    let keep = |line: &Value| {
+        let key = serde_json::to_string(line).unwrap_or_default();
+        if kept.contains(&key) {
+            return true;
+        }
+        if dropped.contains(&key) {
+            return false;
+        }
+        line.get("timestamp")
+            .and_then(Value::as_str)
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .is_none_or(|ts| ts <= cutoff)
+    };
+    if let Some(lines) = raw.get_mut("lines").and_then(Value::as_array_mut) {
+        lines.retain(keep);
+    }
+    if let Some(backends) = raw.get_mut("backend_lines").and_then(Value::as_object_mut) {
+        for lines in backends.values_mut().filter_map(Value::as_array_mut) {
+            lines.retain(keep);
+        }
+    }
 }
 
 pub fn canonical_sort_events(events: &mut [SteadEvent]) {
@@ -259,11 +377,22 @@ pub fn canonical_sort_events(events: &mut [SteadEvent]) {
     }
 }
 
+pub fn event_fingerprint(event: &SteadEvent) -> String {
+    format!(
+        "{:?}|{}|{}",
+        event.kind,
+        event.timestamp.to_rfc3339(),
+        serde_json::to_string(&event.payload).unwrap_or_default()
+    )
+}
+
 fn canonical_event_cmp(a: &SteadEvent, b: &SteadEvent) -> Ordering {
     a.timestamp
         .cmp(&b.timestamp)
         .then_with(|| stream_priority(&a.stream_id).cmp(&stream_priority(&b.stream_id)))
         .then_with(|| a.line_number.cmp(&b.line_number))
+        // Artifact refs share their tool call's position and follow it.
+        .then_with(|| (a.kind == EventKind::ArtifactRef).cmp(&(b.kind == EventKind::ArtifactRef)))
         .then_with(|| a.event_uid.cmp(&b.event_uid))
 }
 
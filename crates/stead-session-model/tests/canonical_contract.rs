@@ -154,3 +154,133 @@ fn session_can_store_shared_session_uid() {
     let value = serde_json::to_value(session).expect("serialize with shared uid");
     assert_eq!(value["shared_session_uid"], "stead:shared:123");
 }
+
+#[test]
+fn fork_at_truncates_events_and_records_lineage() {
+    let mut events = sample_events();
+    canonical_sort_events(&mut events);
+
+    let mut extensions = serde_json::Map::new();
+    extensions.insert(
+        "native_refs".to_string(),
+        serde_json::json!({"codex": {"session_id": "abc", "path": "/tmp/source.jsonl"}}),
+    );
+    let parent = SteadSession {
+        schema_version: schema_version().to_string(),
+        session_uid: build_session_uid(BackendKind::Codex, "abc"),
+        source: SessionSource::new(BackendKind::Codex, "abc", vec!["/tmp/source.jsonl".into()]),
+        metadata: SessionMetadata::new(
+            Some("fork".into()),
+            "/Users/jonas/repos/stead".into(),
+            Utc.with_ymd_and_hms(2026, 2, 17, 12, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2026, 2, 17, 12, 0, 1).unwrap(),
+        ),
+        events,
+        artifacts: vec![],
+        capabilities: serde_json::Map::new(),
+        extensions,
+        shared_session_uid: Some("stead:shared:abc".to_string()),
+        lineage: None,
+        raw_vendor_payload: serde_json::json!({"lines": [
+            {"timestamp": "2026-02-17T12:00:00Z", "type": "session_meta"},
+            {"timestamp": "2026-02-17T12:00:01Z", "type": "response_item"}
+        ]}),
+    };
+
+    let fork = parent.fork_at("0", "forked").expect("fork by sequence");
+    assert_eq!(
+        fork.session_uid,
+        build_session_uid(BackendKind::Codex, "forked")
+    );
+    assert_eq!(fork.events.len(), 1);
+    assert_eq!(fork.events[0].event_uid, "ev-1");
+    assert!(fork.shared_session_uid.is_none());
+    assert!(!fork.extensions.contains_key("native_refs"));
+    assert_eq!(
+        fork.raw_vendor_payload["lines"].as_array().unwrap().len(),
+        1
+    );
+    fork.validate().expect("fork keeps contiguous sequences");
+    let lineage = fork.lineage.as_ref().unwrap();
+    assert_eq!(
+        lineage.root_session_uid.as_deref(),
+        Some(parent.session_uid.as_str())
+    );
+    assert_eq!(
+        lineage.parent_session_uid.as_deref(),
+        Some(parent.session_uid.as_str())
+    );
+    assert_eq!(lineage.fork_origin_event_uid.as_deref(), Some("ev-1"));
+    assert_eq!(lineage.strategy.as_deref(), Some("fork"));
+
+    let grandchild = fork.fork_at("ev-1", "again").expect("fork by uid");
+    assert_eq!(
+        grandchild.lineage.unwrap().root_session_uid.as_deref(),
+        Some(parent.session_uid.as_str())
+    );
+    assert!(matches!(
+        parent.fork_at("missing", "x"),
+        Err(SteadSessionError::ForkPointNotFound { .. })
+    ));
+}
+
+#[test]
+fn fork_at_drops_raw_lines_of_later_events_with_the_same_timestamp() {
+    let at = Utc.with_ymd_and_hms(2026, 2, 17, 12, 0, 0).unwrap();
+    let line = |text: &str| serde_json::json!({"timestamp": "2026-02-17T12:00:00Z", "text": text});
+    let event = |uid: &str, line_number: u64, raw: serde_json::Value| SteadEvent {
+        event_uid: uid.to_string(),
+        stream_id: "main".to_string(),
+        line_number,
+        sequence: None,
+        timestamp: at,
+        kind: EventKind::MessageUser,
+        actor: None,
+        payload: EventPayload::text(uid),
+        raw_vendor_payload: raw,
+        extensions: serde_json::Map::new(),
+    };
+    let mut events = vec![
+        event("ev-1", 1, line("first")),
+        event("ev-2", 2, line("second")),
+        event("ev-3", 3, line("third")),
+    ];
+    canonical_sort_events(&mut events);
+    let parent = SteadSession {
+        schema_version: schema_version().to_string(),
+        session_uid: build_session_uid(BackendKind::Codex, "abc"),
+        source: SessionSource::new(BackendKind::Codex, "abc", vec![]),
+        metadata: SessionMetadata::new(None, "/repo".into(), at, at),
+        events,
+        artifacts: vec![],
+        capabilities: serde_json::Map::new(),
+        extensions: serde_json::Map::new(),
+        shared_session_uid: None,
+        lineage: None,
+        raw_vendor_payload: serde_json::json!({
+            "lines": [line("meta"), line("first"), line("second"), line("third")],
+            "backend_lines": {"claude_code": [line("first"), line("third")]}
+        }),
+    };
+
+    let fork = parent.fork_at("ev-2", "forked").unwrap();
+    assert_eq!(
+        fork.raw_vendor_payload["lines"],
+        serde_json::json!([line("meta"), line("first"), line("second")])
+    );
+    assert_eq!(
+        fork.raw_vendor_payload["backend_lines"]["claude_code"],
+        serde_json::json!([line("first")])
+    );
+}
+
+#[test]
+fn event_kind_names_match_serialization() {
+    for kind in EventKind::ALL {
+        assert_eq!(serde_json::to_value(kind).unwrap(), kind.as_str());
+        assert_eq!(
+            serde_json::from_value::<EventKind>(kind.as_str().into()).unwrap(),
+            kind
+        );
+    }
+}
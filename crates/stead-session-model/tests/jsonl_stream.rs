@@ -0,0 +1,113 @@
+use chrono::{TimeZone, Utc};
+use jsonschema::validator_for;
+use stead_session_model::jsonl::{
+    JsonlError, read_session_jsonl, write_event_jsonl, write_header_jsonl, write_session_jsonl,
+};
+use stead_session_model::{
+    BackendKind, EventActor, EventKind, EventPayload, SessionMetadata, SessionSource, SteadEvent,
+    SteadSession, build_session_uid, canonical_sort_events, schema_version,
+};
+
+fn event(uid: &str, second: u32, text: &str) -> SteadEvent {
+    SteadEvent {
+        event_uid: uid.to_string(),
+        stream_id: "main".to_string(),
+        line_number: second as u64,
+        sequence: None,
+        timestamp: Utc.with_ymd_and_hms(2026, 2, 17, 12, 0, second).unwrap(),
+        kind: EventKind::MessageUser,
+        actor: Some(EventActor::user("user")),
+        payload: EventPayload::text(text),
+        raw_vendor_payload: serde_json::json!({"line": second}),
+        extensions: serde_json::Map::new(),
+    }
+}
+
+fn session(events: Vec<SteadEvent>) -> SteadSession {
+    let mut events = events;
+    canonical_sort_events(&mut events);
+    SteadSession {
+        schema_version: schema_version().to_string(),
+        session_uid: build_session_uid(BackendKind::Codex, "abc"),
+        shared_session_uid: None,
+        source: SessionSource::new(BackendKind::Codex, "abc", vec![]),
+        metadata: SessionMetadata::new(
+            Some("jsonl".into()),
+            "/repo".into(),
+            Utc.with_ymd_and_hms(2026, 2, 17, 12, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2026, 2, 17, 12, 0, 9).unwrap(),
+        ),
+        events,
+        artifacts: vec![],
+        capabilities: serde_json::Map::new(),
+        extensions: serde_json::Map::new(),
+        lineage: None,
+        raw_vendor_payload: serde_json::json!({"lines": []}),
+    }
+}
+
+fn load_line_schema() -> serde_json::Value {
+    let schema_path = format!(
+        "{}/../../schemas/session-jsonl.v0.1.0.schema.json",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let contents = std::fs::read_to_string(schema_path).expect("schema file should exist");
+    serde_json::from_str(&contents).expect("schema must be valid JSON")
+}
+
+#[test]
+fn jsonl_round_trips_and_every_line_matches_schema() {
+    let original = session(vec![event("ev-1", 1, "hello"), event("ev-2", 2, "world")]);
+    let mut out = Vec::new();
+    write_session_jsonl(&original, &mut out).unwrap();
+
+    let text = String::from_utf8(out.clone()).unwrap();
+    let lines: Vec<serde_json::Value> = text
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["record"], "session_header");
+    assert!(lines[0].get("events").is_none());
+    assert_eq!(lines[1]["event_uid"], "ev-1");
+
+    let validator = validator_for(&load_line_schema()).expect("schema should compile");
+    for line in &lines {
+        assert!(validator.is_valid(line), "line should validate: {line}");
+    }
+    assert!(!validator.is_valid(&serde_json::json!({"record": "session_header"})));
+    assert!(!validator.is_valid(&serde_json::json!({"event_uid": "ev-x"})));
+
+    assert_eq!(read_session_jsonl(out.as_slice()).unwrap(), original);
+}
+
+#[test]
+fn appended_events_and_headers_update_the_session() {
+    let mut session = session(vec![event("ev-1", 1, "hello")]);
+    let mut out = Vec::new();
+    write_session_jsonl(&session, &mut out).unwrap();
+
+    let mut appended = event("ev-2", 2, "more");
+    appended.sequence = Some(1);
+    write_event_jsonl(&appended, &mut out).unwrap();
+    session.events.push(appended);
+    session.metadata.title = Some("renamed".to_string());
+    write_header_jsonl(&session, &mut out).unwrap();
+    out.extend_from_slice(b"\n");
+
+    assert_eq!(read_session_jsonl(out.as_slice()).unwrap(), session);
+}
+
+#[test]
+fn events_before_header_are_rejected() {
+    let mut out = Vec::new();
+    write_event_jsonl(&event("ev-1", 1, "hello"), &mut out).unwrap();
+    assert!(matches!(
+        read_session_jsonl(out.as_slice()),
+        Err(JsonlError::MissingHeader { line: 1 })
+    ));
+    assert!(matches!(
+        read_session_jsonl(&b""[..]),
+        Err(JsonlError::Empty)
+    ));
+}
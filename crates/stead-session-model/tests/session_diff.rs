@@ -0,0 +1,96 @@
+use chrono::{TimeZone, Utc};
+use stead_session_model::diff::diff_sessions;
+use stead_session_model::{
+    BackendKind, EventActor, EventKind, EventPayload, SessionMetadata, SessionSource, SteadEvent,
+    SteadSession, build_session_uid, canonical_sort_events, schema_version,
+};
+
+fn event(uid: &str, second: u32, text: &str) -> SteadEvent {
+    SteadEvent {
+        event_uid: uid.to_string(),
+        stream_id: "main".to_string(),
+        line_number: second as u64,
+        sequence: None,
+        timestamp: Utc.with_ymd_and_hms(2026, 2, 17, 12, 0, second).unwrap(),
+        kind: EventKind::MessageUser,
+        actor: Some(EventActor::user("user")),
+        payload: EventPayload::text(text),
+        raw_vendor_payload: serde_json::json!({}),
+        extensions: serde_json::Map::new(),
+    }
+}
+
+fn session(events: Vec<SteadEvent>) -> SteadSession {
+    let mut events = events;
+    canonical_sort_events(&mut events);
+    SteadSession {
+        schema_version: schema_version().to_string(),
+        session_uid: build_session_uid(BackendKind::Codex, "abc"),
+        shared_session_uid: None,
+        source: SessionSource::new(BackendKind::Codex, "abc", vec![]),
+        metadata: SessionMetadata::new(
+            Some("diff".into()),
+            "/repo".into(),
+            Utc.with_ymd_and_hms(2026, 2, 17, 12, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2026, 2, 17, 12, 0, 9).unwrap(),
+        ),
+        events,
+        artifacts: vec![],
+        capabilities: serde_json::Map::new(),
+        extensions: serde_json::Map::new(),
+        lineage: None,
+        raw_vendor_payload: serde_json::json!({}),
+    }
+}
+
+#[test]
+fn identical_sessions_have_empty_diff() {
+    let left = session(vec![event("a", 1, "one"), event("b", 2, "two")]);
+    assert!(diff_sessions(&left, &left.clone()).is_empty());
+}
+
+#[test]
+fn diff_reports_added_removed_modified_and_metadata() {
+    let left = session(vec![
+        event("a", 1, "one"),
+        event("b", 2, "two"),
+        event("c", 3, "three"),
+    ]);
+    let mut right = session(vec![
+        event("a", 1, "one"),
+        event("b", 2, "two, edited"),
+        event("renamed", 3, "three"),
+        event("d", 4, "four"),
+    ]);
+    right.metadata.title = Some("changed".into());
+
+    let diff = diff_sessions(&left, &right);
+    assert!(diff.removed.is_empty());
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].event_uid, "d");
+    assert_eq!(diff.modified.len(), 2);
+    assert_eq!(diff.modified[0].left_uid, "b");
+    assert_eq!(diff.modified[0].fields[0].field, "payload");
+    assert_eq!(diff.modified[1].right_uid, "renamed");
+    assert_eq!(diff.modified[1].fields[0].field, "event_uid");
+    let fields: Vec<&str> = diff.metadata.iter().map(|f| f.field.as_str()).collect();
+    assert_eq!(fields, vec!["metadata.title", "event_count"]);
+    assert!(diff.reordered.is_empty());
+}
+
+#[test]
+fn diff_reports_reordered_events() {
+    let left = session(vec![
+        event("a", 1, "one"),
+        event("b", 2, "two"),
+        event("c", 3, "three"),
+    ]);
+    let mut right = left.clone();
+    right.events.rotate_right(1);
+
+    let diff = diff_sessions(&left, &right);
+    assert!(diff.added.is_empty() && diff.removed.is_empty() && diff.modified.is_empty());
+    assert_eq!(diff.reordered.len(), 1);
+    assert_eq!(diff.reordered[0].event_uid, "c");
+    assert_eq!(diff.reordered[0].right_index, 0);
+}